@@ -74,7 +74,7 @@ class SpeechService:
                                   In websocket mode, the websocket connection will be established when this object is created.
             key (Optional[str]): The subscription key for the speech service.
             token (Optional[str]): The auth token for the speech service.
-            proxy (Optional[str]): The proxy for the speech service. Only http/socks5 proxy servers are supported by now.
+            proxy (Optional[str]): The proxy for the speech service. Only http/socks5 proxy servers are supported by now. Host names are resolved by socks5 proxies, socks5h:// is accepted as well.
             headers (Optional[Iterable[Tuple[str, str]]]): Additional request headers.
         """
     def speak_text(self, text: str, **options: Any) -> None:
@@ -1,16 +1,23 @@
 use std::{
+    collections::HashMap,
     error::Error,
     fmt::{self, Display, Formatter},
+    time::Duration,
 };
 
 #[cfg(feature = "binary")]
 use clap::ValueEnum;
 use phf::phf_map;
 use serde::{Deserialize, Serialize};
-use strum::{EnumIter, EnumString, IntoStaticStr};
+use strum::{EnumIter, EnumMessage, EnumString, IntoStaticStr};
+
+use crate::WordBoundary;
 
 pub type QualityMap = phf::Map<i8, AudioFormat>;
 
+/// Overrides of [`QUALITY_MAP`], from container format to quality level to audio format.
+pub type QualityOverrides = HashMap<String, HashMap<i8, AudioFormat>>;
+
 static WAV_QUALITY_MAP: QualityMap = phf_map! {
     -2i8 => AudioFormat::Riff8Khz16BitMonoPcm,
     -1i8 => AudioFormat::Riff16Khz16BitMonoPcm,
@@ -46,8 +53,11 @@ mod internal {
     use std::error::Error;
     use std::fmt::{self, Display, Formatter};
 
-    use rodio::{decoder::DecoderError, PlayError, StreamError};
+    use rodio::{buffer::SamplesBuffer, decoder::DecoderError, PlayError, StreamError};
     use rodio::{Decoder, OutputStream, Sink};
+
+    use super::{pcm_payload, AudioDataError, AudioFormat, PcmEncoding};
+
     #[allow(unused)]
     pub fn play_borrowed_audio_blocking(buffer: &[u8]) -> Result<(), AudioError> {
         play_owned_audio_blocking(buffer.to_vec())
@@ -65,6 +75,28 @@ mod internal {
         Ok(())
     }
 
+    /// Play audio in the given format and block until the playback finishes.
+    ///
+    /// RIFF PCM and compressed audio(e.g. MP3) is decoded by its header. Raw PCM, A-law and μ-law audio
+    /// has no header that a decoder understands, so its samples are decoded according to the format
+    /// and played at the sample rate of the format.
+    pub fn play_audio_blocking(format: AudioFormat, buffer: Vec<u8>) -> Result<(), AudioError> {
+        let encoding = format
+            .pcm_encoding()
+            .filter(|e| *e != PcmEncoding::I16Le || !buffer.starts_with(b"RIFF"));
+        let Some(encoding) = encoding else {
+            return play_owned_audio_blocking(buffer);
+        };
+        log::info!("Playing {encoding:?} audio... ({} bytes)", buffer.len());
+        let samples = encoding.decode(pcm_payload(format, &buffer)?);
+        let (_stream, stream_handle) = OutputStream::try_default()?;
+        let sink = Sink::try_new(&stream_handle)?;
+        sink.append(SamplesBuffer::new(1, format.sample_rate(), samples));
+        sink.sleep_until_end();
+        log::debug!("Done playing audio");
+        Ok(())
+    }
+
     #[derive(Debug)]
     #[non_exhaustive]
     /// An error that can occur when trying to play audio
@@ -116,6 +148,7 @@ mod internal {
     impl_from_for_audio_error!(StreamError, Stream);
     impl_from_for_audio_error!(DecoderError, Decoder);
     impl_from_for_audio_error!(PlayError, Decoder);
+    impl_from_for_audio_error!(AudioDataError, Decoder);
 
     #[cfg(feature = "python")]
     impl From<AudioError> for pyo3::PyErr {
@@ -145,9 +178,32 @@ pub static QUALITY_RANGE_MAP: phf::Map<&'static str, (i8, i8)> = phf_map! {
 /// All possible audio formats
 ///
 /// Some endpoints only support a subset of these formats.
+///
+/// Besides the full names, some formats can be parsed from friendly aliases:
+///
+/// | Alias           | Format                             |
+/// |-----------------|------------------------------------|
+/// | `wav`           | `riff-24khz-16bit-mono-pcm`        |
+/// | `mp3`           | `audio-24khz-96kbitrate-mono-mp3`  |
+/// | `mp3-high`      | `audio-48khz-192kbitrate-mono-mp3` |
+/// | `mp3-low`       | `audio-16khz-32kbitrate-mono-mp3`  |
+/// | `ogg`, `opus`   | `ogg-24khz-16bit-mono-opus`        |
+/// | `webm`          | `webm-24khz-16bit-mono-opus`       |
 #[cfg_attr(feature = "python", pyo3::pyclass)]
 #[derive(
-    Debug, Clone, Copy, Default, IntoStaticStr, EnumString, EnumIter, Deserialize, Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    IntoStaticStr,
+    EnumString,
+    EnumIter,
+    EnumMessage,
+    Deserialize,
+    Serialize,
 )]
 #[non_exhaustive]
 pub enum AudioFormat {
@@ -162,8 +218,8 @@ pub enum AudioFormat {
     #[strum(to_string = "audio-16khz-16bit-32kbps-mono-opus")]
     #[serde(rename = "audio-16khz-16bit-32kbps-mono-opus")]
     Audio16Khz16Bit32KbpsMonoOpus,
-    #[strum(to_string = "audio-16khz-32kbitrate-mono-mp3")]
-    #[serde(rename = "audio-16khz-32kbitrate-mono-mp3")]
+    #[strum(to_string = "audio-16khz-32kbitrate-mono-mp3", serialize = "mp3-low")]
+    #[serde(rename = "audio-16khz-32kbitrate-mono-mp3", alias = "mp3-low")]
     Audio16Khz32KBitRateMonoMp3,
     #[strum(to_string = "audio-16khz-64kbitrate-mono-mp3")]
     #[serde(rename = "audio-16khz-64kbitrate-mono-mp3")]
@@ -180,11 +236,11 @@ pub enum AudioFormat {
     #[strum(to_string = "audio-24khz-48kbitrate-mono-mp3")]
     #[serde(rename = "audio-24khz-48kbitrate-mono-mp3")]
     Audio24Khz48KBitRateMonoMp3,
-    #[strum(to_string = "audio-24khz-96kbitrate-mono-mp3")]
-    #[serde(rename = "audio-24khz-96kbitrate-mono-mp3")]
+    #[strum(to_string = "audio-24khz-96kbitrate-mono-mp3", serialize = "mp3")]
+    #[serde(rename = "audio-24khz-96kbitrate-mono-mp3", alias = "mp3")]
     Audio24Khz96KBitRateMonoMp3,
-    #[strum(to_string = "audio-48khz-192kbitrate-mono-mp3")]
-    #[serde(rename = "audio-48khz-192kbitrate-mono-mp3")]
+    #[strum(to_string = "audio-48khz-192kbitrate-mono-mp3", serialize = "mp3-high")]
+    #[serde(rename = "audio-48khz-192kbitrate-mono-mp3", alias = "mp3-high")]
     Audio48Khz192KBitRateMonoMp3,
     #[strum(to_string = "audio-48khz-96kbitrate-mono-mp3")]
     #[serde(rename = "audio-48khz-96kbitrate-mono-mp3")]
@@ -192,8 +248,12 @@ pub enum AudioFormat {
     #[strum(to_string = "ogg-16khz-16bit-mono-opus")]
     #[serde(rename = "ogg-16khz-16bit-mono-opus")]
     Ogg16Khz16BitMonoOpus,
-    #[strum(to_string = "ogg-24khz-16bit-mono-opus")]
-    #[serde(rename = "ogg-24khz-16bit-mono-opus")]
+    #[strum(
+        to_string = "ogg-24khz-16bit-mono-opus",
+        serialize = "ogg",
+        serialize = "opus"
+    )]
+    #[serde(rename = "ogg-24khz-16bit-mono-opus", alias = "ogg", alias = "opus")]
     Ogg24Khz16BitMonoOpus,
     #[strum(to_string = "ogg-48khz-16bit-mono-opus")]
     #[serde(rename = "ogg-48khz-16bit-mono-opus")]
@@ -235,8 +295,8 @@ pub enum AudioFormat {
     #[serde(rename = "riff-22050hz-16bit-mono-pcm")]
     Riff22050Hz16BitMonoPcm,
     #[default]
-    #[strum(to_string = "riff-24khz-16bit-mono-pcm")]
-    #[serde(rename = "riff-24khz-16bit-mono-pcm")]
+    #[strum(to_string = "riff-24khz-16bit-mono-pcm", serialize = "wav")]
+    #[serde(rename = "riff-24khz-16bit-mono-pcm", alias = "wav")]
     Riff24Khz16BitMonoPcm,
     #[strum(to_string = "riff-44100hz-16bit-mono-pcm")]
     #[serde(rename = "riff-44100hz-16bit-mono-pcm")]
@@ -259,8 +319,8 @@ pub enum AudioFormat {
     #[strum(to_string = "webm-24khz-16bit-24kbps-mono-opus")]
     #[serde(rename = "webm-24khz-16bit-24kbps-mono-opus")]
     Webm24Khz16Bit24KbpsMonoOpus,
-    #[strum(to_string = "webm-24khz-16bit-mono-opus")]
-    #[serde(rename = "webm-24khz-16bit-mono-opus")]
+    #[strum(to_string = "webm-24khz-16bit-mono-opus", serialize = "webm")]
+    #[serde(rename = "webm-24khz-16bit-mono-opus", alias = "webm")]
     Webm24Khz16BitMonoOpus,
 }
 
@@ -274,6 +334,32 @@ impl AudioFormat {
         quality: i8,
         use_closest: bool,
     ) -> Result<AudioFormat, AudioFormatParseError> {
+        Self::from_container_and_quality_with_overrides(
+            container,
+            quality,
+            use_closest,
+            &QualityOverrides::new(),
+        )
+    }
+
+    /// Like [`AudioFormat::from_container_and_quality`],
+    /// but the quality levels in `overrides` take precedence over the built-in [`QUALITY_MAP`].
+    ///
+    /// Overrides may also add quality levels that are not in the built-in map.
+    /// The closest quality level is still selected from the built-in range.
+    pub fn from_container_and_quality_with_overrides(
+        container: &str,
+        quality: i8,
+        use_closest: bool,
+        overrides: &QualityOverrides,
+    ) -> Result<AudioFormat, AudioFormatParseError> {
+        if let Some(format) = overrides
+            .get(container)
+            .and_then(|map| map.get(&quality))
+            .copied()
+        {
+            return Ok(format);
+        }
         let map = QUALITY_MAP
             .get(container)
             .ok_or_else(|| AudioFormatParseError {
@@ -284,7 +370,12 @@ impl AudioFormat {
         } else if use_closest {
             let (min, max) = QUALITY_RANGE_MAP.get(container).unwrap();
             let closest = if quality < *min { *min } else { *max };
-            Ok(*map.get(&closest).unwrap())
+            Ok(overrides
+                .get(container)
+                .and_then(|map| map.get(&closest))
+                .or_else(|| map.get(&closest))
+                .copied()
+                .unwrap())
         } else {
             Err(AudioFormatParseError {
                 kind: AudioFormatParseErrorKind::InvalidQuality {
@@ -294,6 +385,766 @@ impl AudioFormat {
             })
         }
     }
+
+    /// Suggest the name(or alias) of a format that is closest to a misspelled one,
+    /// e.g. `riff-24khz-16bit-mono-pcm` for `riff-24khz-16bit-mono`.
+    ///
+    /// Names are compared by edit distance, and nothing is suggested if even the closest name is too different.
+    pub fn suggest_name(name: &str) -> Option<&'static str> {
+        use strum::IntoEnumIterator;
+        let max_distance = (name.chars().count() / 3).max(1);
+        AudioFormat::iter()
+            .flat_map(|format| format.get_serializations().iter().copied())
+            .map(|candidate| (crate::utils::edit_distance(name, candidate), candidate))
+            .min_by_key(|(distance, _)| *distance)
+            .filter(|(distance, _)| *distance <= max_distance)
+            .map(|(_, candidate)| candidate)
+    }
+
+    fn name_parts(&self) -> impl Iterator<Item = &'static str> {
+        Into::<&'static str>::into(*self).split('-')
+    }
+
+    /// The sample rate of this format in Hz, parsed from the format name.
+    pub fn sample_rate(&self) -> u32 {
+        self.name_parts()
+            .find_map(|part| {
+                if let Some(khz) = part.strip_suffix("khz") {
+                    khz.parse::<u32>().ok().map(|x| x * 1000)
+                } else {
+                    part.strip_suffix("hz").and_then(|hz| hz.parse().ok())
+                }
+            })
+            .expect("every audio format name contains its sample rate")
+    }
+
+    /// The bit rate of this format in bits per second, if it is a constant bit rate format.
+    pub fn bit_rate(&self) -> Option<u32> {
+        self.name_parts().find_map(|part| {
+            part.strip_suffix("kbitrate")
+                .or_else(|| part.strip_suffix("kbps"))
+                .and_then(|k| k.parse::<u32>().ok())
+                .map(|k| k * 1000)
+        })
+    }
+
+    /// The MIME type of audio in this format, e.g. for the `Content-Type` header of an HTTP response.
+    ///
+    /// Raw streams without a widely supported media type(e.g. raw PCM or raw Opus frames)
+    /// are reported as `application/octet-stream`.
+    pub fn content_type(&self) -> &'static str {
+        let name: &str = self.into();
+        if name.starts_with("riff-") {
+            "audio/wav"
+        } else if name.ends_with("-mp3") {
+            "audio/mpeg"
+        } else if name.starts_with("ogg-") {
+            "audio/ogg"
+        } else if name.starts_with("webm-") {
+            "audio/webm"
+        } else if name.starts_with("amr-wb-") {
+            "audio/AMR-WB"
+        } else if name == "raw-8khz-8bit-mono-mulaw" {
+            "audio/basic"
+        } else if name == "raw-8khz-8bit-mono-alaw" {
+            "audio/PCMA"
+        } else {
+            "application/octet-stream"
+        }
+    }
+
+    /// The encoding of the samples of raw and RIFF formats, to configure audio libraries
+    /// that consume the bytes directly. For RIFF formats, it describes the payload after the header.
+    ///
+    /// `None` is returned for compressed formats, including the raw TrueSilk formats.
+    pub fn pcm_encoding(&self) -> Option<PcmEncoding> {
+        let name: &str = self.into();
+        if !(name.starts_with("raw-") || name.starts_with("riff-")) {
+            return None;
+        }
+        match name.rsplit('-').next() {
+            Some("pcm") => Some(PcmEncoding::I16Le),
+            Some("alaw") => Some(PcmEncoding::ALaw),
+            Some("mulaw") => Some(PcmEncoding::MuLaw),
+            _ => None,
+        }
+    }
+
+    /// Bytes per second of uncompressed PCM data, for raw and RIFF formats.
+    pub(crate) fn pcm_byte_rate(&self) -> Option<u32> {
+        let name: &str = self.into();
+        if !(name.starts_with("raw-") || name.starts_with("riff-")) || name.ends_with("truesilk") {
+            return None;
+        }
+        let bits = self
+            .name_parts()
+            .find_map(|part| part.strip_suffix("bit").and_then(|b| b.parse::<u32>().ok()))?;
+        Some(self.sample_rate() * bits / 8)
+    }
+
+    /// Compute the duration of the given audio data in this format.
+    ///
+    /// The duration is computed from the PCM data length for raw/RIFF formats,
+    /// from the last granule position for OGG and from the bit rate for other constant bit rate formats.
+    /// `None` is returned if the duration can not be determined.
+    pub fn audio_duration(&self, data: &[u8]) -> Option<Duration> {
+        let name: &str = self.into();
+        if name.starts_with("riff-") {
+            let (fmt, payload) = riff_chunks(data)?;
+            let byte_rate = u32::from_le_bytes(fmt.get(8..12)?.try_into().ok()?);
+            (byte_rate != 0)
+                .then(|| Duration::from_secs_f64(payload.len() as f64 / byte_rate as f64))
+        } else if name.starts_with("ogg-") {
+            ogg_opus_duration(data)
+        } else if let Some(byte_rate) = self.pcm_byte_rate() {
+            Some(Duration::from_secs_f64(
+                data.len() as f64 / byte_rate as f64,
+            ))
+        } else {
+            self.bit_rate()
+                .map(|rate| Duration::from_secs_f64(data.len() as f64 * 8.0 / rate as f64))
+        }
+    }
+}
+
+/// The encoding of the samples of uncompressed audio, see [`AudioFormat::pcm_encoding`].
+///
+/// All formats are mono, so a frame is a single sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, IntoStaticStr, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum PcmEncoding {
+    /// Signed 16 bit little endian linear PCM(`s16le`)
+    I16Le,
+    /// 8 bit G.711 A-law
+    ALaw,
+    /// 8 bit G.711 μ-law
+    MuLaw,
+}
+
+impl PcmEncoding {
+    /// The size of a sample in bytes
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            PcmEncoding::I16Le => 2,
+            PcmEncoding::ALaw | PcmEncoding::MuLaw => 1,
+        }
+    }
+
+    /// Decode samples in this encoding into signed 16 bit linear PCM samples.
+    /// A trailing incomplete sample is ignored.
+    pub fn decode(&self, data: &[u8]) -> Vec<i16> {
+        match self {
+            PcmEncoding::I16Le => data
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]))
+                .collect(),
+            PcmEncoding::ALaw => data.iter().copied().map(alaw_to_linear).collect(),
+            PcmEncoding::MuLaw => data.iter().copied().map(mulaw_to_linear).collect(),
+        }
+    }
+}
+
+/// Expand a G.711 A-law sample
+fn alaw_to_linear(sample: u8) -> i16 {
+    let sample = sample ^ 0x55;
+    let exponent = (sample >> 4) & 0x07;
+    let mantissa = (sample & 0x0f) as i16;
+    let magnitude = match exponent {
+        0 => (mantissa << 4) + 8,
+        _ => ((mantissa << 4) + 0x108) << (exponent - 1),
+    };
+    if sample & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+/// Expand a G.711 μ-law sample
+fn mulaw_to_linear(sample: u8) -> i16 {
+    let sample = !sample;
+    let exponent = (sample >> 4) & 0x07;
+    let mantissa = (sample & 0x0f) as i16;
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+    if sample & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Container formats that can be recognized by [`sniff_audio`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AudioContainer {
+    /// RIFF/WAV
+    Riff,
+    /// Ogg, usually with Opus inside
+    Ogg,
+    /// WebM(Matroska), usually with Opus inside
+    WebM,
+    /// MPEG audio, possibly starting with an ID3v2 tag
+    Mp3,
+    /// Raw streams and anything else
+    Unknown,
+}
+
+/// The properties of audio data inferred from its header by [`sniff_audio`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SniffedAudio {
+    pub container: AudioContainer,
+    /// The sample rate in Hz, if it is found in the header.
+    /// For Ogg Opus, this is the sample rate of the original input.
+    pub sample_rate: Option<u32>,
+}
+
+/// Infer the container and sample rate of audio data from its magic bytes and header.
+///
+/// This is useful when the data is not in a known [`AudioFormat`], e.g. when a custom format is requested from the service,
+/// so that it can still be wrapped or decoded properly.
+/// Raw streams have no header, so they are reported as [`AudioContainer::Unknown`].
+pub fn sniff_audio(data: &[u8]) -> SniffedAudio {
+    let (container, sample_rate) = if data.starts_with(b"RIFF") {
+        let sample_rate = riff_chunks(data)
+            .and_then(|(fmt, _)| Some(u32::from_le_bytes(fmt.get(4..8)?.try_into().ok()?)));
+        (AudioContainer::Riff, sample_rate)
+    } else if data.starts_with(b"OggS") {
+        (AudioContainer::Ogg, ogg_sample_rate(data))
+    } else if data.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]) {
+        (AudioContainer::WebM, None)
+    } else if let Some(sample_rate) = mp3_sample_rate(data) {
+        (AudioContainer::Mp3, Some(sample_rate))
+    } else {
+        (AudioContainer::Unknown, None)
+    };
+    SniffedAudio {
+        container,
+        sample_rate,
+    }
+}
+
+/// Read the sample rate from the identification header in the first page of an Ogg Opus/Vorbis stream.
+fn ogg_sample_rate(data: &[u8]) -> Option<u32> {
+    let segments = *data.get(26)? as usize;
+    let packet = data.get(27 + segments..)?;
+    if packet.starts_with(b"OpusHead") || packet.starts_with(b"\x01vorbis") {
+        Some(u32::from_le_bytes(packet.get(12..16)?.try_into().ok()?))
+    } else {
+        None
+    }
+}
+
+/// Skip the ID3v2 tag at the start of MPEG audio data, if any.
+fn skip_id3v2(data: &[u8]) -> Option<&[u8]> {
+    if data.starts_with(b"ID3") {
+        // The tag size is a 28 bit syncsafe integer
+        let size = data
+            .get(6..10)?
+            .iter()
+            .fold(0usize, |size, &b| (size << 7) | (b & 0x7f) as usize);
+        data.get(10 + size..)
+    } else {
+        Some(data)
+    }
+}
+
+/// Read the sample rate from the first MPEG audio frame header, skipping an ID3v2 tag.
+fn mp3_sample_rate(data: &[u8]) -> Option<u32> {
+    let header = skip_id3v2(data)?.get(0..4)?;
+    if header[0] != 0xff || header[1] & 0xe0 != 0xe0 || header[1] & 0x06 == 0 {
+        return None;
+    }
+    let rates = match (header[1] >> 3) & 0x03 {
+        0b11 => [44100, 48000, 32000], // MPEG 1
+        0b10 => [22050, 24000, 16000], // MPEG 2
+        0b00 => [11025, 12000, 8000],  // MPEG 2.5
+        _ => return None,
+    };
+    rates.get(((header[2] >> 2) & 0x03) as usize).copied()
+}
+
+/// The length in bytes of the MPEG Layer III frame starting with `header`, including the header.
+///
+/// `None` if `header` is not a valid Layer III frame header or the frame is in free format.
+fn mp3_frame_len(header: &[u8]) -> Option<usize> {
+    const MPEG1_BIT_RATES: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const MPEG2_BIT_RATES: [u32; 15] =
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    let header = header.get(0..4)?;
+    if header[1] & 0x06 != 0b010 {
+        return None;
+    }
+    let sample_rate = mp3_sample_rate(header)?;
+    let mpeg1 = (header[1] >> 3) & 0x03 == 0b11;
+    let bit_rates = if mpeg1 {
+        MPEG1_BIT_RATES
+    } else {
+        MPEG2_BIT_RATES
+    };
+    let bit_rate = *bit_rates
+        .get((header[2] >> 4) as usize)
+        .filter(|&&r| r != 0)?
+        * 1000;
+    let samples_per_frame = if mpeg1 { 1152 } else { 576 };
+    let padding = ((header[2] >> 1) & 0x01) as u32;
+    Some((samples_per_frame / 8 * bit_rate / sample_rate + padding) as usize)
+}
+
+/// Split RIFF/WAV data into the payload of its `fmt ` chunk and its PCM payload.
+fn riff_chunks(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    if data.get(0..4)? != b"RIFF" || data.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut fmt = None;
+    let mut offset = 12;
+    while let Some(header) = data.get(offset..offset + 8) {
+        let size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        let body = offset + 8;
+        match &header[0..4] {
+            // The declared size of the data chunk may be bogus for streamed audio.
+            b"data" => return Some((fmt?, &data[body..])),
+            b"fmt " => fmt = Some(data.get(body..body + size)?),
+            _ => {}
+        }
+        offset = body + size + size % 2;
+    }
+    None
+}
+
+/// The offset of the samples in RIFF/WAV data, or `None` if the header up to the `data` chunk is incomplete.
+#[cfg(feature = "websocket-synthesizer")]
+pub(crate) fn riff_data_offset(data: &[u8]) -> Option<usize> {
+    riff_chunks(data).map(|(_, payload)| data.len() - payload.len())
+}
+
+/// Compute the duration of Ogg Opus data from its pre-skip and last granule position.
+fn ogg_opus_duration(data: &[u8]) -> Option<Duration> {
+    const OPUS_SAMPLE_RATE: f64 = 48000.0;
+    let mut offset = 0;
+    let mut pre_skip = None;
+    let mut granule = None;
+    while data.get(offset..offset + 4) == Some(b"OggS") {
+        let header = data.get(offset..offset + 27)?;
+        let segments = header[26] as usize;
+        let lacing = data.get(offset + 27..offset + 27 + segments)?;
+        let body = offset + 27 + segments;
+        let body_len = lacing.iter().map(|&x| x as usize).sum::<usize>();
+        if pre_skip.is_none() {
+            let packet = data.get(body..body + body_len)?;
+            if packet.starts_with(b"OpusHead") {
+                pre_skip = Some(u16::from_le_bytes(packet.get(10..12)?.try_into().ok()?));
+            }
+        }
+        let position = u64::from_le_bytes(header[6..14].try_into().unwrap());
+        if position != u64::MAX {
+            granule = Some(position);
+        }
+        offset = body + body_len;
+    }
+    let samples = granule?.saturating_sub(pre_skip? as u64);
+    Some(Duration::from_secs_f64(samples as f64 / OPUS_SAMPLE_RATE))
+}
+
+/// Concatenate audio segments of the same format.
+///
+/// For RIFF formats, the headers of the segments are merged into one and the sizes are fixed.
+/// For Ogg formats, the segments are merged into one logical stream, see [`concat_ogg`].
+/// WebM segments can not be concatenated without remuxing, so more than one of them is an
+/// [`AudioDataErrorKind::UnsupportedFormat`] error.
+/// For MP3 formats, the segments are joined at frame boundaries, see [`concat_mp3`].
+/// Other formats(raw PCM, A-law etc.) are simply appended.
+pub fn concat_audio(
+    format: AudioFormat,
+    segments: &[impl AsRef<[u8]>],
+) -> Result<Vec<u8>, AudioDataError> {
+    let name: &str = format.into();
+    if segments.len() > 1 && name.starts_with("webm-") {
+        return Err(AudioDataError {
+            kind: AudioDataErrorKind::UnsupportedFormat(format),
+        });
+    }
+    if name.starts_with("ogg-") {
+        return concat_ogg(segments);
+    }
+    if name.contains("-mp3") {
+        return concat_mp3(segments);
+    }
+    if !name.starts_with("riff-") {
+        return Ok(segments.iter().flat_map(|s| s.as_ref()).copied().collect());
+    }
+    let mut result = Vec::new();
+    for segment in segments {
+        let segment = segment.as_ref();
+        let (_, payload) = riff_chunks(segment).ok_or(AudioDataError {
+            kind: AudioDataErrorKind::InvalidData,
+        })?;
+        if result.is_empty() {
+            result.extend_from_slice(segment);
+        } else {
+            result.extend_from_slice(payload);
+        }
+    }
+    if !result.is_empty() {
+        fix_riff_sizes(&mut result);
+    }
+    Ok(result)
+}
+
+/// The CRC-32 of Ogg pages(polynomial `0x04c11db7`, no reflection, zero initial value and no final xor)
+fn ogg_crc(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| {
+            if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Merge Ogg Opus segments into one logical stream.
+///
+/// The header pages(`OpusHead` and `OpusTags`, whose granule position is 0) of all segments but the first are dropped,
+/// and the audio pages are renumbered into the stream of the first segment, with their granule positions shifted
+/// so that they continue after the previous segment. The pre-skip of a later segment is taken off its shift,
+/// since only the pre-skip of the first segment is discarded by decoders, so that the duration of the result
+/// is the sum of the durations of the segments. Only the first page keeps the beginning-of-stream flag
+/// and only the last page keeps the end-of-stream flag.
+fn concat_ogg(segments: &[impl AsRef<[u8]>]) -> Result<Vec<u8>, AudioDataError> {
+    const BOS: u8 = 0x02;
+    const EOS: u8 = 0x04;
+    let invalid = || AudioDataError {
+        kind: AudioDataErrorKind::InvalidData,
+    };
+    let mut result = Vec::new();
+    let mut serial = None;
+    let mut sequence = 0u32;
+    let mut granule_offset = 0u64;
+    let mut last_page = None;
+    for (index, segment) in segments.iter().enumerate() {
+        let data = segment.as_ref();
+        let mut offset = 0;
+        let mut last_granule = 0;
+        while offset < data.len() {
+            let header = data.get(offset..offset + 27).ok_or_else(invalid)?;
+            if &header[..4] != b"OggS" {
+                return Err(invalid());
+            }
+            let lacing = data
+                .get(offset + 27..offset + 27 + header[26] as usize)
+                .ok_or_else(invalid)?;
+            let end =
+                offset + 27 + lacing.len() + lacing.iter().map(|&x| x as usize).sum::<usize>();
+            let page = data.get(offset..end).ok_or_else(invalid)?;
+            offset = end;
+            let granule = u64::from_le_bytes(page[6..14].try_into().unwrap());
+            if index > 0 && granule == 0 {
+                // A header page of a later segment
+                let packet = &page[27 + lacing.len()..];
+                if let Some(pre_skip) = packet
+                    .strip_prefix(b"OpusHead")
+                    .and_then(|head| head.get(2..4))
+                {
+                    let pre_skip = u16::from_le_bytes(pre_skip.try_into().unwrap());
+                    granule_offset = granule_offset.saturating_sub(pre_skip as u64);
+                }
+                continue;
+            }
+            let mut page = page.to_vec();
+            let serial: [u8; 4] = *serial.get_or_insert_with(|| page[14..18].try_into().unwrap());
+            page[14..18].copy_from_slice(&serial);
+            page[18..22].copy_from_slice(&sequence.to_le_bytes());
+            sequence += 1;
+            if granule != u64::MAX {
+                last_granule = granule;
+                page[6..14].copy_from_slice(&(granule + granule_offset).to_le_bytes());
+            }
+            if index > 0 {
+                page[5] &= !BOS;
+            }
+            page[5] &= !EOS;
+            page[22..26].fill(0);
+            let crc = ogg_crc(&page);
+            page[22..26].copy_from_slice(&crc.to_le_bytes());
+            last_page = Some(result.len());
+            result.extend_from_slice(&page);
+        }
+        granule_offset += last_granule;
+    }
+    if let Some(start) = last_page {
+        result[start + 5] |= EOS;
+        result[start + 22..start + 26].fill(0);
+        let crc = ogg_crc(&result[start..]);
+        result[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+    }
+    Ok(result)
+}
+
+/// Join MPEG Layer III segments at frame boundaries.
+///
+/// The ID3v2 tag of the first segment is kept and those of the later segments are dropped,
+/// so that no tag ends up in the middle of the stream. Every segment must start with a frame header
+/// and consist of whole frames, otherwise it is [`AudioDataErrorKind::InvalidData`].
+/// An incomplete frame at the end of a segment(e.g. from a truncated stream) is dropped
+/// because it would corrupt the frame following it.
+fn concat_mp3(segments: &[impl AsRef<[u8]>]) -> Result<Vec<u8>, AudioDataError> {
+    let invalid = || AudioDataError {
+        kind: AudioDataErrorKind::InvalidData,
+    };
+    let mut result = Vec::new();
+    for (index, segment) in segments.iter().enumerate() {
+        let data = segment.as_ref();
+        let frames = skip_id3v2(data).ok_or_else(invalid)?;
+        if index == 0 {
+            result.extend_from_slice(&data[..data.len() - frames.len()]);
+        }
+        let mut offset = 0;
+        while offset < frames.len() {
+            let rest = &frames[offset..];
+            if rest.len() == 128 && rest.starts_with(b"TAG") {
+                // An ID3v1 tag at the end
+                break;
+            }
+            let len = mp3_frame_len(rest).ok_or_else(invalid)?;
+            if len > rest.len() {
+                log::debug!("Dropping an incomplete mp3 frame at the end of segment {index}");
+                break;
+            }
+            offset += len;
+        }
+        result.extend_from_slice(&frames[..offset]);
+    }
+    Ok(result)
+}
+
+/// Fix the sizes in the RIFF header after the data chunk is modified.
+fn fix_riff_sizes(data: &mut [u8]) {
+    let payload_len = riff_chunks(data).unwrap().1.len();
+    let data_size = u32::try_from(payload_len).unwrap_or(u32::MAX);
+    let riff_size = u32::try_from(data.len() - 8).unwrap_or(u32::MAX);
+    let data_size_offset = data.len() - payload_len - 4;
+    data[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    data[data_size_offset..data_size_offset + 4].copy_from_slice(&data_size.to_le_bytes());
+}
+
+/// Concatenate audio segments of the same format with a linear crossfade of the given duration between them.
+///
+/// The end of each segment overlaps with the start of the next one, so the result is shorter than
+/// the plain concatenation by `crossfade` for each join. The crossfade is shortened if a segment is shorter than it.
+///
+/// Only 16 bit PCM formats(raw or RIFF) are supported because the samples need to be mixed.
+/// Compressed formats like MP3 or Opus would need to be decoded and encoded again,
+/// use [`concat_audio`] for them instead.
+pub fn concat_audio_with_crossfade(
+    format: AudioFormat,
+    segments: &[impl AsRef<[u8]>],
+    crossfade: Duration,
+) -> Result<Vec<u8>, AudioDataError> {
+    let name: &str = format.into();
+    if !name.ends_with("-16bit-mono-pcm") {
+        return Err(AudioDataError {
+            kind: AudioDataErrorKind::UnsupportedFormat(format),
+        });
+    }
+    let is_riff = name.starts_with("riff-");
+    let fade_len = (crossfade.as_secs_f64() * format.sample_rate() as f64).round() as usize;
+    let mut header: Option<&[u8]> = None;
+    let mut samples: Vec<i16> = Vec::new();
+    for segment in segments {
+        let segment = segment.as_ref();
+        let payload = if is_riff {
+            let (_, payload) = riff_chunks(segment).ok_or(AudioDataError {
+                kind: AudioDataErrorKind::InvalidData,
+            })?;
+            header.get_or_insert(&segment[..segment.len() - payload.len()]);
+            payload
+        } else {
+            segment
+        };
+        let next = payload
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]));
+        let overlap = fade_len.min(samples.len()).min(payload.len() / 2);
+        let start = samples.len() - overlap;
+        for (i, (sample, next)) in samples[start..].iter_mut().zip(next.clone()).enumerate() {
+            let t = (i + 1) as f64 / (overlap + 1) as f64;
+            *sample = (*sample as f64 * (1.0 - t) + next as f64 * t).round() as i16;
+        }
+        samples.extend(next.skip(overlap));
+    }
+    let mut result = header.map(<[u8]>::to_vec).unwrap_or_default();
+    result.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+    if header.is_some() {
+        fix_riff_sizes(&mut result);
+    }
+    Ok(result)
+}
+
+/// Uncompressed audio that can be cut at sample boundaries
+struct PcmAudio<'a> {
+    /// The RIFF header, empty for raw formats
+    header: &'a [u8],
+    payload: &'a [u8],
+    sample_rate: u32,
+    frame_size: usize,
+}
+
+impl<'a> PcmAudio<'a> {
+    fn new(format: AudioFormat, audio: &'a [u8]) -> Result<Self, AudioDataError> {
+        let byte_rate = format.pcm_byte_rate().ok_or(AudioDataError {
+            kind: AudioDataErrorKind::UnsupportedFormat(format),
+        })?;
+        let sample_rate = format.sample_rate();
+        let name: &str = format.into();
+        let (header, payload) = if name.starts_with("riff-") {
+            let (_, payload) = riff_chunks(audio).ok_or(AudioDataError {
+                kind: AudioDataErrorKind::InvalidData,
+            })?;
+            (&audio[..audio.len() - payload.len()], payload)
+        } else {
+            (&audio[..0], audio)
+        };
+        Ok(Self {
+            header,
+            payload,
+            sample_rate,
+            frame_size: (byte_rate / sample_rate) as usize,
+        })
+    }
+
+    /// The byte position in the payload of the sample nearest to the offset
+    fn position(&self, offset: Duration) -> usize {
+        let sample = (offset.as_secs_f64() * self.sample_rate as f64).round() as usize;
+        (sample * self.frame_size).min(self.payload.len())
+    }
+
+    /// Copy a part of the payload into a segment with its own header
+    fn segment(&self, start: usize, end: usize) -> Vec<u8> {
+        let mut segment = self.header.to_vec();
+        segment.extend_from_slice(&self.payload[start..end.max(start)]);
+        if !self.header.is_empty() {
+            fix_riff_sizes(&mut segment);
+        }
+        segment
+    }
+}
+
+/// Truncate the audio to at most `max_bytes` and return whether it was truncated.
+///
+/// Raw and RIFF PCM audio is cut at a sample boundary and the sizes in the RIFF header are fixed
+/// to the truncated length. The RIFF header is always kept, even if it is longer than `max_bytes`.
+/// Other formats are simply cut, which may leave a partial frame at the end.
+pub fn truncate_audio(format: AudioFormat, audio: &mut Vec<u8>, max_bytes: usize) -> bool {
+    if audio.len() <= max_bytes {
+        return false;
+    }
+    let Ok(pcm) = PcmAudio::new(format, audio) else {
+        audio.truncate(max_bytes);
+        return true;
+    };
+    let header_len = pcm.header.len();
+    let payload_len = max_bytes.saturating_sub(header_len) / pcm.frame_size * pcm.frame_size;
+    audio.truncate(header_len + payload_len);
+    if header_len > 0 {
+        fix_riff_sizes(audio);
+    }
+    true
+}
+
+/// Build a RIFF/WAV header for `data_len` bytes of samples in the given uncompressed format,
+/// e.g. to make raw PCM playable. The sample rate and bit depth are taken from the format.
+///
+/// A-law and μ-law samples get their respective WAV format tags. Sizes that do not fit in the header are capped.
+pub fn wav_header(format: AudioFormat, data_len: usize) -> Result<[u8; 44], AudioDataError> {
+    let unsupported = || AudioDataError {
+        kind: AudioDataErrorKind::UnsupportedFormat(format),
+    };
+    let format_tag: u16 = match format.pcm_encoding().ok_or_else(unsupported)? {
+        PcmEncoding::I16Le => 1,
+        PcmEncoding::ALaw => 6,
+        PcmEncoding::MuLaw => 7,
+    };
+    let byte_rate = format.pcm_byte_rate().ok_or_else(unsupported)?;
+    let sample_rate = format.sample_rate();
+    // All formats are mono, so a block is a single sample
+    let block_align = (byte_rate / sample_rate) as u16;
+    let data_size = u32::try_from(data_len).unwrap_or(u32::MAX);
+    let mut header = [0; 44];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&data_size.saturating_add(36).to_le_bytes());
+    header[8..16].copy_from_slice(b"WAVEfmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&format_tag.to_le_bytes());
+    header[22..24].copy_from_slice(&1u16.to_le_bytes());
+    header[24..28].copy_from_slice(&sample_rate.to_le_bytes());
+    header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    header[32..34].copy_from_slice(&block_align.to_le_bytes());
+    header[34..36].copy_from_slice(&(block_align * 8).to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_size.to_le_bytes());
+    Ok(header)
+}
+
+/// Get the PCM samples of uncompressed audio(raw or RIFF PCM), without the RIFF header.
+pub fn pcm_payload(format: AudioFormat, audio: &[u8]) -> Result<&[u8], AudioDataError> {
+    PcmAudio::new(format, audio).map(|pcm| pcm.payload)
+}
+
+/// Split the audio of a whole synthesis into one segment per sentence, using the sentence boundaries
+/// reported by the service(see [`crate::parse_sentence_boundaries`]).
+///
+/// Each segment starts at the offset of its sentence and ends where the next sentence starts,
+/// so the pauses between sentences are kept and the segments add up to the whole audio.
+/// The cuts are aligned to samples using the sample rate of the format.
+///
+/// Only uncompressed formats(raw or RIFF PCM) are supported because compressed audio can not be cut
+/// at arbitrary positions. For RIFF formats, every segment gets its own header.
+pub fn split_audio_by_sentences(
+    format: AudioFormat,
+    audio: &[u8],
+    sentences: &[WordBoundary],
+) -> Result<Vec<(String, Vec<u8>)>, AudioDataError> {
+    let audio = PcmAudio::new(format, audio)?;
+    let mut starts: Vec<usize> = sentences.iter().map(|s| audio.position(s.offset)).collect();
+    if let Some(first) = starts.first_mut() {
+        // Keep the leading silence in the first sentence
+        *first = 0;
+    }
+    let ends = starts.iter().skip(1).copied().chain([audio.payload.len()]);
+    Ok(sentences
+        .iter()
+        .zip(starts.iter().zip(ends))
+        .map(|(sentence, (&start, end))| (sentence.text.clone(), audio.segment(start, end)))
+        .collect())
+}
+
+/// Split the audio of a whole synthesis into one segment per word(or punctuation), using the word boundaries
+/// reported by the service(see [`crate::parse_word_boundaries`]).
+///
+/// Each segment covers exactly the offset and duration of its boundary, so the silence between words
+/// is not part of any segment. Both ends are rounded to the nearest sample using the sample rate of the format,
+/// so adjacent segments never overlap by more than one sample. Boundaries past the end of the audio get empty segments.
+///
+/// Only uncompressed formats(raw or RIFF PCM) are supported because compressed audio can not be cut
+/// at arbitrary positions. For RIFF formats, every segment gets its own header.
+pub fn split_audio_by_words(
+    format: AudioFormat,
+    audio: &[u8],
+    words: &[WordBoundary],
+) -> Result<Vec<(WordBoundary, Vec<u8>)>, AudioDataError> {
+    let audio = PcmAudio::new(format, audio)?;
+    Ok(words
+        .iter()
+        .map(|word| {
+            let segment = audio.segment(audio.position(word.offset), audio.position(word.end()));
+            (word.clone(), segment)
+        })
+        .collect())
 }
 
 #[cfg(feature = "python")]
@@ -323,7 +1174,14 @@ impl ValueEnum for AudioFormat {
     }
 
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
-        Some(clap::builder::PossibleValue::new(Into::<&str>::into(self)))
+        let name: &str = self.into();
+        Some(
+            clap::builder::PossibleValue::new(name).aliases(
+                self.get_serializations()
+                    .iter()
+                    .filter(|alias| **alias != name),
+            ),
+        )
     }
 }
 
@@ -368,6 +1226,54 @@ pub enum AudioFormatParseErrorKind {
     InvalidQuality { container: String, quality: i8 },
 }
 
+#[derive(Debug)]
+#[non_exhaustive]
+/// An error that can occur while processing audio data
+pub struct AudioDataError {
+    pub kind: AudioDataErrorKind,
+}
+
+impl Display for AudioDataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "audio data error: ")?;
+        match &self.kind {
+            AudioDataErrorKind::InvalidData => write!(f, "the audio data is malformed"),
+            AudioDataErrorKind::UnknownDuration(format) => write!(
+                f,
+                "cannot determine the duration of audio in format {}",
+                Into::<&str>::into(format)
+            ),
+            AudioDataErrorKind::FormatMismatch => {
+                write!(f, "the audio segments are not in the same format")
+            }
+            AudioDataErrorKind::UnsupportedFormat(format) => write!(
+                f,
+                "audio format {} is not supported here",
+                Into::<&str>::into(format)
+            ),
+        }
+    }
+}
+
+impl Error for AudioDataError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum AudioDataErrorKind {
+    /// The audio data is malformed
+    InvalidData,
+    /// The duration of audio in this format can not be determined
+    UnknownDuration(AudioFormat),
+    /// The audio segments are not in the same format
+    FormatMismatch,
+    /// The operation does not support audio in this format
+    UnsupportedFormat(AudioFormat),
+}
+
 #[cfg(feature = "python")]
 pub(crate) fn register_python_items(
     _py: pyo3::Python<'_>,
@@ -376,3 +1282,196 @@ pub(crate) fn register_python_items(
     m.add_class::<AudioFormat>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_the_closest_format_name() {
+        assert_eq!(
+            AudioFormat::suggest_name("riff-24khz-16bit-mono"),
+            Some("riff-24khz-16bit-mono-pcm")
+        );
+        assert_eq!(
+            AudioFormat::suggest_name("audio-24khz-48kbitrate-mono-mp4"),
+            Some("audio-24khz-48kbitrate-mono-mp3")
+        );
+        assert_eq!(AudioFormat::suggest_name("flac"), None);
+    }
+
+    #[test]
+    fn builds_wav_headers() {
+        let pcm = [
+            b"RIFF\xa4\x25\0\0WAVEfmt ".as_slice(),
+            // PCM, mono, 24000Hz, 48000B/s, block align 2, 16 bits
+            b"\x10\0\0\0\x01\0\x01\0\xc0\x5d\0\0\x80\xbb\0\0\x02\0\x10\0",
+            b"data\x80\x25\0\0",
+        ]
+        .concat();
+        assert_eq!(
+            wav_header(AudioFormat::Raw24Khz16BitMonoPcm, 9600).unwrap(),
+            pcm.as_slice()
+        );
+        let mulaw = [
+            b"RIFF\x64\x1f\0\0WAVEfmt ".as_slice(),
+            // μ-law, mono, 8000Hz, 8000B/s, block align 1, 8 bits
+            b"\x10\0\0\0\x07\0\x01\0\x40\x1f\0\0\x40\x1f\0\0\x01\0\x08\0",
+            b"data\x40\x1f\0\0",
+        ]
+        .concat();
+        assert_eq!(
+            wav_header(AudioFormat::Raw8Khz8BitMonoMULaw, 8000).unwrap(),
+            mulaw.as_slice()
+        );
+        assert_eq!(
+            wav_header(AudioFormat::Audio24Khz48KBitRateMonoMp3, 8000)
+                .unwrap_err()
+                .kind,
+            AudioDataErrorKind::UnsupportedFormat(AudioFormat::Audio24Khz48KBitRateMonoMp3)
+        );
+    }
+
+    #[test]
+    // Sizes over `u32::MAX` do not fit in a 32 bit `usize`
+    #[cfg(target_pointer_width = "64")]
+    fn caps_the_sizes_in_wav_headers() {
+        let header = wav_header(AudioFormat::Raw24Khz16BitMonoPcm, u32::MAX as usize + 1).unwrap();
+        assert_eq!(header[4..8], u32::MAX.to_le_bytes());
+        assert_eq!(header[40..44], u32::MAX.to_le_bytes());
+        // The RIFF size saturates before the data size does
+        let header = wav_header(AudioFormat::Raw24Khz16BitMonoPcm, u32::MAX as usize - 10).unwrap();
+        assert_eq!(header[4..8], u32::MAX.to_le_bytes());
+        assert_eq!(header[40..44], (u32::MAX - 10).to_le_bytes());
+    }
+
+    /// An MPEG 2 Layer III frame of 24kHz and 48kbps, which lasts 576 samples(24ms) and takes 144 bytes
+    fn mp3_frame(fill: u8) -> Vec<u8> {
+        let mut frame = vec![0xff, 0xf3, 0x64, 0xc4];
+        frame.resize(144, fill);
+        frame
+    }
+
+    /// An ID3v2.4 tag with a payload of `size` bytes
+    fn id3v2_tag(size: u8) -> Vec<u8> {
+        let mut tag = b"ID3\x04\x00\x00\x00\x00\x00".to_vec();
+        tag.push(size);
+        tag.resize(10 + size as usize, 0);
+        tag
+    }
+
+    #[test]
+    fn concatenates_mp3_at_frame_boundaries() {
+        let format = AudioFormat::Audio24Khz48KBitRateMonoMp3;
+        let first = [mp3_frame(1), mp3_frame(2)].concat();
+        let second = [id3v2_tag(20), mp3_frame(3)].concat();
+        assert_eq!(mp3_frame_len(&second[30..]), Some(144));
+        let merged = concat_audio(format, &[&first, &second]).unwrap();
+        // The tag of the second segment is dropped instead of ending up in the middle of the stream
+        assert_eq!(merged, [first.as_slice(), &mp3_frame(3)].concat());
+        assert_eq!(
+            format.audio_duration(&merged),
+            Some(Duration::from_millis(72))
+        );
+        // The tag of the first segment is kept
+        let merged = concat_audio(format, &[&second, &first]).unwrap();
+        assert_eq!(merged, [second.as_slice(), &first].concat());
+        // An incomplete frame at the end of a segment is dropped
+        let merged = concat_audio(format, &[&first, &second[..40]]).unwrap();
+        assert_eq!(merged, first);
+        // A segment that does not start with a frame is rejected
+        let err = concat_audio(format, &[&first, &second[1..]]).unwrap_err();
+        assert_eq!(err.kind, AudioDataErrorKind::InvalidData);
+    }
+
+    fn ogg_page(flags: u8, granule: u64, serial: u32, sequence: u32, packet: &[u8]) -> Vec<u8> {
+        let mut page = [b"OggS\0".as_slice(), &[flags]].concat();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&[0, 0, 0, 0, 1, packet.len() as u8]);
+        page.extend_from_slice(packet);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    /// An Ogg Opus stream with the header pages and one audio page per granule position
+    fn ogg_opus_stream(serial: u32, pre_skip: u16, granules: &[u64]) -> Vec<u8> {
+        let head = [
+            b"OpusHead\x01\x01".as_slice(),
+            &pre_skip.to_le_bytes(),
+            &48000u32.to_le_bytes(),
+            &[0, 0, 0],
+        ]
+        .concat();
+        let mut stream = ogg_page(0x02, 0, serial, 0, &head);
+        stream.extend(ogg_page(0, 0, serial, 1, b"OpusTags\0\0\0\0\0\0\0\0"));
+        for (index, &granule) in granules.iter().enumerate() {
+            let flags = if index + 1 == granules.len() { 0x04 } else { 0 };
+            let sequence = index as u32 + 2;
+            stream.extend(ogg_page(
+                flags,
+                granule,
+                serial,
+                sequence,
+                &[0xfc, index as u8],
+            ));
+        }
+        stream
+    }
+
+    #[test]
+    fn concatenates_ogg_opus_into_one_stream() {
+        // The check value of CRC-32 without reflection and final xor
+        assert_eq!(ogg_crc(b"123456789"), 0x89a1_897f);
+        let format = AudioFormat::Ogg24Khz16BitMonoOpus;
+        let first = ogg_opus_stream(1, 312, &[12312, 24312]);
+        let second = ogg_opus_stream(2, 120, &[24120, 48120]);
+        assert_eq!(ogg_opus_duration(&first), Some(Duration::from_millis(500)));
+        assert_eq!(ogg_opus_duration(&second), Some(Duration::from_secs(1)));
+        let merged = concat_audio(format, &[&first, &second]).unwrap();
+        let mut pages = Vec::new();
+        let mut offset = 0;
+        while offset < merged.len() {
+            let page = &merged[offset..];
+            assert_eq!(&page[..4], b"OggS");
+            let end = 27
+                + page[26] as usize
+                + page[27..27 + page[26] as usize]
+                    .iter()
+                    .map(|&x| x as usize)
+                    .sum::<usize>();
+            let mut unchecked = page[..end].to_vec();
+            unchecked[22..26].fill(0);
+            assert_eq!(
+                page[22..26],
+                ogg_crc(&unchecked).to_le_bytes(),
+                "page {}",
+                pages.len()
+            );
+            pages.push(page[..end].to_vec());
+            offset += end;
+        }
+        // The header pages of the second stream are dropped
+        assert_eq!(pages.len(), 6);
+        for (index, page) in pages.iter().enumerate() {
+            assert_eq!(page[14..18], 1u32.to_le_bytes());
+            assert_eq!(page[18..22], (index as u32).to_le_bytes());
+        }
+        let flagged = |flag: u8| {
+            pages
+                .iter()
+                .enumerate()
+                .filter(|(_, page)| page[5] & flag != 0)
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(flagged(0x02), [0]);
+        assert_eq!(flagged(0x04), [5]);
+        assert_eq!(
+            ogg_opus_duration(&merged),
+            Some(Duration::from_millis(1500))
+        );
+    }
+}
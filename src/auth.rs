@@ -16,6 +16,7 @@ pub struct AuthOptions<'a> {
     /// Additional headers
     pub(crate) headers: Cow<'a, [(HeaderName, HeaderValue)]>,
     /// Proxy server to use. Only http and socks5 proxy are supported by now.
+    /// Host names are resolved by socks5 proxies, the `socks5h` scheme is accepted as well.
     pub(crate) proxy: Option<Cow<'a, str>>,
 }
 
@@ -65,15 +66,28 @@ impl<'a> AuthOptions<'a> {
     }
 
     /// Proxy server to use. Only http and socks5 proxy are supported by now.
+    /// Host names are resolved by socks5 proxies, the `socks5h` scheme is accepted as well.
     pub fn proxy(&self) -> Option<&str> {
         self.proxy.as_deref()
     }
 
     /// Proxy server to use. Only http and socks5 proxy are supported by now.
+    /// Host names are resolved by socks5 proxies, the `socks5h` scheme is accepted as well.
     pub fn proxy_mut(&mut self) -> &mut Option<Cow<'a, str>> {
         &mut self.proxy
     }
 
+    /// Convert into `AuthOptions` that own all the data, so that it can be stored for later use.
+    pub fn into_owned(self) -> AuthOptions<'static> {
+        AuthOptions {
+            endpoint: Cow::Owned(self.endpoint.into_owned()),
+            token: self.token.map(|x| Cow::Owned(x.into_owned())),
+            key: self.key.map(|x| Cow::Owned(x.into_owned())),
+            headers: Cow::Owned(self.headers.into_owned()),
+            proxy: self.proxy.map(|x| Cow::Owned(x.into_owned())),
+        }
+    }
+
     /// Create a builder for `AuthOptions`
     pub fn builder(endpoint: impl Into<Cow<'a, str>>) -> AuthOptionsBuilder<'a> {
         AuthOptionsBuilder::new(endpoint)
@@ -95,8 +109,8 @@ impl<'a> AuthOptionsBuilder<'a> {
     /// # Arguments
     ///
     /// * `endpoint` - Endpoint of the service.
-    /// It is usually a https url if you are using [`crate::synthesizer::RestSynthesizer`] or
-    /// a wss url if you are using [`crate::synthesizer::WebsocketSynthesizer`].
+    ///   It is usually a https url if you are using [`crate::synthesizer::RestSynthesizer`] or
+    ///   a wss url if you are using [`crate::synthesizer::WebsocketSynthesizer`].
     pub fn new(endpoint: impl Into<Cow<'a, str>>) -> Self {
         Self {
             endpoint: endpoint.into(),
@@ -138,12 +152,14 @@ impl<'a> AuthOptionsBuilder<'a> {
     }
 
     /// Proxy server to use. Only http and socks5 proxy are supported by now.
+    /// Host names are resolved by socks5 proxies, the `socks5h` scheme is accepted as well.
     pub fn proxy(mut self, proxy: impl Into<Cow<'a, str>>) -> Self {
         self.proxy = Some(proxy.into());
         self
     }
 
     /// Proxy server to use. Only http and socks5 proxy are supported by now.
+    /// Host names are resolved by socks5 proxies, the `socks5h` scheme is accepted as well.
     pub fn optional_proxy(mut self, proxy: Option<impl Into<Cow<'a, str>>>) -> Self {
         self.proxy = proxy.map(Into::into);
         self
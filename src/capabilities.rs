@@ -0,0 +1,84 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::{
+    AudioFormat, PcmEncoding, Role, Voice, VoiceListAPIAuth, VoiceListAPIEndpoint,
+    VoiceListAPIError,
+};
+
+/// The properties of an [`AudioFormat`], for presenting the formats to users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AudioFormatInfo {
+    /// The format
+    pub format: AudioFormat,
+    /// The name of the format, e.g. `riff-24khz-16bit-mono-pcm`
+    pub name: &'static str,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Bit rate in bits per second, for constant bit rate formats
+    pub bit_rate: Option<u32>,
+    /// The MIME type of the audio
+    pub content_type: &'static str,
+    /// The encoding of the samples, for uncompressed formats
+    pub pcm_encoding: Option<PcmEncoding>,
+}
+
+impl From<AudioFormat> for AudioFormatInfo {
+    fn from(format: AudioFormat) -> Self {
+        Self {
+            format,
+            name: format.into(),
+            sample_rate: format.sample_rate(),
+            bit_rate: format.bit_rate(),
+            content_type: format.content_type(),
+            pcm_encoding: format.pcm_encoding(),
+        }
+    }
+}
+
+/// Everything a configuration UI(e.g. a TTS playground) needs to know about the service, see [`tts_capabilities`].
+///
+/// It is serializable, so it can be served to a web frontend as JSON directly.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    /// The available voices, with their locales, genders, styles, roles and sample rates
+    pub voices: Vec<Voice>,
+    /// The locales of the voices, sorted and deduplicated
+    pub locales: Vec<String>,
+    /// All audio formats
+    pub audio_formats: Vec<AudioFormatInfo>,
+    /// All values of [`Role`]
+    pub roles: Vec<&'static str>,
+}
+
+impl Capabilities {
+    /// Assemble the capabilities from a voice list,
+    /// e.g. one that is cached instead of requested by [`tts_capabilities`].
+    pub fn from_voices(voices: Vec<Voice>) -> Self {
+        let locales = voices
+            .iter()
+            .map(|voice| voice.locale().to_string())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        Self {
+            voices,
+            locales,
+            audio_formats: AudioFormat::iter().map(AudioFormatInfo::from).collect(),
+            roles: Role::iter().map(|role| role.into()).collect(),
+        }
+    }
+}
+
+/// Request the voice list and assemble the [`Capabilities`] of the service from it
+/// and the [`AudioFormat`]/[`Role`] enums, to back a whole configuration UI with one call.
+pub async fn tts_capabilities(
+    endpoint: VoiceListAPIEndpoint<'_>,
+    auth: Option<VoiceListAPIAuth<'_>>,
+    proxy: Option<&str>,
+) -> Result<Capabilities, VoiceListAPIError> {
+    let voices = Voice::request_available_voices(endpoint, auth, proxy).await?;
+    Ok(Capabilities::from_voices(voices))
+}
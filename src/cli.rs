@@ -1,30 +1,43 @@
 use clap::{ArgAction, Parser};
 use log::{debug, info, warn};
-use rodio::{Decoder, OutputStream, Sink};
 
 use self::{
-    args::{AuthArgs, Color, InputArgs, ProfileArgs, SynthesizerMode, TextArgs},
+    args::{
+        AuthArgs, BatchArgs, Color, ContainerFormat, InputArgs, InputFormat, OutputArgs,
+        ProfileArgs, SubtitleArgs, Sweep, SweepParameter, SynthesizerMode, TextArgs,
+        VoiceListFormat,
+    },
     commands::Command,
-    config::{Config, TextConfig},
+    config::{AuthConfig, TextConfig},
 };
-use aspeak::{get_default_voice_by_locale, RichSsmlOptions, TextOptions};
+use aspeak::{
+    estimate_billed_characters, estimate_speech_duration, events_end, get_default_voice_by_locale,
+    parse_sentence_boundaries, parse_synthesis_events, parse_word_boundaries, play_audio_blocking,
+    AudioFileWriter, AudioFormat, BackgroundAudio, Lexicon, OutputEncoder, OutputEncoders,
+    RichSsmlOptions, SsmlTemplate, SubtitleFormat, SubtitleGenerator, TextOptions, Voice,
+};
+use serde_json::json;
 use std::{
     borrow::Cow,
-    fs::{File, OpenOptions},
-    io::{self, Cursor, Read, Write},
-    path::Path,
+    fs::{self, File, OpenOptions},
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use color_eyre::{
-    eyre::{anyhow, eyre},
+    eyre::{anyhow, eyre, Context},
     Help,
 };
-use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+use encoding_rs::{Encoding, UTF_8};
 
 pub(crate) mod args;
 pub(crate) mod commands;
+pub(crate) mod completions;
 pub(crate) mod config;
 mod parse;
+pub(crate) mod progress;
+pub(crate) mod voice_cache;
 
 #[derive(Parser, Debug)]
 #[command(author, version,
@@ -46,7 +59,33 @@ pub(crate) struct Cli {
     pub auth: AuthArgs,
 }
 
+/// Short input is not checked for truncation because the estimation is too rough for it
+const MIN_DURATION_FOR_TRUNCATION_CHECK: Duration = Duration::from_secs(5);
+
+/// The service stops a synthesis after 10 minutes of audio
+const MAX_AUDIO_DURATION: Duration = Duration::from_secs(600);
+/// Audio this close to [`MAX_AUDIO_DURATION`] is likely cut off by the service
+const MAX_AUDIO_DURATION_MARGIN: Duration = Duration::from_secs(5);
+
 type OutputProcessor = Box<dyn FnOnce(Vec<u8>) -> color_eyre::Result<()> + Send>;
+type StreamedOutput = Box<dyn Write + Send>;
+/// Stdout that is flushed after every chunk of audio, see [`Cli::process_streamed_output`]
+struct StdoutChunks;
+
+impl Write for StdoutChunks {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(buf)?;
+        stdout.flush()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+type BatchEncoder = Box<dyn Fn(&[u8]) -> io::Result<Vec<u8>> + Sync>;
 
 impl Cli {
     fn log_level_by_verbosity(verbosity: u8) -> log::LevelFilter {
@@ -60,16 +99,11 @@ impl Cli {
 
     pub(crate) fn get_synthesizer_mode(
         input_args: &InputArgs,
-        config: &Option<Config>,
+        auth_config: Option<&AuthConfig>,
     ) -> SynthesizerMode {
         input_args
             .mode
-            .or_else(|| {
-                config
-                    .as_ref()
-                    .and_then(|c| c.auth.as_ref())
-                    .and_then(|a| a.mode)
-            })
+            .or_else(|| auth_config.and_then(|a| a.mode))
             .unwrap_or(SynthesizerMode::Rest)
     }
     pub(crate) fn get_log_level(&self, verbosity_config: Option<u8>) -> log::LevelFilter {
@@ -81,54 +115,352 @@ impl Cli {
         }
     }
 
+    /// Read the input from the file or stdin and decode it.
+    ///
+    /// The input is decoded with `--encoding` or as UTF-8, unless it starts with a BOM that tells its encoding.
+    /// Malformed input is an error instead of being decoded into replacement characters.
     pub(crate) fn process_input_text(args: &InputArgs) -> color_eyre::Result<String> {
-        let mut s = String::new();
-
-        let file: Box<dyn io::Read> = match args.file.as_deref() {
+        let mut file: Box<dyn io::Read> = match args.file.as_deref() {
             Some(file) if file != "-" => Box::new(File::open(file)?),
+            // Waiting for the user to type the input is surprising unless stdin is explicitly requested
+            None if io::stdin().is_terminal() => return Err(eyre!("No input text/SSML.")
+                .with_note(|| "Stdin is a terminal and neither the input nor --file is given.")
+                .with_suggestion(|| {
+                    "Pass the input as an argument, pipe it into aspeak, or use `-f -` to type it."
+                })),
             _ => Box::new(io::stdin()),
         };
-        let mut decoder = if let Some(encoding) = args.encoding.as_deref() {
-            let encoding = encoding_rs::Encoding::for_label(encoding.as_bytes())
-                .ok_or(eyre!("Unsupported encoding: {encoding}"))?;
-            DecodeReaderBytesBuilder::new()
-                .encoding(Some(encoding))
-                .build(file)
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let encoding = match args.encoding.as_deref() {
+            Some(label) => Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| eyre!("Unsupported encoding: {label}"))?,
+            None => UTF_8,
+        };
+        let (encoding, bom_len) = Encoding::for_bom(&bytes).unwrap_or((encoding, 0));
+        let text = encoding
+            .decode_without_bom_handling_and_without_replacement(&bytes[bom_len..])
+            .ok_or_else(|| eyre!("The input is not valid {}", encoding.name()))
+            .with_suggestion(|| {
+                "Please specify the encoding of your input with --encoding, e.g. --encoding gbk"
+            })?;
+        Ok(Self::normalize_input_text(text.into_owned()))
+    }
+
+    /// Strip a leading BOM and normalize CRLF and CR line endings to LF,
+    /// so that they do not leak into the SSML.
+    fn normalize_input_text(s: String) -> String {
+        let text = s.strip_prefix('\u{feff}').unwrap_or(&s);
+        if !text.contains('\r') {
+            return if text.len() == s.len() {
+                s
+            } else {
+                text.to_string()
+            };
+        }
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        let cr = text.matches('\r').count() - crlf;
+        if (crlf > 0) as u8 + (lf > 0) as u8 + (cr > 0) as u8 > 1 {
+            debug!("The input has mixed line endings: {crlf} CRLF, {lf} LF and {cr} CR");
+        }
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    }
+
+    /// Whether the input looks like SSML, i.e. it starts with `<speak` after an optional XML declaration
+    fn is_ssml(input: &str) -> bool {
+        let input = input.trim_start_matches('\u{feff}').trim_start();
+        let input = match input.strip_prefix("<?xml") {
+            Some(rest) => rest
+                .split_once("?>")
+                .map_or("", |(_, rest)| rest.trim_start()),
+            None => input,
+        };
+        input.starts_with("<speak")
+    }
+
+    /// Turn the synthesize command into the text or ssml command according to the input format
+    pub(crate) fn route_synthesize_command(
+        input_format: InputFormat,
+        mut text_args: TextArgs,
+        input_args: InputArgs,
+        output_args: OutputArgs,
+        subtitle_args: SubtitleArgs,
+    ) -> color_eyre::Result<Command> {
+        let input = match text_args.text.take() {
+            Some(text) => text,
+            None => Self::process_input_text(&input_args)?,
+        };
+        let is_ssml = match input_format {
+            InputFormat::Auto => Self::is_ssml(&input),
+            InputFormat::Text => false,
+            InputFormat::Ssml => true,
+        };
+        debug!("Input format: {}", if is_ssml { "ssml" } else { "text" });
+        Ok(if is_ssml {
+            Command::Ssml {
+                ssml: Some(input),
+                minify_ssml: false,
+                max_request_size: None,
+                estimate: false,
+                input_args,
+                output_args,
+            }
         } else {
-            DecodeReaderBytes::new(file)
+            text_args.text = Some(input);
+            Command::Text {
+                text_args,
+                input_args,
+                output_args,
+                subtitle_args,
+                dump_ssml: None,
+                report: None,
+                estimate: false,
+                max_chunk_chars: None,
+                batch_args: BatchArgs::default(),
+            }
+        })
+    }
+
+    /// Print the estimated billed characters of the SSML documents
+    pub(crate) fn print_estimate(ssml: &[impl AsRef<str>]) {
+        let characters: usize = ssml
+            .iter()
+            .map(|s| estimate_billed_characters(s.as_ref()))
+            .sum();
+        println!("Estimated billed characters: {characters}");
+        if ssml.len() > 1 {
+            println!("Requests: {}", ssml.len());
+        }
+    }
+
+    /// Print the voices in the format chosen by `--format`, with all their details if `--verbose`
+    pub(crate) fn print_voices(
+        voices: &[&Voice],
+        format: VoiceListFormat,
+        verbose: bool,
+    ) -> color_eyre::Result<()> {
+        match format {
+            VoiceListFormat::Human if verbose => {
+                for voice in voices {
+                    println!("{}", voice.verbose());
+                }
+            }
+            VoiceListFormat::Human => {
+                for voice in voices {
+                    println!("{voice}");
+                }
+            }
+            VoiceListFormat::Json => println!("{}", serde_json::to_string_pretty(voices)?),
+            VoiceListFormat::Table => {
+                let rows: Vec<[Cow<str>; 4]> = voices
+                    .iter()
+                    .map(|voice| {
+                        [
+                            voice.short_name().into(),
+                            voice.locale().into(),
+                            voice.gender().into(),
+                            voice.style_list().unwrap_or_default().join(",").into(),
+                        ]
+                    })
+                    .collect();
+                let header = ["Short name", "Locale", "Gender", "Styles"].map(Cow::from);
+                let widths: Vec<usize> = (0..3)
+                    .map(|i| {
+                        std::iter::once(&header)
+                            .chain(&rows)
+                            .map(|row| row[i].chars().count())
+                            .max()
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                for row in std::iter::once(&header).chain(&rows) {
+                    println!(
+                        "{:w0$}  {:w1$}  {:w2$}  {}",
+                        row[0],
+                        row[1],
+                        row[2],
+                        row[3],
+                        w0 = widths[0],
+                        w1 = widths[1],
+                        w2 = widths[2]
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Print the styles, or the roles if `roles`, of the only voice left by `--voice`, one per line
+    pub(crate) fn print_voice_styles(
+        voices: &[&Voice],
+        short_name: &str,
+        roles: bool,
+    ) -> color_eyre::Result<()> {
+        let voice = voices.first().ok_or_else(|| {
+            eyre!("Voice {short_name} is not found")
+                .with_suggestion(|| "Run `aspeak list-voices` to see the available voices.")
+        })?;
+        let names = if roles {
+            voice.role_play_list()
+        } else {
+            voice.style_list()
         };
-        decoder.read_to_string(&mut s).with_note(|| {
-            "It is possibly due to incorrect encoding. \
-             Please specify an encoding for your file manually"
+        for name in names.unwrap_or_default() {
+            println!("{name}");
+        }
+        Ok(())
+    }
+
+    /// Tell the user that the audio is cut off by `--max-bytes`
+    pub(crate) fn warn_if_truncated(truncated: bool, max_bytes: usize) {
+        if truncated {
+            warn!("The audio is truncated to {max_bytes} bytes because it exceeds --max-bytes");
+        }
+    }
+
+    /// Encode the audio with the named encoder before passing it to the output processor
+    pub(crate) fn encode_output(
+        callback: OutputProcessor,
+        encoder: Option<&str>,
+        format: AudioFormat,
+    ) -> color_eyre::Result<OutputProcessor> {
+        let Some(name) = encoder else {
+            return Ok(callback);
+        };
+        let encoders = OutputEncoders::default();
+        Self::find_encoder(&encoders, name, format)?;
+        let name = name.to_string();
+        Ok(Box::new(move |buffer| {
+            let encoder = encoders.get(&name).expect("the encoder is checked above");
+            callback(encoder.encode(format, &buffer)?)
+        }))
+    }
+
+    /// Find the named encoder and check that it supports the format
+    fn find_encoder<'a>(
+        encoders: &'a OutputEncoders,
+        name: &str,
+        format: AudioFormat,
+    ) -> color_eyre::Result<&'a dyn OutputEncoder> {
+        let Some(encoder) = encoders.get(name) else {
+            let available = encoders.names().collect::<Vec<_>>().join(", ");
+            return Err(anyhow!("Unknown encoder: {name}")
+                .with_note(|| format!("Available encoders: {available}")));
+        };
+        if !encoder.supports(format) {
+            return Err(anyhow!(
+                "The {name} encoder does not support audio format {}",
+                Into::<&str>::into(format)
+            ));
+        }
+        Ok(encoder)
+    }
+
+    /// The encoder and the file extension of the audio files of a batch.
+    ///
+    /// Raw uncompressed audio is encoded as WAV unless another encoder is specified.
+    pub(crate) fn batch_encoder(
+        encoder: Option<&str>,
+        format: AudioFormat,
+    ) -> color_eyre::Result<(BatchEncoder, String)> {
+        let format_name: &str = format.into();
+        let is_raw_pcm = format_name.starts_with("raw-") && format.pcm_encoding().is_some();
+        let Some(name) = encoder.or(is_raw_pcm.then_some("wav")) else {
+            let extension = ContainerFormat::of_audio_format(format)
+                .map_or_else(|| "raw".to_string(), |c| c.as_ref().to_string());
+            return Ok((Box::new(|audio| Ok(audio.to_vec())), extension));
+        };
+        let encoders = OutputEncoders::default();
+        let extension = Self::find_encoder(&encoders, name, format)?
+            .extension()
+            .to_string();
+        let name = name.to_string();
+        Ok((
+            Box::new(move |audio| {
+                let encoder = encoders.get(&name).expect("the encoder is checked above");
+                encoder.encode(format, audio).map_err(io::Error::other)
+            }),
+            extension,
+        ))
+    }
+
+    /// Read the non-empty lines of the input file of a batch, with their(1-based) line numbers
+    pub(crate) fn process_batch_input(
+        batch: &str,
+        input_args: &InputArgs,
+    ) -> color_eyre::Result<Vec<(usize, String)>> {
+        let text = Self::process_input_text(&InputArgs {
+            file: Some(batch.to_string()),
+            encoding: input_args.encoding.clone(),
+            mode: None,
         })?;
-        Ok(s)
+        Ok(text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| (index + 1, line.trim().to_string()))
+            .collect())
+    }
+
+    /// Prepare the output files of the lines of a batch, see [`BatchArgs::output_path`]
+    pub(crate) fn process_batch_outputs(
+        batch_args: &BatchArgs,
+        lines: &[(usize, String)],
+        extension: &str,
+        overwrite: bool,
+    ) -> color_eyre::Result<Vec<PathBuf>> {
+        if let Some(dir) = batch_args.output_dir.as_deref() {
+            fs::create_dir_all(dir)?;
+        }
+        let width = lines
+            .last()
+            .map_or(0, |(line, _)| line.to_string().len())
+            .max(4);
+        lines
+            .iter()
+            .map(|(line, _)| {
+                let path = batch_args.output_path(*line, width, extension);
+                if path.exists() && !overwrite {
+                    return Err(anyhow!("File {} already exists!", path.display())
+                        .suggestion("You can use --overwrite to overwrite this file."));
+                }
+                Ok(path)
+            })
+            .collect()
     }
 
     pub(crate) fn process_output(
         output: Option<String>,
         overwrite: bool,
+        format: AudioFormat,
     ) -> color_eyre::Result<OutputProcessor> {
         Ok(if let Some(file) = output.as_deref() {
+            if file == "-" {
+                // Audio goes to stdout as is: raw formats without a header, RIFF formats as complete WAV.
+                // Logs and progress go to stderr, so they do not corrupt the audio.
+                return Ok(Box::new(|buffer| {
+                    let mut stdout = io::stdout().lock();
+                    stdout.write_all(&buffer)?;
+                    stdout.flush()?;
+                    Ok(())
+                }));
+            }
             let file = Path::new(file);
-            let mut file = match (file.exists(), overwrite) {
-                (_, true) => File::create(file)?,
-                (false, false) => OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create_new(true)
-                    .open(file)?,
-                (true, false) => {
-                    return Err(anyhow!("File {} already exists!", file.display())
-                        .suggestion("You can use --overwrite to overwrite this file."))
-                }
-            };
+            if is_fifo(file) {
+                let mut fifo = Self::open_fifo(file)?;
+                return Ok(Box::new(move |buffer| {
+                    fifo.write_all(&buffer)?;
+                    Ok(())
+                }));
+            }
+            let mut file = Self::create_output_file(file, overwrite)?;
             Box::new(move |buffer| {
                 file.write_all(&buffer)?;
                 Ok(())
             })
         } else {
-            Box::new(|buffer| {
-                info!("Playing audio... ({} bytes)", buffer.len());
+            Box::new(move |buffer| {
                 if buffer.is_empty()
                     || (
                         buffer.starts_with(b"RIFF")
@@ -142,18 +474,226 @@ impl Cli {
                     warn!("Got empty audio buffer, nothing to play");
                     return Ok(());
                 }
-                let (_stream, stream_handle) = OutputStream::try_default()?;
-                let sink = Sink::try_new(&stream_handle).unwrap();
-                let cursor = Cursor::new(buffer);
-                let source = Decoder::new(cursor)?;
-                sink.append(source);
-                sink.sleep_until_end();
-                debug!("Done playing audio");
+                play_audio_blocking(format, buffer)?;
                 Ok(())
             })
         })
     }
 
+    /// Open the output for writing the audio chunk by chunk as it arrives,
+    /// see [`aspeak::UnifiedSynthesizer::process_ssml_streamed`].
+    ///
+    /// FIFOs are always streamed into. Stdout(`-`) and files(through an [`AudioFileWriter`]) are streamed into
+    /// in websocket mode, which receives the audio in chunks, so that the reader of stdout gets the audio
+    /// as soon as it arrives and the audio of long syntheses is not held in memory.
+    /// Other outputs get the whole audio from [`Self::process_output`].
+    pub(crate) fn process_streamed_output(
+        output: Option<&str>,
+        overwrite: bool,
+        mode: SynthesizerMode,
+    ) -> color_eyre::Result<Option<StreamedOutput>> {
+        let Some(path) = output.map(Path::new) else {
+            return Ok(None);
+        };
+        Ok(if path == Path::new("-") {
+            (mode == SynthesizerMode::Websocket).then(|| Box::new(StdoutChunks) as StreamedOutput)
+        } else if is_fifo(path) {
+            Some(Box::new(Self::open_fifo(path)?))
+        } else if mode == SynthesizerMode::Websocket {
+            Some(Box::new(AudioFileWriter::new(Self::create_output_file(
+                path, overwrite,
+            )?)))
+        } else {
+            None
+        })
+    }
+
+    fn create_output_file(path: &Path, overwrite: bool) -> color_eyre::Result<File> {
+        Ok(match (path.exists(), overwrite) {
+            (_, true) => File::create(path)?,
+            (false, false) => OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(path)?,
+            (true, false) => {
+                return Err(anyhow!("File {} already exists!", path.display())
+                    .suggestion("You can use --overwrite to overwrite this file."))
+            }
+        })
+    }
+
+    /// Opening a FIFO for writing blocks until a reader opens it,
+    /// so it is opened before connecting and the reader gets the audio as soon as it arrives.
+    fn open_fifo(path: &Path) -> io::Result<File> {
+        info!("Waiting for a reader to open FIFO {}", path.display());
+        OpenOptions::new().write(true).open(path)
+    }
+
+    /// Write a JSON report of a synthesis for analysis and debugging
+    pub(crate) fn write_report(
+        path: &str,
+        connect_time: Duration,
+        synthesis_time: Duration,
+        audio_metadata: &[String],
+        audio_format: AudioFormat,
+        voice: &str,
+        audio: &[u8],
+    ) -> color_eyre::Result<()> {
+        let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+        let word_boundaries: Vec<_> = parse_word_boundaries(audio_metadata)?
+            .into_iter()
+            .map(|b| {
+                json!({
+                    "text": b.text,
+                    "offset_ms": millis(b.offset),
+                    "duration_ms": millis(b.duration),
+                    "type": format!("{:?}", b.boundary_type),
+                })
+            })
+            .collect();
+        let report = json!({
+            "timings": {
+                "connect_ms": millis(connect_time),
+                "synthesis_ms": millis(synthesis_time),
+                "total_ms": millis(connect_time + synthesis_time),
+            },
+            "word_boundaries": word_boundaries,
+            "format": Into::<&str>::into(audio_format),
+            "voice": voice,
+            "bytes": audio.len(),
+            "duration_ms": audio_format.audio_duration(audio).map(millis),
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        Ok(())
+    }
+
+    /// Write the subtitles generated from the word and sentence boundaries in the audio metadata,
+    /// in the format given by the extension of `path`
+    pub(crate) fn write_subtitles(
+        path: &str,
+        generator: &SubtitleGenerator,
+        metadata: &[String],
+    ) -> color_eyre::Result<()> {
+        let subtitles = generator.generate(
+            &parse_word_boundaries(metadata)?,
+            &parse_sentence_boundaries(metadata)?,
+            SubtitleFormat::from_path(path),
+        );
+        std::fs::write(path, subtitles)?;
+        Ok(())
+    }
+
+    /// The duration of the audio, estimated from the audio metadata if it can not be computed from the audio
+    /// or the audio is not kept, e.g. when it is streamed into the output.
+    pub(crate) fn audio_duration(
+        audio_format: AudioFormat,
+        audio: Option<&[u8]>,
+        metadata: &[String],
+    ) -> Option<Duration> {
+        audio
+            .and_then(|audio| audio_format.audio_duration(audio))
+            .or_else(|| {
+                parse_synthesis_events(metadata)
+                    .ok()
+                    .and_then(|events| events_end(&events))
+            })
+    }
+
+    /// Print the duration and size of the audio to stderr if `print`,
+    /// and warn if the audio is so long that the service likely cut it off at its 10 minute limit.
+    pub(crate) fn summarize_audio(duration: Option<Duration>, bytes: usize, print: bool) {
+        if print {
+            match duration {
+                Some(duration) => eprintln!(
+                    "Synthesized {:.1}s of audio ({} bytes)",
+                    duration.as_secs_f32(),
+                    bytes
+                ),
+                None => eprintln!("Synthesized {bytes} bytes of audio"),
+            }
+        }
+        if duration.is_some_and(|d| d + MAX_AUDIO_DURATION_MARGIN >= MAX_AUDIO_DURATION) {
+            warn!(
+                "The audio is close to the {}-minute limit of a synthesis and it is likely truncated. \
+                 Use --max-chunk-chars to synthesize long text in chunks.",
+                MAX_AUDIO_DURATION.as_secs() / 60
+            );
+        }
+    }
+
+    /// Warn if the audio is much shorter than the estimated duration of the text.
+    ///
+    /// The trial service silently truncates long input, so this is only checked
+    /// when no key or token is used.
+    pub(crate) fn check_truncation(text: &str, options: &TextOptions, duration: Option<Duration>) {
+        let estimated = estimate_speech_duration(text, options.rate());
+        let Some(actual) = duration else {
+            return;
+        };
+        debug!("Audio duration: {actual:?}, estimated: {estimated:?}");
+        if estimated >= MIN_DURATION_FOR_TRUNCATION_CHECK && actual < estimated / 2 {
+            warn!(
+                "The synthesized audio ({:.1}s) is much shorter than expected ({:.1}s). \
+                 The output is likely truncated by the trial service. \
+                 Please shorten your input or use a subscription key.",
+                actual.as_secs_f32(),
+                estimated.as_secs_f32()
+            );
+        }
+    }
+
+    /// The output path of a sweep take, e.g. `hello.wav` -> `hello-rate+10%.wav`
+    fn sweep_output_path(output: &str, parameter: SweepParameter, value: &str) -> PathBuf {
+        let output = Path::new(output);
+        let mut file_name = output.file_stem().unwrap_or_default().to_os_string();
+        file_name.push(format!("-{parameter}{value}"));
+        if let Some(extension) = output.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+        output.with_file_name(file_name)
+    }
+
+    /// Prepare the output of every take of a sweep, encoded with `encoder` like the output of a single synthesis
+    pub(crate) fn process_sweep_outputs<'a>(
+        sweep: &'a Sweep,
+        output: &str,
+        overwrite: bool,
+        format: AudioFormat,
+        encoder: Option<&str>,
+    ) -> color_eyre::Result<Vec<(&'a str, PathBuf, OutputProcessor)>> {
+        if output == "-" {
+            return Err(anyhow!("Can not write the takes of a sweep to stdout")
+                .suggestion("Specify an output file, the value is appended to its name."));
+        }
+        sweep
+            .values
+            .iter()
+            .map(|value| {
+                let path = Self::sweep_output_path(output, sweep.parameter, value);
+                let callback = Self::encode_output(
+                    Self::process_output(
+                        Some(path.to_string_lossy().into_owned()),
+                        overwrite,
+                        format,
+                    )?,
+                    encoder,
+                    format,
+                )?;
+                Ok((value.as_str(), path, callback))
+            })
+            .collect()
+    }
+
+    /// Get the default voice for the locale and tell the user which voice is selected
+    pub(crate) fn default_voice_for_locale(locale: &str) -> color_eyre::Result<&'static str> {
+        let voice = get_default_voice_by_locale(locale)
+            .ok_or_else(|| eyre!("No default voice found for locale {}", locale))?;
+        info!("Selected voice {voice}, the default voice for locale {locale}. Use --voice {voice} to pin it.");
+        Ok(voice)
+    }
+
     pub(crate) fn process_text_options<'a>(
         args: &'a TextArgs,
         config: Option<&'a TextConfig>,
@@ -162,15 +702,52 @@ impl Cli {
             .voice(
                 match (args.voice.as_deref(), args.locale.as_deref(), &config) {
                     (Some(voice), _, _) => voice,
-                    (None, Some(locale), _) => get_default_voice_by_locale(locale)
-                        .ok_or_else(|| eyre!("No default voice found for locale {}", locale))?,
+                    (None, Some(locale), _) => Self::default_voice_for_locale(locale)?,
                     (None, None, config) => config
                         .map(|c| c.voice.as_ref().map(|v| v.try_as_str()).transpose())
                         .transpose()?
                         .flatten()
-                        .unwrap_or_else(|| get_default_voice_by_locale("en-US").unwrap()),
+                        .map_or_else(|| Self::default_voice_for_locale("en-US"), Ok)?,
                 },
             )
+            .optional_lang(args.lang.as_deref())
+            .optional_ssml_template(
+                args.ssml_template
+                    .as_deref()
+                    .map(|path| -> color_eyre::Result<_> {
+                        let template = fs::read_to_string(path)
+                            .with_context(|| format!("Failed to read SSML template from {path}"))?;
+                        Ok(SsmlTemplate::new(&template)?)
+                    })
+                    .transpose()?,
+            )
+            .optional_lexicon(
+                args.lexicon
+                    .as_deref()
+                    .map(|path| -> color_eyre::Result<_> {
+                        let lexicon = fs::read_to_string(path)
+                            .with_context(|| format!("Failed to read lexicon from {path}"))?;
+                        Ok(Lexicon::from_json(&lexicon)?)
+                    })
+                    .transpose()?,
+            )
+            .optional_lexicon_url(args.lexicon_url.as_deref())
+            .optional_emphasis(args.emphasis)
+            .optional_background_audio(args.bg_audio.as_deref().map(|src| {
+                let mut background_audio = BackgroundAudio::new(src);
+                if let Some(volume) = args.bg_volume {
+                    background_audio = background_audio.with_volume(volume);
+                }
+                if let Some(ms) = args.bg_fadein {
+                    background_audio =
+                        background_audio.with_fade_in(Duration::from_millis(ms.into()));
+                }
+                if let Some(ms) = args.bg_fadeout {
+                    background_audio =
+                        background_audio.with_fade_out(Duration::from_millis(ms.into()));
+                }
+                background_audio
+            }))
             .optional_pitch({
                 if let Some(pitch) = args.pitch.as_deref().map(Cow::Borrowed) {
                     Some(pitch)
@@ -220,3 +797,129 @@ impl Cli {
             .build())
     }
 }
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read the bytes as the input file with `--encoding`
+    fn read_input(name: &str, bytes: &[u8], encoding: Option<&str>) -> color_eyre::Result<String> {
+        let path = std::env::temp_dir().join(format!("aspeak-input-{}-{name}", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let result = Cli::process_input_text(&InputArgs {
+            file: Some(path.to_string_lossy().into_owned()),
+            encoding: encoding.map(str::to_string),
+            mode: None,
+        });
+        fs::remove_file(path).unwrap();
+        result
+    }
+
+    #[test]
+    fn strips_a_utf8_bom() {
+        assert_eq!(
+            read_input("utf8-bom", b"\xef\xbb\xbfHello\r\nworld", None).unwrap(),
+            "Hello\nworld"
+        );
+    }
+
+    #[test]
+    fn decodes_utf16le_by_its_bom() {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend("Hi 你好".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(read_input("utf16le", &bytes, None).unwrap(), "Hi 你好");
+        // The BOM takes precedence over --encoding
+        assert_eq!(
+            read_input("utf16le-gbk", &bytes, Some("gbk")).unwrap(),
+            "Hi 你好"
+        );
+    }
+
+    #[test]
+    fn decodes_gbk_input() {
+        // "你好，世界" in GBK
+        let bytes = b"\xc4\xe3\xba\xc3\xa3\xac\xca\xc0\xbd\xe7";
+        assert_eq!(read_input("gbk", bytes, Some("gbk")).unwrap(), "你好，世界");
+        let err = read_input("gbk-as-utf8", bytes, None).unwrap_err();
+        assert_eq!(err.to_string(), "The input is not valid UTF-8");
+    }
+
+    #[test]
+    fn normalizes_lone_crs() {
+        assert_eq!(
+            read_input("cr", b"one\rtwo\r\nthree\nfour\r", None).unwrap(),
+            "one\ntwo\nthree\nfour\n"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let err = read_input("invalid-utf8", b"caf\xe9", None).unwrap_err();
+        assert_eq!(err.to_string(), "The input is not valid UTF-8");
+        // The same bytes are fine in the right encoding
+        assert_eq!(
+            read_input("latin1", b"caf\xe9", Some("latin1")).unwrap(),
+            "café"
+        );
+        let err = read_input("unknown-encoding", b"text", Some("klingon")).unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported encoding: klingon");
+    }
+
+    const STDOUT_CHILD_ENV: &str = "ASPEAK_TEST_STDOUT_CHILD";
+
+    #[test]
+    fn writes_the_audio_to_stdout_for_a_dash() {
+        let audio = b"RIFF\0\0\0\0WAVE-audio-of-the-child";
+        if std::env::var_os(STDOUT_CHILD_ENV).is_some() {
+            let output = Cli::process_output(Some("-".to_string()), false, AudioFormat::default());
+            output.unwrap()(audio.to_vec()).unwrap();
+            let mut streamed =
+                Cli::process_streamed_output(Some("-"), false, SynthesizerMode::Websocket)
+                    .unwrap()
+                    .expect("stdout is streamed into in websocket mode");
+            for chunk in [&b"-streamed-"[..], b"chunks-"] {
+                streamed.write_all(chunk).unwrap();
+            }
+            return;
+        }
+        // The REST API returns the audio as a whole
+        assert!(
+            Cli::process_streamed_output(Some("-"), false, SynthesizerMode::Rest)
+                .unwrap()
+                .is_none()
+        );
+        // Run this test again in a child process whose stdout is captured
+        let dir = std::env::temp_dir().join(format!("aspeak-stdout-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let child = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "cli::tests::writes_the_audio_to_stdout_for_a_dash",
+            ])
+            .env(STDOUT_CHILD_ENV, "1")
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(child.status.success(), "{child:?}");
+        for expected in [&audio[..], b"-streamed-chunks-"] {
+            assert!(
+                child.stdout.windows(expected.len()).any(|w| w == expected),
+                "{child:?}"
+            );
+        }
+        // No file named `-` is created
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
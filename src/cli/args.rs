@@ -1,16 +1,30 @@
 use std::borrow::Cow;
 use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use super::config::{AuthConfig, Config, OutputConfig};
 use super::parse;
 use aspeak::{
-    get_rest_endpoint_by_region, get_websocket_endpoint_by_region, AudioFormat, AuthOptions, Role,
+    get_rest_endpoint_by_region, get_websocket_endpoint_by_region, AudioFormat,
+    AudioFormatParseError, AudioFormatParseErrorKind, AuthOptions, EmphasisLevel, QualityOverrides,
+    Region, Role, SubtitleGenerator, SynthesizerConfig, DEFAULT_CONNECT_TIMEOUT,
+    DEFAULT_IDLE_TIMEOUT, QUALITY_RANGE_MAP,
 };
-use clap::{ArgAction, Args, ValueEnum};
-use color_eyre::Help;
+use clap::{
+    builder::{PossibleValue, TypedValueParser},
+    error::{ContextKind, ContextValue, ErrorKind},
+    ArgAction, Args, ValueEnum,
+};
+use color_eyre::{
+    eyre::{anyhow, eyre},
+    Help,
+};
+use log::warn;
 use reqwest::header::{HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
-use strum::{AsRefStr, Display};
+use strum::{AsRefStr, Display, IntoEnumIterator};
 
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Display)]
 #[strum(serialize_all = "kebab-case")]
@@ -20,7 +34,7 @@ pub(crate) enum Color {
     Never,
 }
 
-#[derive(Debug, Clone, Copy, Default, ValueEnum, AsRefStr, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum, AsRefStr, Deserialize)]
 #[strum(serialize_all = "kebab-case")]
 #[serde(rename_all = "kebab-case")]
 pub(crate) enum ContainerFormat {
@@ -31,20 +45,94 @@ pub(crate) enum ContainerFormat {
     Wav,
 }
 
+impl ContainerFormat {
+    /// The container of an output file with this extension
+    fn from_extension(extension: &str) -> Option<Self> {
+        Some(match extension.to_ascii_lowercase().as_str() {
+            "mp3" => Self::Mp3,
+            "ogg" | "opus" => Self::Ogg,
+            "webm" => Self::Webm,
+            "wav" => Self::Wav,
+            _ => return None,
+        })
+    }
+
+    /// The container of an audio format, `None` for compressed raw and other formats.
+    /// Raw uncompressed formats are written as WAV to `.wav` files, see [`OutputArgs::encoder_for`].
+    pub(crate) fn of_audio_format(format: AudioFormat) -> Option<Self> {
+        let name: &str = format.into();
+        if name.starts_with("riff-") || format.pcm_encoding().is_some() {
+            Some(Self::Wav)
+        } else if name.ends_with("-mp3") {
+            Some(Self::Mp3)
+        } else if name.starts_with("ogg-") {
+            Some(Self::Ogg)
+        } else if name.starts_with("webm-") {
+            Some(Self::Webm)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum, PartialEq, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub(crate) enum VoiceListFormat {
+    /// Every property of every voice, for humans
+    #[default]
+    Human,
+    /// Pretty printed JSON array of the voices, for scripts
+    Json,
+    /// Aligned columns of short name, locale, gender and styles
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum, PartialEq, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub(crate) enum InputFormat {
+    /// Treat the input as SSML if it starts with `<speak`, otherwise as text
+    #[default]
+    Auto,
+    Text,
+    Ssml,
+}
+
 #[derive(Args, Debug)]
 pub struct ProfileArgs {
     #[arg(long, action = ArgAction::SetTrue, help = "Do not use profile")]
     no_profile: bool,
-    #[arg(long, conflicts_with = "no_profile", help = "The profile to use")]
+    #[arg(
+        long,
+        conflicts_with = "no_profile",
+        help = "The profile to use, either the path to a profile \
+                or the name of a `[profiles.<name>]` table in the default profile"
+    )]
     profile: Option<String>,
 }
 
 impl ProfileArgs {
+    /// Whether `--profile` is a path rather than the name of a profile
+    fn profile_is_path(profile: &str) -> bool {
+        profile.contains(['/', '\\']) || profile.ends_with(".toml") || Path::new(profile).is_file()
+    }
+
     pub(crate) fn load_profile(&self) -> color_eyre::Result<Option<Config>> {
         if self.no_profile {
-            Ok(None)
-        } else {
-            Ok(Config::load(self.profile.as_ref())?)
+            return Ok(None);
+        }
+        match self.profile.as_deref() {
+            Some(path) if Self::profile_is_path(path) => Config::load(Some(path))?
+                .map(|config| config.select_profile(None))
+                .transpose(),
+            Some(name) => Config::load(None::<&str>)?
+                .ok_or_else(|| {
+                    anyhow!("Profile {name:?} is selected but the default profile does not exist")
+                })?
+                .select_profile(Some(name))
+                .map(Some),
+            None => Config::load(None::<&str>)?
+                .map(|config| config.select_profile(None))
+                .transpose(),
         }
     }
 }
@@ -56,7 +144,8 @@ pub struct AuthArgs {
     #[arg(
         short,
         long,
-        help = "If you are using official endpoints, you can specify a region instead of full endpoint url",
+        help = "If you are using official endpoints, you can specify a region instead of full endpoint url. \
+                Falls back to the AZURE_SPEECH_REGION environment variable.",
         conflicts_with = "endpoint"
     )]
     pub region: Option<String>,
@@ -66,19 +155,98 @@ pub struct AuthArgs {
         help = "Auth token for speech service. If you provide an auth token, the subscription key will be ignored."
     )]
     pub token: Option<String>,
-    #[arg(short, long, help = "Azure subscription key for speech service.")]
+    #[arg(
+        short,
+        long,
+        help = "Azure subscription key for speech service. Falls back to the ASPEAK_AUTH_KEY or AZURE_SPEECH_KEY environment variable."
+    )]
     pub key: Option<String>,
-    #[arg(short = 'H', long, value_parser = parse::parse_header, help = "Additional request headers")]
+    #[arg(
+        short = 'H',
+        long,
+        visible_alias = "header",
+        value_parser = parse::parse_header,
+        help = "Additional request headers in the form of `Name: Value` or `Name=Value`. Can be repeated"
+    )]
     pub headers: Vec<(HeaderName, HeaderValue)>,
     #[arg(
         long,
-        help = "Proxy to use. Only http and socks5 proxy are supported by now.\
+        help = "Proxy to use. Only http and socks5 proxy are supported by now. \
+                Host names are resolved by socks5 proxies, socks5h:// is accepted as well. \
                 This option takes precedence over the http_proxy or HTTP_PROXY environment variable."
     )]
     pub proxy: Option<String>,
+    #[arg(
+        long,
+        help = "Read auth settings like endpoint/region and key from a TOML file (or JSON if it ends with .json). \
+                The file takes precedence over the auth section of the profile, \
+                but command line options and environment variables take precedence over the file."
+    )]
+    pub auth_file: Option<String>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Give up connecting to the service after this many seconds, default to 10. 0 waits forever"
+    )]
+    pub connect_timeout: Option<f32>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Give up a websocket synthesis if the service sends nothing for this many seconds, default to 30. \
+                0 waits forever"
+    )]
+    pub idle_timeout: Option<f32>,
 }
 
 impl AuthArgs {
+    /// The auth config from the auth file, with the missing fields filled from the profile
+    pub(crate) fn load_auth_config(
+        &self,
+        profile_auth_config: Option<&AuthConfig>,
+    ) -> color_eyre::Result<Option<AuthConfig>> {
+        Ok(match self.auth_file.as_deref() {
+            Some(path) => {
+                Some(AuthConfig::load_file(Path::new(path))?.with_fallback(profile_auth_config))
+            }
+            None => profile_auth_config.cloned(),
+        })
+    }
+
+    /// Apply `--connect-timeout` and `--idle-timeout` to the config
+    pub(crate) fn apply_timeouts<'a>(
+        &self,
+        config: SynthesizerConfig<'a>,
+    ) -> color_eyre::Result<SynthesizerConfig<'a>> {
+        let timeout = |seconds: Option<f32>, default: Duration| {
+            seconds
+                .map(|s| {
+                    Duration::try_from_secs_f32(s)
+                        .map(|d| (!d.is_zero()).then_some(d))
+                        .map_err(|e| {
+                            color_eyre::eyre::eyre!("Invalid timeout: {e}")
+                                .with_note(|| "The timeout should be a positive number of seconds.")
+                        })
+                })
+                .unwrap_or(Ok(Some(default)))
+        };
+        Ok(config
+            .with_connect_timeout(timeout(self.connect_timeout, DEFAULT_CONNECT_TIMEOUT)?)
+            .with_idle_timeout(timeout(self.idle_timeout, DEFAULT_IDLE_TIMEOUT)?))
+    }
+
+    /// The first of the environment variables that is set
+    fn env_var(names: &[&str]) -> Option<String> {
+        names.iter().find_map(|name| env::var(name).ok())
+    }
+
+    /// The region from `--region`, or from the `AZURE_SPEECH_REGION` environment variable
+    pub(crate) fn region(&self) -> Option<Cow<'_, str>> {
+        self.region
+            .as_deref()
+            .map(Cow::Borrowed)
+            .or_else(|| Self::env_var(&["AZURE_SPEECH_REGION"]).map(Cow::Owned))
+    }
+
     pub(crate) fn to_auth_options<'a>(
         &'a self,
         auth_config: Option<&'a AuthConfig>,
@@ -90,12 +258,17 @@ impl AuthArgs {
                 .as_deref()
                 .map(Cow::Borrowed)
                 .or_else(|| {
-                    self.region
-                        .as_deref()
+                    self.region()
                         .map(|r| {
+                            if r.parse::<Region>().is_err() {
+                                warn!(
+                                    "Unknown region {r:?}, the endpoint may not exist. Known regions: {}",
+                                    Region::iter().map(|r| r.as_str()).collect::<Vec<_>>().join(", ")
+                                );
+                            }
                             match mode {
-                                SynthesizerMode::Rest => get_rest_endpoint_by_region(r),
-                                SynthesizerMode::Websocket => get_websocket_endpoint_by_region(r),
+                                SynthesizerMode::Rest => get_rest_endpoint_by_region(&r),
+                                SynthesizerMode::Websocket => get_websocket_endpoint_by_region(&r),
                             }
                         })
                         .map(Cow::Owned)
@@ -129,14 +302,14 @@ impl AuthArgs {
                     Cow::Borrowed::<'_, [(HeaderName, HeaderValue)]>(&self.headers)
                 }
             ).optional_token(
-                match (self.token.as_deref(), auth_config, env::var("ASPEAK_AUTH_TOKEN").ok()) {
+                match (self.token.as_deref(), auth_config, Self::env_var(&["ASPEAK_AUTH_TOKEN"])) {
                     (Some(token), _, _) => Some(Cow::Borrowed(token)),
                     (None, _, Some(token)) => Some(Cow::Owned(token)),
                     (None, Some(config), _) => config.token.as_deref().map(Cow::Borrowed),
                     (None, None, _) => None,
                 }
             ).optional_key(
-                match (self.key.as_deref(), auth_config, env::var("ASPEAK_AUTH_KEY").ok()) {
+                match (self.key.as_deref(), auth_config, Self::env_var(&["ASPEAK_AUTH_KEY", "AZURE_SPEECH_KEY"])) {
                     (Some(key), _, _) => Some(Cow::Borrowed(key)),
                     (None, _, Some(key)) => Some(Cow::Owned(key)),
                     (None, Some(config), _) => config.key.as_deref().map(Cow::Borrowed),
@@ -179,7 +352,11 @@ pub(crate) struct InputArgs {
 
 #[derive(Args, Debug, Default)]
 pub(crate) struct OutputArgs {
-    #[arg(short, long, help = "Output file path")]
+    #[arg(
+        short,
+        long,
+        help = "Output file path, `-` to write the audio to stdout. Play the audio if not specified"
+    )]
     pub output: Option<String>,
     #[arg(
         short,
@@ -188,6 +365,13 @@ pub(crate) struct OutputArgs {
         help = "Output quality, default to 0. Run `aspeak list-qualities` to list available quality levels"
     )]
     pub quality: Option<i32>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        requires = "quality",
+        help = "Use the closest quality level that the container supports if --quality is out of its range"
+    )]
+    pub closest_quality: bool,
     #[arg(short, long)]
     pub container_format: Option<ContainerFormat>,
     #[arg(
@@ -196,22 +380,92 @@ pub(crate) struct OutputArgs {
         conflicts_with = "quality",
         conflicts_with = "container_format",
         hide_possible_values = true,
+        value_parser = AudioFormatParser,
         help = "Set output audio format (experts only). Run `aspeak list-formats` to list available formats"
     )]
     pub format: Option<AudioFormat>,
     #[arg(long, action = ArgAction::SetTrue, help="Overwrite existing file")]
     pub overwrite: bool,
+    #[arg(
+        long,
+        help = "Encode the synthesized audio with this encoder before writing or playing it. \
+                Built-in encoders: wav (for raw PCM, A-law and mu-law formats, \
+                used by default when writing them to a .wav file)"
+    )]
+    pub encoder: Option<String>,
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help = "Cap the audio at this many bytes, stopping the synthesis once it is reached in websocket mode. \
+                The audio is truncated at a sample boundary for PCM formats."
+    )]
+    pub max_bytes: Option<usize>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Show the progress of the synthesis on stderr, and the duration and size of the audio at the end. \
+                The progress is hidden if stderr is not a terminal"
+    )]
+    pub progress: bool,
 }
 
 impl OutputArgs {
+    /// The encoder to use: the specified one, or `wav` when writing a raw uncompressed format to a `.wav` file
+    /// so that the file gets a RIFF header and is playable.
+    pub(crate) fn encoder_for(&self, format: AudioFormat) -> Option<&str> {
+        if self.encoder.is_some() {
+            return self.encoder.as_deref();
+        }
+        let is_wav_file = self
+            .output
+            .as_deref()
+            .and_then(|output| Path::new(output).extension())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("wav"));
+        let name: &str = format.into();
+        (is_wav_file && name.starts_with("raw-") && format.pcm_encoding().is_some())
+            .then_some("wav")
+    }
+
+    /// Infer the container from the extension of the output file,
+    /// and warn if it contradicts an explicitly specified format or container.
+    fn container_from_output(&self) -> Option<ContainerFormat> {
+        let extension = Path::new(self.output.as_deref()?).extension()?.to_str()?;
+        let inferred = ContainerFormat::from_extension(extension);
+        let explicit = match (self.format, self.container_format) {
+            (Some(format), _) => Some(ContainerFormat::of_audio_format(format)),
+            (None, Some(container)) => Some(Some(container)),
+            (None, None) => None,
+        };
+        match (explicit, inferred) {
+            // The encoder determines the container of the output
+            _ if self.encoder.is_some() => None,
+            (None, None) => {
+                warn!("Can not infer the audio format from the extension `.{extension}` of the output file, using the default");
+                None
+            }
+            (None, inferred) => inferred,
+            (Some(explicit), Some(inferred)) if explicit != Some(inferred) => {
+                warn!("The audio format does not match the extension `.{extension}` of the output file, the file may not be playable");
+                None
+            }
+            (Some(_), _) => None,
+        }
+    }
+
     pub(crate) fn get_audio_format(
         &self,
         config: Option<&OutputConfig>,
     ) -> color_eyre::Result<AudioFormat> {
+        let overrides = config
+            .map(|c| c.quality_overrides())
+            .transpose()?
+            .unwrap_or_default();
+        // Always check the output file so that contradictions are warned about
+        let container_format = self.container_format.or(self.container_from_output());
         Ok(
             match (
                 self.format,
-                self.container_format,
+                container_format,
                 self.quality,
                 config
                     .map(|c| (c.format.as_ref(), c.container.as_ref(), c.quality.as_ref()))
@@ -219,43 +473,85 @@ impl OutputArgs {
             ) {
                 // Explicitly specified format
                 (Some(format), _, _, _) => format,
-                // Explicitly specified container and quality
-                (None, Some(container), Some(quality), (_, _, _)) => {
-                    AudioFormat::from_container_and_quality(
-                        container.as_ref(),
-                        quality as i8,
-                        false,
-                    )?
-                }
+                // Explicitly specified container(or inferred from the output file) and quality
+                (None, Some(container), Some(quality), (_, _, _)) => Self::format_of_quality(
+                    container.as_ref(),
+                    quality,
+                    self.closest_quality,
+                    &overrides,
+                )?,
                 // Explicitly specified container
-                (None, Some(container), None, (_, _, quality)) => {
-                    AudioFormat::from_container_and_quality(
-                        container.as_ref(),
-                        quality.copied().unwrap_or_default() as i8,
-                        true,
-                    )?
-                }
+                (None, Some(container), None, (_, _, quality)) => Self::format_of_quality(
+                    container.as_ref(),
+                    quality.copied().unwrap_or_default(),
+                    true,
+                    &overrides,
+                )?,
                 // Explicitly specified quality
-                (None, None, Some(quality), (_, alt_container, _)) => {
-                    AudioFormat::from_container_and_quality(
-                        alt_container.copied().unwrap_or_default().as_ref(),
-                        quality as i8,
-                        false,
-                    )?
-                }
+                (None, None, Some(quality), (_, alt_container, _)) => Self::format_of_quality(
+                    alt_container.copied().unwrap_or_default().as_ref(),
+                    quality,
+                    self.closest_quality,
+                    &overrides,
+                )?,
                 // Format from config
                 (None, None, None, (Some(format), _, _)) => *format,
                 // Container and/or quality from config
-                (None, None, None, (None, container, quality)) => {
-                    AudioFormat::from_container_and_quality(
-                        container.copied().unwrap_or_default().as_ref(),
-                        quality.copied().unwrap_or_default() as i8,
-                        true,
-                    )?
-                }
+                (None, None, None, (None, container, quality)) => Self::format_of_quality(
+                    container.copied().unwrap_or_default().as_ref(),
+                    quality.copied().unwrap_or_default(),
+                    true,
+                    &overrides,
+                )?,
             },
         )
     }
+
+    /// The valid range of the quality levels of the container
+    fn quality_range_note(container: &str) -> String {
+        let (min, max) = QUALITY_RANGE_MAP
+            .get(container)
+            .copied()
+            .unwrap_or_default();
+        format!("The quality levels of {container} range from {min} to {max}.")
+    }
+
+    /// Look up the format of the quality level of the container, with the valid range in the error if it is invalid.
+    ///
+    /// If `use_closest` is `true`, out of range quality levels(even those that do not fit in an `i8`)
+    /// are replaced by the closest valid one instead.
+    fn format_of_quality(
+        container: &str,
+        quality: i32,
+        use_closest: bool,
+        overrides: &QualityOverrides,
+    ) -> color_eyre::Result<AudioFormat> {
+        let invalid_quality = || {
+            eyre!("Invalid quality {quality} for container {container}")
+                .with_note(|| Self::quality_range_note(container))
+                .with_suggestion(|| {
+                    "Use --closest-quality to fall back to the closest valid quality level, \
+                     or run `aspeak list-qualities` to list the quality levels."
+                })
+        };
+        let quality = match i8::try_from(quality) {
+            Ok(quality) => quality,
+            Err(_) if use_closest => quality.clamp(i8::MIN.into(), i8::MAX.into()) as i8,
+            Err(_) => return Err(invalid_quality()),
+        };
+        match AudioFormat::from_container_and_quality_with_overrides(
+            container,
+            quality,
+            use_closest,
+            overrides,
+        ) {
+            Err(AudioFormatParseError {
+                kind: AudioFormatParseErrorKind::InvalidQuality { .. },
+                ..
+            }) => Err(invalid_quality()),
+            result => Ok(result?),
+        }
+    }
 }
 
 fn parse_pitch(pitch: &str) -> Result<String, parse::ParseError> {
@@ -266,6 +562,99 @@ fn parse_rate(rate: &str) -> Result<String, parse::ParseError> {
     parse::parse_rate(rate).map(String::from)
 }
 
+/// Parse an [`AudioFormat`] like clap does for value enums, but suggest the closest format name
+/// and point to `list-formats` instead of listing all the formats when the value is invalid.
+#[derive(Debug, Clone, Copy)]
+struct AudioFormatParser;
+
+impl TypedValueParser for AudioFormatParser {
+    type Value = AudioFormat;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let value = value.to_string_lossy();
+        value.parse().map_err(|_| {
+            let mut error = clap::Error::new(ErrorKind::InvalidValue).with_cmd(cmd);
+            if let Some(arg) = arg {
+                error.insert(
+                    ContextKind::InvalidArg,
+                    ContextValue::String(arg.to_string()),
+                );
+            }
+            error.insert(
+                ContextKind::InvalidValue,
+                ContextValue::String(value.to_string()),
+            );
+            if let Some(name) = AudioFormat::suggest_name(&value) {
+                error.insert(
+                    ContextKind::SuggestedValue,
+                    ContextValue::String(name.to_string()),
+                );
+            }
+            error.insert(
+                ContextKind::Suggested,
+                ContextValue::StyledStrs(vec![
+                    "run `aspeak list-formats` to list available formats".into(),
+                ]),
+            );
+            error
+        })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            AudioFormat::value_variants()
+                .iter()
+                .filter_map(ValueEnum::to_possible_value),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub(crate) enum SweepParameter {
+    Rate,
+    Pitch,
+}
+
+/// Values of a prosody parameter to synthesize one take for each
+#[derive(Debug, Clone)]
+pub(crate) struct Sweep {
+    pub parameter: SweepParameter,
+    pub values: Vec<String>,
+}
+
+fn parse_sweep(arg: &str) -> Result<Sweep, parse::ParseError> {
+    let (parameter, values) = arg.split_once('=').ok_or_else(|| {
+        parse::ParseError::new(format!(
+            "Invalid sweep: {arg}. Expected PARAMETER=VALUE1,VALUE2,... like rate=-10%,0%,+10%"
+        ))
+    })?;
+    let parameter = match parameter {
+        "rate" => SweepParameter::Rate,
+        "pitch" => SweepParameter::Pitch,
+        _ => {
+            return Err(parse::ParseError::new(format!(
+                "Invalid sweep parameter: {parameter}. Only rate and pitch can be swept."
+            )))
+        }
+    };
+    Ok(Sweep {
+        parameter,
+        values: values
+            .split(',')
+            .map(|v| match parameter {
+                SweepParameter::Rate => parse_rate(v.trim()),
+                SweepParameter::Pitch => parse_pitch(v.trim()),
+            })
+            .collect::<Result<_, _>>()?,
+    })
+}
+
 #[derive(Args, Debug, Default)]
 pub(crate) struct TextArgs {
     #[clap(help = "The text to speak. \
@@ -300,6 +689,77 @@ pub(crate) struct TextArgs {
     pub voice: Option<String>,
     #[arg(short, long, help = "Locale to use, default to en-US")]
     pub locale: Option<String>,
+    #[arg(
+        long,
+        help = "Language of the text(xml:lang in SSML), default to the locale of the voice"
+    )]
+    pub lang: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Generate SSML from this template instead of the built-in structure. \
+                Placeholders: {text}(required), {voice}, {lang}, {pitch}, {rate}, {style}, {role} and {style_degree}. \
+                Use {{ and }} for literal braces."
+    )]
+    pub ssml_template: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Pronounce the words in this JSON file as specified, e.g. {\"tomato\": \"təˈmɑːtoʊ\"}. \
+                A pronunciation is either an IPA string or an object like {\"alphabet\": \"sapi\", \"ph\": \"...\"}."
+    )]
+    pub lexicon: Option<String>,
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Apply the pronunciation lexicon(PLS) at this URL, which has to be publicly accessible"
+    )]
+    pub lexicon_url: Option<String>,
+    #[arg(long, value_enum, help = "Emphasize the text at this level")]
+    pub emphasis: Option<EmphasisLevel>,
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Play the audio at this URL under the speech. It has to be publicly accessible"
+    )]
+    pub bg_audio: Option<String>,
+    #[arg(
+        long,
+        requires = "bg_audio",
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        help = "Volume of the background audio, from 0 to 100, default to 1"
+    )]
+    pub bg_volume: Option<u8>,
+    #[arg(
+        long,
+        value_name = "MS",
+        requires = "bg_audio",
+        value_parser = clap::value_parser!(u16).range(0..=10000),
+        help = "Fade in the background audio in this many milliseconds, at most 10000"
+    )]
+    pub bg_fadein: Option<u16>,
+    #[arg(
+        long,
+        value_name = "MS",
+        requires = "bg_audio",
+        value_parser = clap::value_parser!(u16).range(0..=10000),
+        help = "Fade out the background audio in this many milliseconds, at most 10000"
+    )]
+    pub bg_fadeout: Option<u16>,
+    #[arg(
+        long,
+        help = "Voice to retry with if the voice is not available, e.g. not supported in the region of the endpoint"
+    )]
+    pub fallback_voice: Option<String>,
+    #[arg(
+        long,
+        value_name = "RETRIES",
+        conflicts_with = "max_bytes",
+        conflicts_with = "sweep",
+        help = "Synthesize again, at most RETRIES times, if the audio is empty or much shorter than expected. \
+                Short input is only retried if the audio is empty."
+    )]
+    pub retry_short_audio: Option<u32>,
     #[arg(
         long,
         help = "Disable rich SSML. This is helpful if the endpoint you are using doesn't support some ssml extensions like mstts.\
@@ -310,4 +770,208 @@ pub(crate) struct TextArgs {
         conflicts_with = "style_degree"
     )]
     pub no_rich_ssml: bool,
+    #[arg(
+        long,
+        value_parser = parse_sweep,
+        requires = "output",
+        conflicts_with = "subtitles",
+        help = "Synthesize one take per value of a prosody parameter for comparison, \
+                e.g. rate=-10%,0%,+10% or pitch=low,medium,high. \
+                The value is appended to the file name of the output for each take."
+    )]
+    pub sweep: Option<Sweep>,
+}
+
+#[derive(Args, Debug, Default)]
+pub(crate) struct SubtitleArgs {
+    #[arg(
+        long,
+        help = "Write subtitles generated from word boundaries to this file. \
+                WebVTT is used for files ending with .vtt, otherwise SRT is used. \
+                Only available in websocket mode."
+    )]
+    pub subtitles: Option<String>,
+    #[arg(
+        long,
+        requires = "subtitles",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Maximum characters per subtitle cue, default to 42"
+    )]
+    pub subtitle_max_chars: Option<u32>,
+    #[arg(
+        long,
+        requires = "subtitles",
+        help = "Maximum duration of a subtitle cue in seconds, default to 7"
+    )]
+    pub subtitle_max_duration: Option<f32>,
+    #[arg(
+        long,
+        requires = "subtitles",
+        action = ArgAction::SetTrue,
+        help = "Do not start a new subtitle cue at the end of a sentence"
+    )]
+    pub no_subtitle_sentence_break: bool,
+}
+
+impl SubtitleArgs {
+    pub(crate) fn to_generator(&self) -> color_eyre::Result<SubtitleGenerator> {
+        let max_duration = self
+            .subtitle_max_duration
+            .map(Duration::try_from_secs_f32)
+            .transpose()
+            .map_err(|e| {
+                color_eyre::eyre::eyre!("Invalid subtitle cue duration: {e}")
+                    .with_note(|| "The duration should be a positive number of seconds.")
+            })?;
+        Ok(SubtitleGenerator::builder()
+            .optional_max_chars_per_cue(self.subtitle_max_chars.map(|c| c as usize))
+            .optional_max_cue_duration(max_duration)
+            .break_on_sentence(!self.no_subtitle_sentence_break)
+            .build())
+    }
+}
+
+/// The name of the checkpoint file of --batch, see [`BatchArgs::checkpoint_path`]
+const BATCH_CHECKPOINT_NAME: &str = ".aspeak-checkpoint.json";
+
+#[derive(Args, Debug, Default)]
+pub(crate) struct BatchArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "output_dir",
+        conflicts_with_all = ["text", "file", "output", "sweep", "dump_ssml", "report", "estimate", "subtitles", "max_chunk_chars"],
+        help = "Synthesize every non-empty line of this file(`-` for stdin) into its own audio file in --output-dir. \
+                The files are named by line number, e.g. 0001.wav"
+    )]
+    pub batch: Option<String>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "batch",
+        help = "The directory to write the audio files of --batch to. It is created if it does not exist"
+    )]
+    pub output_dir: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        requires = "batch",
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Synthesize at most this many lines of --batch concurrently, each over its own connection, default to 4"
+    )]
+    pub jobs: Option<u16>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        requires = "batch",
+        help = "Resume an interrupted --batch, skipping the lines that are completed according to the checkpoint \
+                in --output-dir. A line is synthesized again if it has changed since it was completed. \
+                The files of the lines that are not completed are overwritten"
+    )]
+    pub resume: bool,
+}
+
+impl BatchArgs {
+    /// The file in --output-dir that tracks the completed lines of the batch
+    pub(crate) fn checkpoint_path(&self) -> PathBuf {
+        Path::new(self.output_dir.as_deref().unwrap_or_default()).join(BATCH_CHECKPOINT_NAME)
+    }
+
+    /// The output file of the line with the given(1-based) line number,
+    /// zero-padded to `width` digits so that the files sort in the order of the lines
+    pub(crate) fn output_path(&self, line: usize, width: usize, extension: &str) -> PathBuf {
+        Path::new(self.output_dir.as_deref().unwrap_or_default())
+            .join(format!("{line:0width$}.{extension}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aspeak::QUALITY_MAP;
+
+    fn output_args(output: &str) -> OutputArgs {
+        OutputArgs {
+            output: Some(output.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn infers_the_container_from_the_extension() {
+        for (output, container) in [
+            ("out.wav", ContainerFormat::Wav),
+            ("out.mp3", ContainerFormat::Mp3),
+            ("out.ogg", ContainerFormat::Ogg),
+            ("out.OPUS", ContainerFormat::Ogg),
+            ("out.webm", ContainerFormat::Webm),
+        ] {
+            let args = output_args(output);
+            assert_eq!(args.container_from_output(), Some(container), "{output}");
+            let format = args.get_audio_format(None).unwrap();
+            assert_eq!(
+                ContainerFormat::of_audio_format(format),
+                Some(container),
+                "{output}"
+            );
+        }
+        // Unknown extensions fall back to the default format
+        let args = output_args("out.flac");
+        assert_eq!(args.container_from_output(), None);
+        assert_eq!(
+            args.get_audio_format(None).unwrap(),
+            AudioFormat::Riff24Khz16BitMonoPcm
+        );
+        assert_eq!(output_args("-").container_from_output(), None);
+    }
+
+    #[test]
+    fn validates_the_quality_against_the_container() {
+        for (container, (min, max)) in QUALITY_RANGE_MAP.entries() {
+            let format_of = |quality: i32, use_closest| {
+                OutputArgs::format_of_quality(
+                    container,
+                    quality,
+                    use_closest,
+                    &QualityOverrides::new(),
+                )
+            };
+            for quality in [i32::from(*min) - 1, i32::from(*max) + 1, 1000] {
+                let err = format_of(quality, false).unwrap_err();
+                assert_eq!(
+                    err.to_string(),
+                    format!("Invalid quality {quality} for container {container}")
+                );
+            }
+            let lowest = QUALITY_MAP[container][min];
+            let highest = QUALITY_MAP[container][max];
+            assert_eq!(format_of(i32::from(*min) - 1, true).unwrap(), lowest);
+            assert_eq!(format_of(i32::MIN, true).unwrap(), lowest);
+            assert_eq!(format_of(i32::from(*max) + 1, true).unwrap(), highest);
+            assert_eq!(format_of(1000, true).unwrap(), highest);
+        }
+        assert_eq!(
+            OutputArgs::quality_range_note("mp3"),
+            "The quality levels of mp3 range from -4 to 3."
+        );
+    }
+
+    #[test]
+    fn uses_the_closest_quality_only_if_requested() {
+        let args = OutputArgs {
+            container_format: Some(ContainerFormat::Mp3),
+            quality: Some(7),
+            ..Default::default()
+        };
+        assert!(args.get_audio_format(None).is_err());
+        let args = OutputArgs {
+            closest_quality: true,
+            ..args
+        };
+        assert_eq!(
+            args.get_audio_format(None).unwrap(),
+            AudioFormat::Audio48Khz192KBitRateMonoMp3
+        );
+    }
 }
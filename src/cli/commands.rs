@@ -1,20 +1,32 @@
 use clap::{ArgAction, Subcommand};
 
-use super::args::*;
+use super::{args::*, completions::Shell};
 
 #[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)] // The command is only parsed once
 pub(crate) enum Command {
-    #[command(about = "List information of available voices, optionally filtered by locale/voice")]
+    #[command(
+        about = "List information of available voices, optionally filtered by locale/voice/gender/style"
+    )]
     ListVoices {
+        #[arg(short, long, help = "Voice to list, default to all voices")]
+        voice: Option<String>,
         #[arg(
             short,
             long,
-            conflicts_with = "locale",
-            help = "Voice to list, default to all voices"
+            value_delimiter = ',',
+            help = "Locales to list, separated by commas(e.g. en-US,fr-FR), default to all locales. \
+                    A language like `en` lists the voices of all its locales"
         )]
-        voice: Option<String>,
-        #[arg(short, long, help = "Locale to list, default to all locales")]
-        locale: Option<String>,
+        locale: Vec<String>,
+        #[arg(short, long, help = "Only list voices of this gender, e.g. Female")]
+        gender: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "Only list voices that support this style, e.g. cheerful"
+        )]
+        style: Option<String>,
         #[arg(
             short,
             long,
@@ -22,6 +34,39 @@ pub(crate) enum Command {
                     then aspeak will use the API url designated for that region."
         )]
         url: Option<String>,
+        #[arg(
+            long,
+            help = "Request the voice list even if it is cached, and refresh the cache"
+        )]
+        refresh: bool,
+        #[arg(long, default_value_t = VoiceListFormat::Human, help = "Output format")]
+        format: VoiceListFormat,
+        #[arg(
+            long,
+            conflicts_with = "format",
+            help = "Output JSON, same as --format json"
+        )]
+        json: bool,
+        #[arg(
+            long,
+            help = "Show all the details of the voices in the human readable format, \
+                    e.g. the other locales they speak, the scenarios they are tailored for and their personalities"
+        )]
+        verbose: bool,
+        #[arg(
+            long,
+            requires = "voice",
+            conflicts_with_all = ["format", "json", "verbose", "roles"],
+            help = "Only print the styles of the voice given by --voice, one per line"
+        )]
+        styles: bool,
+        #[arg(
+            long,
+            requires = "voice",
+            conflicts_with_all = ["format", "json", "verbose"],
+            help = "Only print the role-play roles of the voice given by --voice, one per line"
+        )]
+        roles: bool,
     },
     #[command(about = "List available qualities for all container formats")]
     ListQualities,
@@ -35,6 +80,39 @@ pub(crate) enum Command {
         input_args: InputArgs,
         #[command(flatten)]
         output_args: OutputArgs,
+        #[command(flatten)]
+        subtitle_args: SubtitleArgs,
+        #[arg(
+            long,
+            conflicts_with = "sweep",
+            help = "Save the generated SSML to this file. \
+                    You can synthesize it again later with the ssml subcommand."
+        )]
+        dump_ssml: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "sweep",
+            help = "Write a JSON report with the timings, word boundaries, format, voice and size of the synthesis to this file. \
+                    Word boundaries are only available in websocket mode."
+        )]
+        report: Option<String>,
+        #[arg(
+            long,
+            action = ArgAction::SetTrue,
+            help = "Estimate the billed characters of the generated SSML and exit without synthesizing"
+        )]
+        estimate: bool,
+        #[arg(
+            long,
+            value_name = "CHARS",
+            conflicts_with_all = ["sweep", "subtitles", "report", "retry_short_audio"],
+            help = "Split text longer than this many characters at sentence ends, synthesize the chunks \
+                    over one connection and concatenate the audio. --max-bytes caps the concatenated audio. \
+                    It works around the 10 minute limit on the audio of a single request. Not supported for webm"
+        )]
+        max_chunk_chars: Option<usize>,
+        #[command(flatten)]
+        batch_args: BatchArgs,
     },
     #[command(about = "Speak SSML")]
     Ssml {
@@ -42,16 +120,67 @@ pub(crate) enum Command {
                     If neither SSML nor input file is specified, the SSML will be read from stdin. \
                     Do not include the document type definition in your SSML.")]
         ssml: Option<String>,
+        #[arg(
+            long,
+            action = ArgAction::SetTrue,
+            help = "Remove insignificant whitespace and comments from the SSML before sending it"
+        )]
+        minify_ssml: bool,
+        #[arg(
+            long,
+            value_name = "BYTES",
+            help = "Split the SSML into requests of at most this many bytes and concatenate the audio. \
+                    The SSML is only split after top-level sentences and paragraphs or between voices."
+        )]
+        max_request_size: Option<usize>,
+        #[arg(
+            long,
+            action = ArgAction::SetTrue,
+            help = "Estimate the billed characters of the SSML and exit without synthesizing"
+        )]
+        estimate: bool,
+        #[command(flatten)]
+        input_args: InputArgs,
+        #[command(flatten)]
+        output_args: OutputArgs,
+    },
+    #[command(about = "Speak text or SSML, detecting which one the input is")]
+    Synthesize {
+        #[arg(
+            long,
+            default_value_t = InputFormat::Auto,
+            help = "Format of the input. \
+                    In auto mode, the input is treated as SSML if it starts with `<speak` and as text otherwise. \
+                    Text and subtitle options are ignored for SSML input."
+        )]
+        input_format: InputFormat,
+        #[command(flatten)]
+        text_args: TextArgs,
         #[command(flatten)]
         input_args: InputArgs,
         #[command(flatten)]
         output_args: OutputArgs,
+        #[command(flatten)]
+        subtitle_args: SubtitleArgs,
     },
     #[command(about = "Configure settings of aspeak")]
     Config {
         #[command(subcommand)]
         command: ConfigCommand,
     },
+    #[command(
+        about = "Print the shell completion script for bash, zsh, fish, elvish or PowerShell"
+    )]
+    Completions {
+        #[arg(required_unless_present = "cached_voices")]
+        shell: Option<Shell>,
+        #[arg(
+            long,
+            hide = true,
+            help = "Print the short names of the voices in the cached voice lists, used by the completion scripts"
+        )]
+        cached_voices: bool,
+    },
 }
 
 impl Default for Command {
@@ -60,6 +189,12 @@ impl Default for Command {
             text_args: TextArgs::default(),
             input_args: InputArgs::default(),
             output_args: OutputArgs::default(),
+            subtitle_args: SubtitleArgs::default(),
+            dump_ssml: None,
+            report: None,
+            estimate: false,
+            max_chunk_chars: None,
+            batch_args: BatchArgs::default(),
         }
     }
 }
@@ -77,4 +212,13 @@ pub(crate) enum ConfigCommand {
     },
     #[command(about = "Show full path to the default profile")]
     Where,
+    #[command(about = "List the named profiles in the `[profiles.<name>]` tables of a profile")]
+    ListProfiles {
+        #[arg(
+            short,
+            long,
+            help = "Path to the profile, default to the default profile"
+        )]
+        path: Option<String>,
+    },
 }
@@ -0,0 +1,124 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+use clap::{Arg, Command};
+pub(crate) use clap_complete::Shell;
+
+/// Options whose values are completed with the short names of the cached voices
+const VOICE_OPTIONS: [&str; 2] = ["voice", "fallback-voice"];
+/// The command that prints the short names of the cached voices, see [`super::voice_cache::VoiceCache::cached_short_names`]
+const CACHED_VOICES_COMMAND: &str = "aspeak completions --cached-voices 2>/dev/null";
+
+/// The options in the command tree whose values are voice names
+fn voice_options(command: &Command) -> Vec<&Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| {
+            arg.get_long()
+                .is_some_and(|long| VOICE_OPTIONS.contains(&long))
+        })
+        .chain(command.get_subcommands().flat_map(voice_options))
+        .collect()
+}
+
+/// Write the completion script of the command for the shell with [`clap_complete::generate`].
+///
+/// Subcommands, options and the possible values of options are completed in every shell.
+/// In bash and fish, voice names are completed from the cached voice lists(see `list-voices`) as well.
+pub(crate) fn generate(
+    shell: Shell,
+    command: &mut Command,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, command, &name, out);
+    match shell {
+        Shell::Bash => generate_bash_voices(command, &name, out),
+        Shell::Fish => generate_fish_voices(command, &name, out),
+        _ => Ok(()),
+    }
+}
+
+/// Wrap the completion function generated by clap to complete the values of voice options
+fn generate_bash_voices(command: &Command, name: &str, out: &mut impl Write) -> io::Result<()> {
+    let flags = voice_options(command)
+        .into_iter()
+        .flat_map(|arg| {
+            let long = arg.get_long().map(|long| format!("--{long}"));
+            let short = arg.get_short().map(|short| format!("-{short}"));
+            long.into_iter().chain(short)
+        })
+        .collect::<BTreeSet<_>>();
+    let patterns = flags.into_iter().collect::<Vec<_>>().join("|");
+    writeln!(out)?;
+    writeln!(out, "_{name}_voices() {{")?;
+    writeln!(out, "    case \"${{COMP_WORDS[COMP_CWORD-1]}}\" in")?;
+    writeln!(out, "        {patterns})")?;
+    writeln!(
+        out,
+        "            COMPREPLY=($(compgen -W \"$({CACHED_VOICES_COMMAND})\" -- \"${{COMP_WORDS[COMP_CWORD]}}\"))"
+    )?;
+    writeln!(out, "            return 0 ;;")?;
+    writeln!(out, "    esac")?;
+    writeln!(out, "    _{name} \"$@\"")?;
+    writeln!(out, "}}")?;
+    writeln!(
+        out,
+        "complete -F _{name}_voices -o nosort -o bashdefault -o default {name}"
+    )
+}
+
+/// Add completions of the values of voice options, which fish merges with the generated ones
+fn generate_fish_voices(command: &Command, name: &str, out: &mut impl Write) -> io::Result<()> {
+    let flags = voice_options(command)
+        .into_iter()
+        .map(|arg| (arg.get_long(), arg.get_short()))
+        .collect::<BTreeSet<_>>();
+    for (long, short) in flags {
+        write!(out, "complete -c {name}")?;
+        if let Some(long) = long {
+            write!(out, " -l {long}")?;
+        }
+        if let Some(short) = short {
+            write!(out, " -s {short}")?;
+        }
+        writeln!(out, " -x -a '({CACHED_VOICES_COMMAND})'")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, ValueEnum};
+
+    use super::*;
+    use crate::cli::Cli;
+
+    fn script(shell: Shell) -> String {
+        let mut out = Vec::new();
+        generate(shell, &mut Cli::command(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn generates_scripts_for_every_shell() {
+        for &shell in Shell::value_variants() {
+            assert!(script(shell).contains("list-voices"), "{shell}");
+        }
+    }
+
+    #[test]
+    fn completes_voices_from_the_cache_in_bash_and_fish() {
+        let bash = script(Shell::Bash);
+        assert!(bash.contains("        --fallback-voice|--voice|-v)\n"));
+        assert!(bash
+            .ends_with("complete -F _aspeak_voices -o nosort -o bashdefault -o default aspeak\n"));
+        let fish = script(Shell::Fish);
+        assert!(fish.contains(
+            "complete -c aspeak -l voice -s v -x -a '(aspeak completions --cached-voices 2>/dev/null)'\n"
+        ));
+        assert!(!script(Shell::Zsh).contains(CACHED_VOICES_COMMAND));
+    }
+}
@@ -1,21 +1,29 @@
 use std::{
     borrow::Cow,
+    collections::{BTreeMap, HashMap},
     fs,
     path::{Path, PathBuf},
 };
 
 use aspeak::{
-    get_default_voice_by_locale, get_rest_endpoint_by_region, get_websocket_endpoint_by_region,
-    AudioFormat, Role,
+    get_rest_endpoint_by_region, get_websocket_endpoint_by_region, AudioFormat, QualityOverrides,
+    Role, QUALITY_MAP,
 };
 use color_eyre::eyre::{anyhow, bail};
+use log::warn;
 
 use serde::Deserialize;
 
-use super::args::{ContainerFormat, SynthesizerMode};
+use super::{
+    args::{ContainerFormat, SynthesizerMode},
+    Cli,
+};
 
 pub(crate) const CONFIG_TEMPLATE: &str = include_str!("aspeak.toml");
 pub(crate) const DEFAULT_PROFILE_NAME: &str = ".aspeak.toml";
+const XDG_PROFILE_NAME: &str = "aspeak.toml";
+/// The named profile used when `--profile` is not given
+const DEFAULT_NAMED_PROFILE: &str = "default";
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct Config {
@@ -23,6 +31,17 @@ pub(crate) struct Config {
     pub text: Option<TextConfig>,
     pub output: Option<OutputConfig>,
     pub verbosity: Option<u8>,
+    /// Named profiles in `[profiles.<name>]` tables, selected by `--profile <name>`
+    pub profiles: Option<BTreeMap<String, ProfileConfig>>,
+}
+
+/// A named profile. Its sections replace the sections of the same name at the top level of the config.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ProfileConfig {
+    pub auth: Option<AuthConfig>,
+    pub text: Option<TextConfig>,
+    pub output: Option<OutputConfig>,
+    pub verbosity: Option<u8>,
 }
 
 impl Config {
@@ -46,11 +65,27 @@ impl Config {
         Ok(())
     }
 
+    /// `~/.aspeak.toml`, unless only the profile in the config dir(e.g. `~/.config/aspeak/aspeak.toml`) exists
     pub fn default_location() -> color_eyre::Result<PathBuf> {
-        let path = dirs::home_dir()
-            .ok_or(anyhow!("Could not find home directory"))?
-            .join(DEFAULT_PROFILE_NAME);
-        Ok::<PathBuf, color_eyre::eyre::ErrReport>(path)
+        let home = dirs::home_dir().ok_or(anyhow!("Could not find home directory"))?;
+        Ok(Self::default_location_in(
+            &home,
+            dirs::config_dir().as_deref(),
+        ))
+    }
+
+    /// [`Config::default_location`] in the given home and config dirs
+    fn default_location_in(home: &Path, config_dir: Option<&Path>) -> PathBuf {
+        let path = home.join(DEFAULT_PROFILE_NAME);
+        if !path.exists() {
+            if let Some(xdg_path) = config_dir
+                .map(|dir| dir.join("aspeak").join(XDG_PROFILE_NAME))
+                .filter(|path| path.exists())
+            {
+                return xdg_path;
+            }
+        }
+        path
     }
 
     pub fn load<P: AsRef<Path>>(path: Option<P>) -> color_eyre::Result<Option<Self>> {
@@ -66,9 +101,38 @@ impl Config {
         };
         Ok(text.as_deref().map(toml::from_str).transpose()?)
     }
+
+    /// The names of the named profiles, sorted
+    pub fn profile_names(&self) -> impl Iterator<Item = &str> {
+        self.profiles
+            .iter()
+            .flatten()
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Apply the named profile, or the profile named `default` if `name` is `None` and it exists
+    pub fn select_profile(mut self, name: Option<&str>) -> color_eyre::Result<Self> {
+        let mut profiles = self.profiles.take().unwrap_or_default();
+        let profile = match name {
+            Some(name) => Some(profiles.remove(name).ok_or_else(|| {
+                anyhow!(
+                    "Profile {name:?} is not found in the config, available profiles: {:?}",
+                    profiles.keys().collect::<Vec<_>>()
+                )
+            })?),
+            None => profiles.remove(DEFAULT_NAMED_PROFILE),
+        };
+        if let Some(profile) = profile {
+            self.auth = profile.auth.or(self.auth);
+            self.text = profile.text.or(self.text);
+            self.output = profile.output.or(self.output);
+            self.verbosity = profile.verbosity.or(self.verbosity);
+        }
+        Ok(self)
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub(crate) struct AuthConfig {
     #[serde(flatten)]
     pub endpoint_config: Option<EndpointConfig>,
@@ -77,10 +141,63 @@ pub(crate) struct AuthConfig {
     pub headers: Option<Vec<(String, String)>>,
     pub proxy: Option<String>,
     pub voice_list_api: Option<String>,
+    /// How long the voice list is cached in seconds, 0 disables the cache
+    pub voice_list_cache_ttl: Option<u64>,
     pub mode: Option<SynthesizerMode>,
 }
 
-#[derive(Debug, Deserialize)]
+impl AuthConfig {
+    /// Load auth config from a credentials file, which is JSON if it ends with `.json` and TOML otherwise.
+    pub fn load_file(path: &Path) -> color_eyre::Result<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                warn!(
+                    "The auth file {} is accessible by other users (mode {:o}). \
+                     Consider restricting it with `chmod 600`.",
+                    path.display(),
+                    mode & 0o777
+                );
+            }
+        }
+        let text = fs::read_to_string(path)?;
+        Ok(
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+            {
+                serde_json::from_str(&text)?
+            } else {
+                toml::from_str(&text)?
+            },
+        )
+    }
+
+    /// Fill the fields that are not set with the ones from `fallback`
+    pub fn with_fallback(self, fallback: Option<&AuthConfig>) -> Self {
+        let Some(fallback) = fallback else {
+            return self;
+        };
+        Self {
+            endpoint_config: self
+                .endpoint_config
+                .or_else(|| fallback.endpoint_config.clone()),
+            key: self.key.or_else(|| fallback.key.clone()),
+            token: self.token.or_else(|| fallback.token.clone()),
+            headers: self.headers.or_else(|| fallback.headers.clone()),
+            proxy: self.proxy.or_else(|| fallback.proxy.clone()),
+            voice_list_api: self
+                .voice_list_api
+                .or_else(|| fallback.voice_list_api.clone()),
+            voice_list_cache_ttl: self.voice_list_cache_ttl.or(fallback.voice_list_cache_ttl),
+            mode: self.mode.or(fallback.mode),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub(crate) enum EndpointConfig {
     Endpoint { endpoint: String },
@@ -88,7 +205,7 @@ pub(crate) enum EndpointConfig {
 }
 
 impl EndpointConfig {
-    pub(crate) fn to_cow_str(&self, mode: SynthesizerMode) -> Cow<str> {
+    pub(crate) fn to_cow_str(&self, mode: SynthesizerMode) -> Cow<'_, str> {
         match self {
             EndpointConfig::Endpoint { endpoint } => Cow::Borrowed(endpoint),
             EndpointConfig::Region { region } => Cow::Owned(match mode {
@@ -151,8 +268,7 @@ impl VoiceConfig {
     pub fn try_as_str(&self) -> color_eyre::Result<&str> {
         Ok(match self {
             VoiceConfig::Voice { voice } => voice.as_str(),
-            VoiceConfig::Locale { locale } => get_default_voice_by_locale(locale)
-                .ok_or_else(|| anyhow!("Could not find default voice for locale: {}", locale))?,
+            VoiceConfig::Locale { locale } => Cli::default_voice_for_locale(locale)?,
         })
     }
 }
@@ -162,4 +278,191 @@ pub(crate) struct OutputConfig {
     pub format: Option<AudioFormat>,
     pub container: Option<ContainerFormat>,
     pub quality: Option<i32>,
+    pub quality_overrides: Option<HashMap<String, HashMap<String, AudioFormat>>>,
+}
+
+impl OutputConfig {
+    pub fn quality_overrides(&self) -> color_eyre::Result<QualityOverrides> {
+        let Some(overrides) = self.quality_overrides.as_ref() else {
+            return Ok(QualityOverrides::new());
+        };
+        overrides
+            .iter()
+            .map(|(container, qualities)| {
+                if !QUALITY_MAP.contains_key(container) {
+                    bail!("Invalid container format in quality_overrides: {container}");
+                }
+                let qualities = qualities
+                    .iter()
+                    .map(|(quality, format)| {
+                        let quality = quality.parse::<i8>().map_err(|_| {
+                            anyhow!(
+                                "Invalid quality level in quality_overrides.{container}: {quality}"
+                            )
+                        })?;
+                        Ok((quality, *format))
+                    })
+                    .collect::<color_eyre::Result<_>>()?;
+                Ok((container.clone(), qualities))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::args::{OutputArgs, TextArgs};
+
+    /// An empty temporary directory that is unique to the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aspeak-config-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const PROFILE: &str = r#"
+[text]
+voice = "en-US-JennyNeural"
+rate = 0.5
+pitch = "+10%"
+
+[output]
+container = "mp3"
+
+[profiles.default.text]
+voice = "en-GB-RyanNeural"
+
+[profiles.slow.text]
+locale = "zh-CN"
+rate = "-20%"
+
+[profiles.slow.output]
+format = "ogg-48khz-16bit-mono-opus"
+"#;
+
+    fn load(dir: &Path) -> Config {
+        let path = dir.join(DEFAULT_PROFILE_NAME);
+        fs::write(&path, PROFILE).unwrap();
+        Config::load(Some(&path)).unwrap().unwrap()
+    }
+
+    #[test]
+    fn looks_up_the_profile_in_the_home_dir_then_the_config_dir() {
+        let home = temp_dir("home");
+        let config_dir = home.join(".config");
+        let home_profile = home.join(DEFAULT_PROFILE_NAME);
+        let xdg_profile = config_dir.join("aspeak").join(XDG_PROFILE_NAME);
+        // Neither exists: the profile is created in the home dir
+        assert_eq!(
+            Config::default_location_in(&home, Some(&config_dir)),
+            home_profile
+        );
+        fs::create_dir_all(xdg_profile.parent().unwrap()).unwrap();
+        fs::write(&xdg_profile, PROFILE).unwrap();
+        assert_eq!(
+            Config::default_location_in(&home, Some(&config_dir)),
+            xdg_profile
+        );
+        assert_eq!(Config::default_location_in(&home, None), home_profile);
+        fs::write(&home_profile, PROFILE).unwrap();
+        assert_eq!(
+            Config::default_location_in(&home, Some(&config_dir)),
+            home_profile
+        );
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn named_profiles_replace_the_top_level_sections() {
+        let dir = temp_dir("profiles");
+        let config = load(&dir);
+        assert_eq!(
+            config.profile_names().collect::<Vec<_>>(),
+            ["default", "slow"]
+        );
+        // The `default` profile is applied when no profile is named
+        let default = config.select_profile(None).unwrap();
+        let text = default.text.unwrap();
+        assert_eq!(
+            text.voice.unwrap().try_as_str().unwrap(),
+            "en-GB-RyanNeural"
+        );
+        assert!(text.rate.is_none());
+        assert_eq!(
+            default.output.unwrap().container,
+            Some(ContainerFormat::Mp3)
+        );
+
+        let slow = load(&dir).select_profile(Some("slow")).unwrap();
+        assert_eq!(slow.text.unwrap().rate().unwrap().unwrap(), "-20%");
+        assert_eq!(
+            slow.output.unwrap().format,
+            Some(AudioFormat::Ogg48Khz16BitMonoOpus)
+        );
+
+        let err = load(&dir).select_profile(Some("fast")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(r#"available profiles: ["default", "slow"]"#),
+            "{err}"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn command_line_options_take_precedence_over_the_profile_and_defaults() {
+        let dir = temp_dir("precedence");
+        let mut config = load(&dir);
+        config.profiles = None;
+        let text_config = config.text.as_ref();
+        let output_config = config.output.as_ref();
+
+        // The profile over the defaults
+        let no_args = TextArgs::default();
+        let options = Cli::process_text_options(&no_args, text_config).unwrap();
+        assert_eq!(options.voice(), "en-US-JennyNeural");
+        assert_eq!(options.rate(), Some("50.00%"));
+        assert_eq!(options.pitch(), Some("+10%"));
+        assert_eq!(
+            OutputArgs::default()
+                .get_audio_format(output_config)
+                .unwrap(),
+            AudioFormat::Audio24Khz96KBitRateMonoMp3
+        );
+
+        // The command line over the profile
+        let args = TextArgs {
+            locale: Some("zh-CN".to_string()),
+            rate: Some("-10%".to_string()),
+            ..Default::default()
+        };
+        let options = Cli::process_text_options(&args, text_config).unwrap();
+        assert_eq!(options.voice(), "zh-CN-XiaoxiaoNeural");
+        assert_eq!(options.rate(), Some("-10%"));
+        assert_eq!(options.pitch(), Some("+10%"));
+        let output_args = OutputArgs {
+            quality: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            output_args.get_audio_format(output_config).unwrap(),
+            AudioFormat::Audio48Khz192KBitRateMonoMp3
+        );
+
+        // The defaults without a profile
+        let options = Cli::process_text_options(&no_args, None).unwrap();
+        assert_eq!(
+            options.voice(),
+            aspeak::get_default_voice_by_locale("en-US").unwrap()
+        );
+        assert_eq!(options.rate(), None);
+        assert_eq!(options.pitch(), None);
+        assert_eq!(
+            OutputArgs::default().get_audio_format(None).unwrap(),
+            AudioFormat::Riff24Khz16BitMonoPcm
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
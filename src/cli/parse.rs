@@ -2,16 +2,20 @@ use std::error::Error;
 
 use reqwest::header::{HeaderName, HeaderValue};
 
-/// Parse a single key-value pair
+/// Parse a single header in the form of `Name: Value` or `Name=Value`
 pub(super) fn parse_header(
     s: &str,
 ) -> Result<(HeaderName, HeaderValue), Box<dyn Error + Send + Sync + 'static>> {
+    // Header names can contain neither `:` nor `=`, so the first one is the separator
     let pos = s
-        .find('=')
-        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
+        .find([':', '='])
+        .ok_or_else(|| format!("invalid header `{s}`: expected `Name: Value` or `Name=Value`"))?;
+    let (name, value) = (s[..pos].trim(), s[pos + 1..].trim());
     Ok((
-        HeaderName::from_bytes(s[..pos].as_bytes())?,
-        HeaderValue::from_str(&s[pos + 1..])?,
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("invalid header name `{name}` in `{s}`"))?,
+        HeaderValue::from_str(value)
+            .map_err(|_| format!("invalid header value `{value}` in `{s}`"))?,
     ))
 }
 
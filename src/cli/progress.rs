@@ -0,0 +1,82 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    time::{Duration, Instant},
+};
+
+use aspeak::AudioFormat;
+
+/// How often the progress bar is redrawn at most
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+/// A progress bar of a synthesis on stderr, driven by the number of audio bytes received so far.
+///
+/// The total is estimated from the expected duration of the speech and the byte rate of the format,
+/// so the bar never reaches 100% before the synthesis finishes. If either is unknown(e.g. for Opus or SSML input),
+/// only the size of the audio received so far is shown.
+pub(crate) struct Progress {
+    expected_bytes: Option<usize>,
+    start: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl Progress {
+    /// `None` if stderr is not a terminal, so that the progress does not end up in logs or pipes
+    pub fn new(format: AudioFormat, expected_duration: Option<Duration>) -> Option<Self> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+        let byte_rate = format.bit_rate().map(|rate| rate / 8).or_else(|| {
+            format
+                .pcm_encoding()
+                .map(|encoding| encoding.bytes_per_sample() as u32 * format.sample_rate())
+        });
+        let expected_bytes = byte_rate
+            .zip(expected_duration)
+            .map(|(rate, duration)| (duration.as_secs_f64() * rate as f64) as usize)
+            .filter(|&bytes| bytes > 0);
+        Some(Self {
+            expected_bytes,
+            start: Instant::now(),
+            last_draw: None,
+        })
+    }
+
+    /// Redraw the progress bar with the number of bytes received so far
+    pub fn update(&mut self, bytes: usize) {
+        if self
+            .last_draw
+            .is_some_and(|last| last.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+        let elapsed = self.start.elapsed().as_secs_f32();
+        let line = match self.expected_bytes {
+            Some(expected) => {
+                let ratio = (bytes as f64 / expected as f64).min(0.99);
+                let filled = (ratio * BAR_WIDTH as f64) as usize;
+                format!(
+                    "[{}{}] {:>2}% {elapsed:.1}s",
+                    "#".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled),
+                    (ratio * 100.0) as u32
+                )
+            }
+            None => format!("{:.1} KiB received {elapsed:.1}s", bytes as f64 / 1024.0),
+        };
+        let mut stderr = io::stderr().lock();
+        // Errors of drawing the progress bar are not worth failing the synthesis for
+        let _ = write!(stderr, "\r\x1b[2K{line}");
+        let _ = stderr.flush();
+    }
+
+    /// Clear the progress bar
+    pub fn finish(self) {
+        if self.last_draw.is_some() {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use aspeak::Voice;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+/// How long the voice list is cached if it is not configured
+pub(crate) const DEFAULT_VOICE_LIST_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize)]
+struct CachedVoices {
+    url: String,
+    /// Seconds since the unix epoch
+    fetched_at: u64,
+    voices: Vec<Voice>,
+}
+
+/// A cache of the voice list on disk, so that the voice list API is not requested on every call.
+///
+/// The voice list is keyed by the url of the voice list API, so changing the region or the url
+/// invalidates it. Filtering by locale happens after loading the whole list.
+pub(crate) struct VoiceCache {
+    path: PathBuf,
+    url: String,
+    ttl: Duration,
+}
+
+impl VoiceCache {
+    /// `None` if there is no cache directory on this platform
+    pub fn new(url: &str, ttl: Duration) -> Option<Self> {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        let path = Self::dir()?.join(format!("voices-{:016x}.json", hasher.finish()));
+        Some(Self {
+            path,
+            url: url.to_string(),
+            ttl,
+        })
+    }
+
+    fn dir() -> Option<PathBuf> {
+        Some(dirs::cache_dir()?.join("aspeak"))
+    }
+
+    /// The voices in all the cached voice lists, fresh or stale
+    fn cached_voices() -> impl Iterator<Item = Voice> {
+        Self::dir()
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("voices-") && name.ends_with(".json")
+            })
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .filter_map(|text| serde_json::from_str::<CachedVoices>(&text).ok())
+            .flat_map(|cached| cached.voices)
+    }
+
+    /// The short names of the voices in all the cached voice lists, fresh or stale, e.g. for shell completion
+    pub fn cached_short_names() -> Vec<String> {
+        let mut names: Vec<String> = Self::cached_voices()
+            .map(|voice| voice.short_name().to_string())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// The details of a voice by its short name or full name in any cached voice list, fresh or stale,
+    /// e.g. to check its styles before synthesizing. `None` if the voice list has never been cached.
+    pub fn cached_voice(name: &str) -> Option<Voice> {
+        Self::cached_voices().find(|voice| voice.short_name() == name || voice.name() == name)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    /// The cached voice list, if it is still fresh
+    pub fn load(&self) -> Option<Vec<Voice>> {
+        let text = fs::read_to_string(&self.path).ok()?;
+        let cached: CachedVoices = match serde_json::from_str(&text) {
+            Ok(cached) => cached,
+            Err(e) => {
+                debug!(
+                    "Ignoring the broken voice list cache {}: {e}",
+                    self.path.display()
+                );
+                return None;
+            }
+        };
+        let age = Duration::from_secs(Self::now().saturating_sub(cached.fetched_at));
+        if cached.url != self.url || age >= self.ttl {
+            debug!("The voice list cache {} is stale", self.path.display());
+            return None;
+        }
+        debug!("Using the voice list cached {}s ago", age.as_secs());
+        Some(cached.voices)
+    }
+
+    pub fn store(&self, voices: &[Voice]) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let cached = CachedVoices {
+            url: self.url.clone(),
+            fetched_at: Self::now(),
+            voices: voices.to_vec(),
+        };
+        fs::write(&self.path, serde_json::to_vec(&cached)?)?;
+        debug!("Cached the voice list in {}", self.path.display());
+        Ok(())
+    }
+}
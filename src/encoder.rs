@@ -0,0 +1,163 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use strum::AsRefStr;
+
+use crate::{pcm_payload, wav_header, AudioDataError, AudioFormat};
+
+/// An encoder that converts the synthesized audio into an output format that the service does not provide,
+/// e.g. FLAC or AAC.
+///
+/// Encoders receive the raw bytes returned by the service together with their format.
+/// Use [`pcm_payload`] to get the PCM samples of raw or RIFF audio.
+/// Register custom encoders in an [`OutputEncoders`] to make them selectable by name.
+pub trait OutputEncoder: Send + Sync {
+    /// The name to select the encoder by, e.g. `wav`
+    fn name(&self) -> &str;
+    /// The file extension of the encoded audio, without the leading dot
+    fn extension(&self) -> &str;
+    /// Whether the encoder accepts audio in this format
+    fn supports(&self, format: AudioFormat) -> bool;
+    /// Encode the audio, which is in the given format
+    fn encode(&self, format: AudioFormat, audio: &[u8]) -> Result<Vec<u8>, EncoderError>;
+}
+
+/// Encode uncompressed audio(PCM, A-law or μ-law) as WAV.
+///
+/// This is the reference implementation of [`OutputEncoder`].
+/// Raw audio gets a RIFF header(see [`wav_header`]) and RIFF audio is passed through with fixed sizes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WavEncoder;
+
+impl OutputEncoder for WavEncoder {
+    fn name(&self) -> &str {
+        "wav"
+    }
+
+    fn extension(&self) -> &str {
+        "wav"
+    }
+
+    fn supports(&self, format: AudioFormat) -> bool {
+        format.pcm_encoding().is_some()
+    }
+
+    fn encode(&self, format: AudioFormat, audio: &[u8]) -> Result<Vec<u8>, EncoderError> {
+        if !self.supports(format) {
+            return Err(EncoderError {
+                kind: EncoderErrorKind::UnsupportedFormat(format),
+                source: None,
+            });
+        }
+        let payload = pcm_payload(format, audio)?;
+        let mut wav = Vec::with_capacity(44 + payload.len());
+        wav.extend_from_slice(&wav_header(format, payload.len())?);
+        wav.extend_from_slice(payload);
+        Ok(wav)
+    }
+}
+
+/// A set of output encoders that can be selected by name.
+///
+/// [`OutputEncoders::default`] contains the built-in encoders.
+pub struct OutputEncoders {
+    encoders: Vec<Box<dyn OutputEncoder>>,
+}
+
+impl Default for OutputEncoders {
+    fn default() -> Self {
+        Self {
+            encoders: vec![Box::new(WavEncoder)],
+        }
+    }
+}
+
+impl OutputEncoders {
+    /// Create an empty set of encoders
+    pub fn new() -> Self {
+        Self {
+            encoders: Vec::new(),
+        }
+    }
+
+    /// Register an encoder. It replaces any registered encoder with the same name.
+    pub fn register(&mut self, encoder: impl OutputEncoder + 'static) -> &mut Self {
+        self.encoders.retain(|e| e.name() != encoder.name());
+        self.encoders.push(Box::new(encoder));
+        self
+    }
+
+    /// Get an encoder by its name
+    pub fn get(&self, name: &str) -> Option<&dyn OutputEncoder> {
+        self.encoders
+            .iter()
+            .find(|e| e.name() == name)
+            .map(|e| e.as_ref())
+    }
+
+    /// The names of all registered encoders
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.encoders.iter().map(|e| e.name())
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+/// An error that occurred while encoding audio
+pub struct EncoderError {
+    pub kind: EncoderErrorKind,
+    pub(crate) source: Option<anyhow::Error>,
+}
+
+impl EncoderError {
+    /// Create an error for a failure inside an encoder
+    pub fn new(source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self {
+            kind: EncoderErrorKind::Encode,
+            source: Some(anyhow::anyhow!(source.into())),
+        }
+    }
+}
+
+impl Display for EncoderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "encoder error: ")?;
+        match &self.kind {
+            EncoderErrorKind::UnsupportedFormat(format) => write!(
+                f,
+                "audio format {} is not supported by the encoder",
+                Into::<&str>::into(format)
+            ),
+            kind => write!(f, "{} error", kind.as_ref()),
+        }
+    }
+}
+
+impl Error for EncoderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|e| e.as_ref() as _)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, AsRefStr)]
+#[non_exhaustive]
+#[strum(serialize_all = "title_case")]
+pub enum EncoderErrorKind {
+    /// The encoder does not accept audio in this format
+    UnsupportedFormat(AudioFormat),
+    /// The audio data is invalid
+    AudioData,
+    /// The encoder failed
+    Encode,
+}
+
+impl From<AudioDataError> for EncoderError {
+    fn from(e: AudioDataError) -> Self {
+        Self {
+            kind: EncoderErrorKind::AudioData,
+            source: Some(e.into()),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use crate::ssml::{tokenize, Token};
+
+/// Average duration of a word in languages that separate words by spaces (about 150 words per minute)
+const SECONDS_PER_WORD: f64 = 0.4;
+/// Average duration of a character in CJK languages (about 4.5 characters per second)
+const SECONDS_PER_CJK_CHAR: f64 = 0.22;
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK Unified Ideographs Extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+        | '\u{ac00}'..='\u{d7af}' // Hangul Syllables
+        | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2fa1f}' // CJK Unified Ideographs Extension B..
+    )
+}
+
+/// The speed multiplier of a speaking rate, as accepted by [`crate::TextOptions`].
+///
+/// Returns `None` if the rate is not recognized.
+pub fn rate_multiplier(rate: &str) -> Option<f64> {
+    match rate {
+        "x-slow" => Some(0.5),
+        "slow" => Some(0.64),
+        "default" | "medium" => Some(1.0),
+        "fast" => Some(1.55),
+        "x-fast" => Some(2.0),
+        _ => match rate.strip_suffix('%') {
+            Some(percentage) => percentage.parse::<f64>().ok().map(|p| 1.0 + p / 100.0),
+            None => rate.parse::<f64>().ok(),
+        },
+    }
+    .filter(|multiplier| *multiplier > 0.0)
+}
+
+/// Roughly estimate how long it takes to speak the text at the given rate.
+///
+/// The estimation only looks at the number of words(or CJK characters) and ignores pauses,
+/// so it is only good for sanity checks like detecting truncated output.
+pub fn estimate_speech_duration(text: &str, rate: Option<&str>) -> Duration {
+    let mut words = 0usize;
+    let mut cjk_chars = 0usize;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk_chars += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            words += usize::from(!in_word);
+            in_word = true;
+        } else if c != '\'' && c != '-' {
+            in_word = false;
+        }
+    }
+    let seconds = words as f64 * SECONDS_PER_WORD + cjk_chars as f64 * SECONDS_PER_CJK_CHAR;
+    let multiplier = rate.and_then(rate_multiplier).unwrap_or(1.0);
+    Duration::from_secs_f64(seconds / multiplier)
+}
+
+/// Estimate how many characters the service bills for synthesizing the SSML.
+///
+/// This follows the published billing rules as far as they can be checked offline:
+/// - Every character of the SSML is counted, including whitespace, punctuation and markup,
+///   except for the `<speak>` and `<voice>` tags.
+/// - CJK characters are counted twice.
+///
+/// It is an approximation, the service may count entities, whitespace or new markup differently.
+pub fn estimate_billed_characters(ssml: &str) -> usize {
+    let count = |s: &str| {
+        s.chars()
+            .map(|c| if is_cjk(c) { 2 } else { 1 })
+            .sum::<usize>()
+    };
+    tokenize(ssml)
+        .into_iter()
+        .map(|token| match token {
+            Token::Tag {
+                name: "speak" | "voice",
+                ..
+            } => 0,
+            Token::Tag { raw, .. } | Token::Other(raw) | Token::Comment(raw) => count(raw),
+            Token::Text(text) => count(text),
+        })
+        .sum()
+}
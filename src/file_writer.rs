@@ -0,0 +1,151 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use log::debug;
+use tokio::io::AsyncWrite;
+
+use crate::audio::riff_data_offset;
+
+/// Give up looking for the `data` chunk of a RIFF header after this many bytes
+const MAX_RIFF_HEADER_LEN: usize = 4096;
+
+enum RiffHeader {
+    /// Not enough bytes are written to tell, they are kept until the `data` chunk shows up
+    Pending(Vec<u8>),
+    /// The offset of the samples in the file
+    Found(u64),
+    /// The audio is not RIFF, e.g. raw PCM or MP3
+    Absent,
+}
+
+/// A sink that writes audio into a file as soon as it arrives,
+/// so that memory stays bounded and the audio written so far is kept even if the process dies.
+///
+/// Pass it to [`crate::WebsocketSynthesizer::synthesize_ssml_to_writer`], which writes the audio chunk by chunk
+/// and flushes the writer when the synthesis finishes(or more often, see [`crate::WebsocketSynthesizer::set_flush_interval`]).
+/// The chunks go through a [`BufWriter`] to save syscalls.
+/// It is a [`Write`] as well, e.g. to write the chunks passed to a callback.
+///
+/// The service does not know the length of streamed RIFF/WAV audio in advance, so its header carries bogus sizes.
+/// On every flush, the RIFF and `data` chunk sizes in the header are patched to the length written so far,
+/// which keeps the file a valid WAV file after each synthesis. Other formats are written as is.
+///
+/// The file should receive the audio of one synthesis, because the audio of every synthesis starts with its own header.
+/// The buffered audio is flushed and the header is patched on drop as well, but errors are ignored there,
+/// so call [`Self::finish`] to see them.
+pub struct AudioFileWriter {
+    file: BufWriter<File>,
+    written: u64,
+    patched: u64,
+    header: RiffHeader,
+}
+
+impl AudioFileWriter {
+    /// Create a writer that writes into `file` from its current position, which should be the start of the file.
+    pub fn new(file: File) -> Self {
+        Self {
+            file: BufWriter::new(file),
+            written: 0,
+            patched: 0,
+            header: RiffHeader::Pending(Vec::new()),
+        }
+    }
+
+    /// Create(or truncate) the file at `path` and write into it.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        File::create(path).map(Self::new)
+    }
+
+    /// The number of bytes written so far, including the ones that are still buffered
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Flush the buffered audio and patch the RIFF header, reporting errors unlike dropping the writer.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.sync()
+    }
+
+    fn write_audio(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.file.write(buf)?;
+        self.written += len as u64;
+        if let RiffHeader::Pending(header) = &mut self.header {
+            header.extend_from_slice(&buf[..len]);
+            let prefix_len = header.len().min(4);
+            self.header = if header[..prefix_len] != b"RIFF"[..prefix_len] {
+                RiffHeader::Absent
+            } else if let Some(offset) = riff_data_offset(header) {
+                debug!("Found the RIFF data chunk at offset {offset}");
+                RiffHeader::Found(offset as u64)
+            } else if header.len() >= MAX_RIFF_HEADER_LEN {
+                debug!("No RIFF data chunk in the first {MAX_RIFF_HEADER_LEN} bytes, the header is left as is");
+                RiffHeader::Absent
+            } else {
+                return Ok(len);
+            };
+        }
+        Ok(len)
+    }
+
+    /// Flush the buffered audio and patch the sizes in the RIFF header if more audio is written since the last patch
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let RiffHeader::Found(data_offset) = self.header else {
+            return Ok(());
+        };
+        if self.written == self.patched {
+            return Ok(());
+        }
+        let riff_size = u32::try_from(self.written - 8).unwrap_or(u32::MAX);
+        let data_size = u32::try_from(self.written - data_offset).unwrap_or(u32::MAX);
+        let file = self.file.get_mut();
+        file.seek(SeekFrom::Start(4))?;
+        file.write_all(&riff_size.to_le_bytes())?;
+        file.seek(SeekFrom::Start(data_offset - 4))?;
+        file.write_all(&data_size.to_le_bytes())?;
+        file.seek(SeekFrom::End(0))?;
+        self.patched = self.written;
+        Ok(())
+    }
+}
+
+impl Write for AudioFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_audio(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sync()
+    }
+}
+
+impl AsyncWrite for AudioFileWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.write_audio(buf))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.sync())
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.sync())
+    }
+}
+
+impl Drop for AudioFileWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.sync() {
+            debug!("Failed to flush the audio file on drop: {e}");
+        }
+    }
+}
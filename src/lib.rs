@@ -21,6 +21,16 @@
 //! let config = SynthesizerConfig::new(auth, AudioFormat::Riff16Khz16BitMonoPcm);
 //! ```
 //!
+//! Tokens expire after a while. For long-running services, install a [TokenProvider] that fetches
+//! fresh tokens when connecting, e.g. [SubscriptionKeyTokenProvider] that exchanges a subscription key for tokens.
+//!
+//! ```ignore
+//! use aspeak::SubscriptionKeyTokenProvider;
+//!
+//! let config = SynthesizerConfig::new(auth, AudioFormat::Riff16Khz16BitMonoPcm)
+//!     .with_token_provider(SubscriptionKeyTokenProvider::from_region("eastus", "YOUR_AZURE_SUBSCRIPTION_KEY"));
+//! ```
+//!
 //! ## RESTful Synthesizer
 //!
 //! Then, you can create a [RestSynthesizer][crate::synthesizer::RestSynthesizer]
@@ -79,6 +89,32 @@
 //! There is also a unified synthesizer trait [Synthesizer][crate::synthesizer::UnifiedSynthesizer] that can be used to
 //! provide a unified interface for both RESTful and Websocket synthesizers.
 //!
+//! # Errors
+//!
+//! Each synthesizer and API has its own error type, e.g. [RestSynthesizerError][crate::synthesizer::RestSynthesizerError]
+//! and [WebsocketSynthesizerError][crate::synthesizer::WebsocketSynthesizerError].
+//! All of them can be converted into [AspeakError] with `?` in functions returning [`aspeak::Result`][crate::Result].
+//! Its [kind][AspeakError::kind] tells what went wrong:
+//!
+//! - `Connect`: Failed to connect to the service or the proxy.
+//! - `Auth`: The service rejected the key or token.
+//! - `InvalidRequest`: The request was invalid, either caught early or rejected by the service.
+//! - `Http`: Other unsuccessful HTTP responses, e.g. too many requests.
+//! - `Websocket`: Websocket protocol errors, including the server closing the connection.
+//! - `Connection`: The connection broke after it was established.
+//! - `InvalidMessage`: The service sent something we do not understand.
+//! - `Ssml`: Errors while processing SSML.
+//! - `Timeout`: The operation did not finish in time.
+//! - `Io`: I/O errors.
+//! - `Audio`: Invalid audio data or errors while playing audio.
+//!
+//! ```ignore
+//! async fn speak(config: SynthesizerConfig<'_>, ssml: &str) -> aspeak::Result<Vec<u8>> {
+//!     let mut ws_syn = config.connect_websocket().await?;
+//!     Ok(ws_syn.synthesize_ssml(ssml).await?)
+//! }
+//! ```
+//!
 //! # TLS feature flags
 //!
 //! By default, this crate uses `native-tls`. To use other TLS implementations, you can use the following feature flags:
@@ -92,23 +128,42 @@
 //! # Feature flags
 //!
 //! - `rest-synthesizer`: Enable the RESTful synthesizer.
-//! - `websocket-synthesizer`: Enable the Websocket synthesizer.
+//! - `websocket-synthesizer`: Enable the Websocket synthesizer, along with the [`AudioFileWriter`]
+//!   that streams its audio into a file.
 //! - `unified-synthesizer`: Enable the unified synthesizer trait.
 //! - `synthesizers`: Enable all synthesizers.
+//! - `audio`: Enable audio playback. Together with `websocket-synthesizer`, it also enables
+//!   the [audio ring buffer][crate::audio_ring_buffer] for real-time playback.
+//! - `test-util`: Enable a [mock server][crate::test_util::MockServer] of the websocket API for offline testing.
 
 mod audio;
 mod auth;
+mod capabilities;
 mod constants;
+mod encoder;
 mod errors;
+mod estimate;
+#[cfg(feature = "websocket-synthesizer")]
+mod file_writer;
+mod metadata;
 #[cfg(feature = "websocket-synthesizer")]
 mod msg;
 #[cfg(feature = "websocket-synthesizer")]
 mod net;
-#[cfg(feature = "python")]
+mod output;
+// Only the Python bindings parse style degrees from strings
+#[cfg_attr(not(feature = "python"), allow(unused))]
 mod parse;
+#[cfg(all(feature = "audio", feature = "websocket-synthesizer"))]
+mod ring_buffer;
 mod ssml;
+mod subtitle;
 pub mod synthesizer;
+#[cfg(any(feature = "rest-synthesizer", feature = "websocket-synthesizer"))]
+mod token;
 pub use synthesizer::*;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 mod types;
 mod utils;
 pub mod voice;
@@ -124,10 +179,31 @@ pub fn get_rest_endpoint_by_region(region: &str) -> String {
     format!("https://{region}.tts.speech.microsoft.com/cognitiveservices/v1")
 }
 
-pub use audio::{AudioFormat, AudioFormatParseError, QUALITY_MAP, QUALITY_RANGE_MAP};
+pub use audio::{
+    concat_audio, concat_audio_with_crossfade, pcm_payload, sniff_audio, split_audio_by_sentences,
+    split_audio_by_words, truncate_audio, wav_header, AudioContainer, AudioDataError,
+    AudioDataErrorKind, AudioFormat, AudioFormatParseError, AudioFormatParseErrorKind, PcmEncoding,
+    QualityOverrides, SniffedAudio, QUALITY_MAP, QUALITY_RANGE_MAP,
+};
+#[cfg(feature = "audio")]
+pub use audio::{play_audio_blocking, AudioError, AudioErrorKind};
 pub use auth::*;
+pub use capabilities::*;
+pub use encoder::*;
+pub use errors::{AspeakError, AspeakErrorKind, ConnectError, ConnectErrorKind, Result};
+pub use estimate::*;
+#[cfg(feature = "websocket-synthesizer")]
+pub use file_writer::*;
+pub use metadata::*;
+pub use output::*;
+pub use parse::ParseError;
 use phf::phf_map;
+#[cfg(all(feature = "audio", feature = "websocket-synthesizer"))]
+pub use ring_buffer::*;
 pub use ssml::*;
+pub use subtitle::*;
+#[cfg(any(feature = "rest-synthesizer", feature = "websocket-synthesizer"))]
+pub use token::*;
 pub use types::*;
 
 #[cfg(feature = "python")]
@@ -142,8 +218,8 @@ pub mod python;
 ///
 /// # Returns
 ///
-/// A `Result` that contains the default voice as a static string slice if the
-/// specified locale is valid. Otherwise, an `AspeakError` is returned.
+/// The default voice as a static string slice if the specified locale is valid.
+/// Otherwise, `None` is returned.
 pub fn get_default_voice_by_locale(locale: &str) -> Option<&'static str> {
     DEFAULT_VOICES.get(locale).copied()
 }
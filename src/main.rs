@@ -4,16 +4,21 @@ use std::{
     borrow::Cow,
     error::Error,
     fmt::{self, Display, Formatter},
+    io::Write,
     path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
 };
 
 use cli::{commands::Command, Cli};
 
 use aspeak::{
-    AudioFormat, SynthesizerConfig, UnifiedSynthesizer, Voice, VoiceListAPIAuth,
-    VoiceListAPIEndpoint, VoiceListAPIError, VoiceListAPIErrorKind, QUALITY_MAP,
+    estimate_speech_duration, interpolate_ssml, synthesize_batch_with_checkpoint, truncate_audio,
+    AudioFormat, BatchCheckpoint, BatchPolicy, MetadataOptions, ShortAudioRetryPolicy,
+    SynthesizerConfig, UnifiedSynthesizer, UnifiedSynthesizerError, Voice, VoiceFilter,
+    VoiceListAPIAuth, VoiceListAPIEndpoint, VoiceListAPIError, VoiceListAPIErrorKind, QUALITY_MAP,
 };
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use color_eyre::{
     eyre::{anyhow, eyre},
     Help,
@@ -21,15 +26,18 @@ use color_eyre::{
 use colored::Colorize;
 
 use env_logger::WriteStyle;
-use log::debug;
+use log::{debug, info, warn};
 
 use reqwest::header::HeaderMap;
 use strum::IntoEnumIterator;
 
 use crate::cli::{
-    args::{Color, SynthesizerMode},
+    args::{Color, SweepParameter, SynthesizerMode, VoiceListFormat},
     commands::ConfigCommand,
+    completions,
     config::{Config, EndpointConfig},
+    progress::Progress,
+    voice_cache::{VoiceCache, DEFAULT_VOICE_LIST_CACHE_TTL},
 };
 
 #[derive(Debug)]
@@ -62,6 +70,49 @@ async fn synthesizer_by_mode(
     })
 }
 
+/// Synthesize the SSML, showing the progress if it is enabled
+async fn process_ssml_with_progress(
+    synthesizer: &mut dyn UnifiedSynthesizer,
+    ssml: &str,
+    progress: Option<Progress>,
+) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+    let Some(mut progress) = progress else {
+        return synthesizer.process_ssml(ssml).await;
+    };
+    let result = synthesizer
+        .process_ssml_with_progress(ssml, &mut |bytes| progress.update(bytes))
+        .await;
+    progress.finish();
+    result
+}
+
+/// Synthesize the SSML into the streamed output chunk by chunk, showing the progress if it is enabled.
+/// Returns the number of bytes written.
+async fn process_ssml_to_output(
+    synthesizer: &mut dyn UnifiedSynthesizer,
+    ssml: &str,
+    output: &mut (dyn Write + Send),
+    mut progress: Option<Progress>,
+) -> Result<usize, UnifiedSynthesizerError> {
+    let mut written = 0;
+    let result = synthesizer
+        .process_ssml_streamed(ssml, &mut |chunk| {
+            output.write_all(chunk)?;
+            written += chunk.len();
+            if let Some(progress) = progress.as_mut() {
+                progress.update(written);
+            }
+            Ok(())
+        })
+        .await;
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    result?;
+    output.flush()?;
+    Ok(written)
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> color_eyre::eyre::Result<()> {
     let mut cli = Cli::parse();
@@ -86,82 +137,590 @@ async fn main() -> color_eyre::eyre::Result<()> {
     debug!("Commandline args: {cli:?}");
     debug!("Profile: {config:?}");
     let Cli { command, auth, .. } = cli;
-    match command.unwrap_or_default() {
+    let auth_config = auth.load_auth_config(config.as_ref().and_then(|c| c.auth.as_ref()))?;
+    let command = match command.unwrap_or_default() {
+        Command::Synthesize {
+            input_format,
+            text_args,
+            input_args,
+            output_args,
+            subtitle_args,
+        } => Cli::route_synthesize_command(
+            input_format,
+            text_args,
+            input_args,
+            output_args,
+            subtitle_args,
+        )?,
+        command => command,
+    };
+    match command {
         Command::Ssml {
             ssml,
+            minify_ssml,
+            max_request_size,
+            estimate,
             input_args,
             output_args,
         } => {
-            let mode = Cli::get_synthesizer_mode(&input_args, &config);
-            let auth_options =
-                auth.to_auth_options(config.as_ref().and_then(|c| c.auth.as_ref()), mode)?;
-            debug!("Auth options: {auth_options:?}");
             let ssml = ssml
                 .ok_or(CliError::Input)
                 .or_else(|_| Cli::process_input_text(&input_args))?;
+            let ssml = if minify_ssml {
+                aspeak::minify_ssml(&ssml)
+            } else {
+                ssml
+            };
+            if estimate {
+                match max_request_size {
+                    Some(max_len) => Cli::print_estimate(&aspeak::split_ssml(&ssml, max_len)?),
+                    None => Cli::print_estimate(&[&ssml]),
+                }
+                return Ok(());
+            }
+            let mode = Cli::get_synthesizer_mode(&input_args, auth_config.as_ref());
+            let auth_options = auth.to_auth_options(auth_config.as_ref(), mode)?;
+            debug!("Auth options: {auth_options:?}");
             let audio_format =
                 output_args.get_audio_format(config.as_ref().and_then(|c| c.output.as_ref()))?;
-            let callback = Cli::process_output(output_args.output, output_args.overwrite)?;
-            let conf = SynthesizerConfig::new(auth_options, audio_format);
+            let encoder = output_args.encoder_for(audio_format).map(str::to_string);
+            // The audio is streamed into the output unless it has to be processed as a whole
+            let streamed = if encoder.is_none()
+                && max_request_size.is_none()
+                && output_args.max_bytes.is_none()
+            {
+                Cli::process_streamed_output(
+                    output_args.output.as_deref(),
+                    output_args.overwrite,
+                    mode,
+                )?
+            } else {
+                None
+            };
+            let callback = streamed
+                .is_none()
+                .then(|| {
+                    Cli::encode_output(
+                        Cli::process_output(
+                            output_args.output,
+                            output_args.overwrite,
+                            audio_format,
+                        )?,
+                        encoder.as_deref(),
+                        audio_format,
+                    )
+                })
+                .transpose()?;
+            let conf = auth.apply_timeouts(SynthesizerConfig::new(auth_options, audio_format))?;
             let mut synthesizer = synthesizer_by_mode(conf, mode).await?;
-            let audio_data = synthesizer.process_ssml(&ssml).await?;
+            if let Some(mut output) = streamed {
+                let progress = output_args
+                    .progress
+                    .then(|| Progress::new(audio_format, None))
+                    .flatten();
+                let written =
+                    process_ssml_to_output(synthesizer.as_mut(), &ssml, output.as_mut(), progress)
+                        .await?;
+                Cli::summarize_audio(
+                    Cli::audio_duration(
+                        audio_format,
+                        None,
+                        synthesizer.audio_metadata().unwrap_or_default(),
+                    ),
+                    written,
+                    output_args.progress,
+                );
+                return Ok(());
+            }
+            let callback = callback.expect("the output is processed when not streaming");
+            let audio_data = match max_request_size {
+                Some(max_len) => {
+                    let chunks = aspeak::split_ssml(&ssml, max_len)?;
+                    debug!("Split SSML into {} chunks", chunks.len());
+                    let mut segments = Vec::with_capacity(chunks.len());
+                    for chunk in chunks {
+                        segments.push(synthesizer.process_ssml(&chunk).await?);
+                    }
+                    let mut audio_data = aspeak::concat_audio(audio_format, &segments)?;
+                    if let Some(max_bytes) = output_args.max_bytes {
+                        Cli::warn_if_truncated(
+                            truncate_audio(audio_format, &mut audio_data, max_bytes),
+                            max_bytes,
+                        );
+                    }
+                    audio_data
+                }
+                None => match output_args.max_bytes {
+                    Some(max_bytes) => {
+                        let (audio_data, truncated) = synthesizer
+                            .process_ssml_with_max_bytes(&ssml, max_bytes)
+                            .await?;
+                        Cli::warn_if_truncated(truncated, max_bytes);
+                        audio_data
+                    }
+                    None => {
+                        let progress = output_args
+                            .progress
+                            .then(|| Progress::new(audio_format, None))
+                            .flatten();
+                        process_ssml_with_progress(synthesizer.as_mut(), &ssml, progress).await?
+                    }
+                },
+            };
+            // The metadata only covers the last chunk when the SSML is split
+            Cli::summarize_audio(
+                Cli::audio_duration(
+                    audio_format,
+                    Some(&audio_data),
+                    synthesizer
+                        .audio_metadata()
+                        .filter(|_| max_request_size.is_none())
+                        .unwrap_or_default(),
+                ),
+                audio_data.len(),
+                output_args.progress,
+            );
             callback(audio_data)?;
         }
         Command::Text {
             text_args,
             input_args,
             output_args,
+            subtitle_args,
+            dump_ssml,
+            report,
+            estimate,
+            max_chunk_chars,
+            batch_args,
         } => {
-            let mode = Cli::get_synthesizer_mode(&input_args, &config);
-            let auth_options =
-                auth.to_auth_options(config.as_ref().and_then(|c| c.auth.as_ref()), mode)?;
-            debug!("Auth options: {auth_options:?}");
+            if let Some(batch) = batch_args.batch.as_deref() {
+                let lines = Cli::process_batch_input(batch, &input_args)?;
+                let options = Cli::process_text_options(
+                    &text_args,
+                    config.as_ref().and_then(|c| c.text.as_ref()),
+                )?;
+                let ssmls = lines
+                    .iter()
+                    .map(|(_, text)| interpolate_ssml(text, &options))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mode = Cli::get_synthesizer_mode(&input_args, auth_config.as_ref());
+                let auth_options = auth.to_auth_options(auth_config.as_ref(), mode)?;
+                debug!("Auth options: {auth_options:?}");
+                let audio_format = output_args
+                    .get_audio_format(config.as_ref().and_then(|c| c.output.as_ref()))?;
+                let (encode, extension) =
+                    Cli::batch_encoder(output_args.encoder.as_deref(), audio_format)?;
+                let checkpoint_path = batch_args.checkpoint_path();
+                let resumed = batch_args
+                    .resume
+                    .then(|| BatchCheckpoint::open(&checkpoint_path))
+                    .transpose()?;
+                // Check all output files before connecting so that we fail early.
+                // Resuming overwrites the files of the lines that are not completed.
+                let outputs = Cli::process_batch_outputs(
+                    &batch_args,
+                    &lines,
+                    &extension,
+                    output_args.overwrite || batch_args.resume,
+                )?;
+                let skipped = resumed.as_ref().map_or(0, |checkpoint| {
+                    (0..ssmls.len())
+                        .filter(|&index| checkpoint.is_completed(index, &ssmls[index]))
+                        .count()
+                });
+                let checkpoint = match resumed {
+                    Some(checkpoint) => {
+                        info!(
+                            "Resuming the batch, {skipped} of {} lines are completed",
+                            ssmls.len()
+                        );
+                        checkpoint
+                    }
+                    None => {
+                        // Start over instead of resuming from a checkpoint of an earlier batch
+                        match std::fs::remove_file(&checkpoint_path) {
+                            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                            _ => Ok(()),
+                        }?;
+                        BatchCheckpoint::open(&checkpoint_path)?
+                    }
+                };
+                let conf =
+                    auth.apply_timeouts(SynthesizerConfig::new(auth_options, audio_format))?;
+                let jobs = (batch_args.jobs.unwrap_or(4) as usize).min(ssmls.len() - skipped);
+                let mut synthesizers = Vec::with_capacity(jobs);
+                for _ in 0..jobs {
+                    synthesizers.push(synthesizer_by_mode(conf.clone(), mode).await?);
+                }
+                let done = AtomicUsize::new(skipped);
+                let results = synthesize_batch_with_checkpoint(
+                    &mut synthesizers,
+                    &ssmls,
+                    BatchPolicy::ContinueOnError,
+                    &checkpoint,
+                    |index, audio| {
+                        std::fs::write(&outputs[index], encode(audio)?)?;
+                        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        eprintln!("[{done}/{}] {}", ssmls.len(), outputs[index].display());
+                        Ok(())
+                    },
+                )
+                .await?;
+                let failures: Vec<_> = results
+                    .iter()
+                    .zip(lines.iter())
+                    .filter_map(|(result, (line, _))| {
+                        Some((line, result.as_ref()?.as_ref().err()?))
+                    })
+                    .collect();
+                for (line, e) in failures.iter() {
+                    eprintln!("{} {line}: {e}", "Line".red());
+                }
+                if !failures.is_empty() {
+                    return Err(eyre!(
+                        "{} of {} lines failed to synthesize",
+                        failures.len(),
+                        lines.len()
+                    )
+                    .with_suggestion(|| {
+                        "Run the same command with --resume to synthesize only the lines that are not completed."
+                    }));
+                }
+                return Ok(());
+            }
             let text = text_args
                 .text
                 .as_deref()
                 .map(Cow::Borrowed)
                 .ok_or(CliError::Input)
                 .or_else(|_| Cli::process_input_text(&input_args).map(Cow::Owned))?;
+            if estimate {
+                let options = Cli::process_text_options(
+                    &text_args,
+                    config.as_ref().and_then(|c| c.text.as_ref()),
+                )?;
+                Cli::print_estimate(&[interpolate_ssml(&text, &options)?]);
+                return Ok(());
+            }
+            let mode = Cli::get_synthesizer_mode(&input_args, auth_config.as_ref());
+            let subtitle_generator = subtitle_args.to_generator()?;
+            if subtitle_args.subtitles.is_some() && mode != SynthesizerMode::Websocket {
+                return Err(eyre!("Subtitles are only available in websocket mode")
+                    .with_note(|| "The RESTful API does not report word boundaries.")
+                    .with_suggestion(|| "Use --mode websocket to generate subtitles."));
+            }
+            let auth_options = auth.to_auth_options(auth_config.as_ref(), mode)?;
+            debug!("Auth options: {auth_options:?}");
+            let is_trial = auth_options.key().is_none() && auth_options.token().is_none();
             let audio_format =
                 output_args.get_audio_format(config.as_ref().and_then(|c| c.output.as_ref()))?;
-            let callback = Cli::process_output(output_args.output, output_args.overwrite)?;
-            let conf = SynthesizerConfig::new(auth_options, audio_format);
-            let mut synthesizer = synthesizer_by_mode(conf, mode).await?;
+            if max_chunk_chars.is_some() && Into::<&str>::into(audio_format).starts_with("webm-") {
+                return Err(eyre!(
+                    "Audio in format {} can not be concatenated",
+                    Into::<&str>::into(audio_format)
+                )
+                .with_note(|| "--max-chunk-chars concatenates the audio of the chunks.")
+                .with_suggestion(|| "Use another container format like ogg or mp3."));
+            }
             let options = &Cli::process_text_options(
                 &text_args,
                 config.as_ref().and_then(|c| c.text.as_ref()),
             )?;
-            let result = synthesizer.process_text(&text, options).await;
+            if let Some(voice) = VoiceCache::cached_voice(options.voice()) {
+                voice.check_text_options(options).with_suggestion(|| {
+                    format!(
+                        "Run `aspeak list-voices --voice {} --styles` or `--roles` to see what the voice supports.",
+                        voice.short_name()
+                    )
+                })?;
+            } else {
+                debug!(
+                    "Voice {} is not in the voice list cache, its styles and roles are not checked",
+                    options.voice()
+                );
+            }
+            let encoder = output_args.encoder_for(audio_format).map(str::to_string);
+            // Create all output files before connecting so that we fail early
+            let sweep_takes = text_args
+                .sweep
+                .as_ref()
+                .map(|sweep| {
+                    Cli::process_sweep_outputs(
+                        sweep,
+                        output_args.output.as_deref().unwrap(),
+                        output_args.overwrite,
+                        audio_format,
+                        encoder.as_deref(),
+                    )
+                })
+                .transpose()?;
+            // The audio is streamed into the output unless it has to be processed as a whole
+            let streamed = if sweep_takes.is_none()
+                && encoder.is_none()
+                && max_chunk_chars.is_none()
+                && output_args.max_bytes.is_none()
+                && text_args.retry_short_audio.is_none()
+                && report.is_none()
+            {
+                Cli::process_streamed_output(
+                    output_args.output.as_deref(),
+                    output_args.overwrite,
+                    mode,
+                )?
+            } else {
+                None
+            };
+            let callback = if sweep_takes.is_none() && streamed.is_none() {
+                Some(Cli::encode_output(
+                    Cli::process_output(output_args.output, output_args.overwrite, audio_format)?,
+                    encoder.as_deref(),
+                    audio_format,
+                )?)
+            } else {
+                None
+            };
+            // Dump the SSML before connecting so that it is kept even if the synthesis fails
+            let ssml = dump_ssml
+                .as_deref()
+                .map(|path| -> color_eyre::Result<String> {
+                    let ssml = interpolate_ssml(&text, options)?;
+                    std::fs::write(path, &ssml)?;
+                    Ok(ssml)
+                })
+                .transpose()?;
+            let mut conf =
+                auth.apply_timeouts(SynthesizerConfig::new(auth_options, audio_format))?;
+            if subtitle_args.subtitles.is_some() {
+                // Subtitle cues break at sentence boundaries
+                conf = conf
+                    .with_metadata_options(MetadataOptions::default().with_sentence_boundary(true));
+            }
+            let connect_start = Instant::now();
+            let mut synthesizer = synthesizer_by_mode(conf.clone(), mode).await?;
+            let connect_time = connect_start.elapsed();
+            if let Some(takes) = sweep_takes {
+                // All takes are synthesized over the same synthesizer(connection)
+                let parameter = text_args.sweep.as_ref().unwrap().parameter;
+                let mut options = options.clone();
+                for (value, path, callback) in takes {
+                    let value = Some(Cow::Borrowed(value));
+                    match parameter {
+                        SweepParameter::Rate => *options.rate_mut() = value,
+                        SweepParameter::Pitch => *options.pitch_mut() = value,
+                    }
+                    let audio_data = match output_args.max_bytes {
+                        Some(max_bytes) => {
+                            let (audio_data, truncated) = synthesizer
+                                .process_ssml_with_max_bytes(
+                                    &interpolate_ssml(&text, &options)?,
+                                    max_bytes,
+                                )
+                                .await?;
+                            Cli::warn_if_truncated(truncated, max_bytes);
+                            audio_data
+                        }
+                        None => synthesizer.process_text(&text, &options).await?,
+                    };
+                    callback(audio_data)?;
+                    println!("{}", path.display());
+                }
+                return Ok(());
+            }
+            let progress = output_args
+                .progress
+                .then(|| {
+                    Progress::new(
+                        audio_format,
+                        Some(estimate_speech_duration(&text, options.rate())),
+                    )
+                })
+                .flatten();
+            let synthesis_start = Instant::now();
+            if let Some(mut output) = streamed {
+                let ssml = match ssml {
+                    Some(ssml) => ssml,
+                    None => interpolate_ssml(&text, options)?,
+                };
+                let result =
+                    process_ssml_to_output(synthesizer.as_mut(), &ssml, output.as_mut(), progress)
+                        .await;
+                let written = match (result, text_args.fallback_voice.as_deref()) {
+                    (Err(e), Some(fallback)) if e.is_voice_unavailable() => {
+                        warn!(
+                            "Voice {} is not available, falling back to voice {fallback}: {e}",
+                            options.voice()
+                        );
+                        let mut options = options.clone();
+                        *options.voice_mut() = Cow::Borrowed(fallback);
+                        // The websocket connection is closed by the server on error
+                        synthesizer = synthesizer_by_mode(conf, mode).await?;
+                        let ssml = interpolate_ssml(&text, &options)?;
+                        process_ssml_to_output(synthesizer.as_mut(), &ssml, output.as_mut(), None)
+                            .await
+                    }
+                    (result, _) => result,
+                }?;
+                let metadata = synthesizer.audio_metadata().unwrap_or_default();
+                let duration = Cli::audio_duration(audio_format, None, metadata);
+                Cli::summarize_audio(duration, written, output_args.progress);
+                if is_trial {
+                    Cli::check_truncation(&text, options, duration);
+                }
+                if let Some(path) = subtitle_args.subtitles.as_deref() {
+                    Cli::write_subtitles(path, &subtitle_generator, metadata)?;
+                }
+                return Ok(());
+            }
+            let callback =
+                callback.expect("the output is processed when not sweeping or streaming");
+            let mut voice = options.voice();
+            // The concatenated audio of the chunks is capped after synthesizing all of them
+            let cap_chunks = |mut audio_data: Vec<u8>| {
+                if let Some(max_bytes) = output_args.max_bytes {
+                    Cli::warn_if_truncated(
+                        truncate_audio(audio_format, &mut audio_data, max_bytes),
+                        max_bytes,
+                    );
+                }
+                audio_data
+            };
+            let result = match (ssml.as_deref(), output_args.max_bytes) {
+                _ if max_chunk_chars.is_some() => synthesizer
+                    .process_text_in_chunks(&text, options, max_chunk_chars.unwrap())
+                    .await
+                    .map(cap_chunks),
+                (ssml, Some(max_bytes)) => {
+                    let ssml = match ssml {
+                        Some(ssml) => Cow::Borrowed(ssml),
+                        None => Cow::Owned(interpolate_ssml(&text, options)?),
+                    };
+                    synthesizer
+                        .process_ssml_with_max_bytes(&ssml, max_bytes)
+                        .await
+                        .map(|(audio_data, truncated)| {
+                            Cli::warn_if_truncated(truncated, max_bytes);
+                            audio_data
+                        })
+                }
+                (Some(ssml), None) => match text_args.retry_short_audio {
+                    Some(retries) => {
+                        synthesizer
+                            .process_ssml_with_short_audio_retry(
+                                ssml,
+                                estimate_speech_duration(&text, options.rate()),
+                                &ShortAudioRetryPolicy::new(retries),
+                            )
+                            .await
+                    }
+                    None => process_ssml_with_progress(synthesizer.as_mut(), ssml, progress).await,
+                },
+                (None, None) => match text_args.retry_short_audio {
+                    Some(retries) => {
+                        synthesizer
+                            .process_text_with_short_audio_retry(
+                                &text,
+                                options,
+                                &ShortAudioRetryPolicy::new(retries),
+                            )
+                            .await
+                    }
+                    None => {
+                        let ssml = interpolate_ssml(&text, options)?;
+                        process_ssml_with_progress(synthesizer.as_mut(), &ssml, progress).await
+                    }
+                },
+            };
+            let result = match (result, text_args.fallback_voice.as_deref()) {
+                (Err(e), Some(fallback)) if e.is_voice_unavailable() => {
+                    warn!(
+                        "Voice {} is not available, falling back to voice {fallback}: {e}",
+                        options.voice()
+                    );
+                    let mut options = options.clone();
+                    *options.voice_mut() = Cow::Borrowed(fallback);
+                    voice = fallback;
+                    // The websocket connection is closed by the server on error
+                    synthesizer = synthesizer_by_mode(conf, mode).await?;
+                    let ssml = interpolate_ssml(&text, &options)?;
+                    match output_args.max_bytes {
+                        _ if max_chunk_chars.is_some() => synthesizer
+                            .process_text_in_chunks(&text, &options, max_chunk_chars.unwrap())
+                            .await
+                            .map(cap_chunks),
+                        Some(max_bytes) => synthesizer
+                            .process_ssml_with_max_bytes(&ssml, max_bytes)
+                            .await
+                            .map(|(audio_data, truncated)| {
+                                Cli::warn_if_truncated(truncated, max_bytes);
+                                audio_data
+                            }),
+                        None => synthesizer.process_ssml(&ssml).await,
+                    }
+                }
+                (result, _) => result,
+            };
             let audio_data = result?;
+            let synthesis_time = synthesis_start.elapsed();
+            // The metadata only covers the last chunk when the text is split
+            let duration = Cli::audio_duration(
+                audio_format,
+                Some(&audio_data),
+                synthesizer
+                    .audio_metadata()
+                    .filter(|_| max_chunk_chars.is_none())
+                    .unwrap_or_default(),
+            );
+            Cli::summarize_audio(duration, audio_data.len(), output_args.progress);
+            if is_trial {
+                Cli::check_truncation(&text, options, duration);
+            }
+            if let Some(path) = subtitle_args.subtitles.as_deref() {
+                let metadata = synthesizer.audio_metadata().unwrap_or_default();
+                Cli::write_subtitles(path, &subtitle_generator, metadata)?;
+            }
+            if let Some(path) = report.as_deref() {
+                Cli::write_report(
+                    path,
+                    connect_time,
+                    synthesis_time,
+                    synthesizer.audio_metadata().unwrap_or_default(),
+                    audio_format,
+                    voice,
+                    &audio_data,
+                )?;
+            }
             callback(audio_data)?;
         }
         Command::ListVoices {
             ref voice,
             ref locale,
             ref url,
+            ref gender,
+            ref style,
+            refresh,
+            format,
+            json,
+            verbose,
+            styles,
+            roles,
         } => {
-            let auth_options = auth.to_auth_options(
-                config.as_ref().and_then(|c| c.auth.as_ref()),
-                SynthesizerMode::Rest,
-            )?;
+            let auth_options = auth.to_auth_options(auth_config.as_ref(), SynthesizerMode::Rest)?;
             debug!("Auth options: {auth_options:?}");
             // Look for --url first,
             // then look for auth.voice_list_api in profile,
             // then try to determine the url by region
             // otherwise, try to use the trial voice list url
             let url = url.as_deref().map(Cow::Borrowed).or_else(|| {
-                    config.as_ref().and_then(|c| c.auth.as_ref().and_then(|a| a.voice_list_api.as_deref().map(Cow::Borrowed)))
+                    auth_config.as_ref().and_then(|a| a.voice_list_api.as_deref().map(Cow::Borrowed))
                 }).or_else(|| {
-                    auth.region.as_deref().or_else(||
-                        config.as_ref().and_then(
-                            |c| c.auth.as_ref().and_then(
-                                |a| a.endpoint_config.as_ref().and_then(
-                                    |e| if let EndpointConfig::Region { ref region } =  e {
-                                        Some(region.as_str())
-                                    } else {
-                                        None
-                                    }
-                                )
+                    auth.region().or_else(||
+                        auth_config.as_ref().and_then(
+                            |a| a.endpoint_config.as_ref().and_then(
+                                |e| if let EndpointConfig::Region { ref region } =  e {
+                                    Some(Cow::Borrowed(region.as_str()))
+                                } else {
+                                    None
+                                }
                             )
                         )
                     ).map(|r| Cow::Owned(format!("https://{r}.tts.speech.microsoft.com/cognitiveservices/voices/list")))
@@ -172,53 +731,86 @@ async fn main() -> color_eyre::eyre::Result<()> {
                         .with_note(|| "The default voice list API that is used in aspeak v4 has been shutdown and is no longer available.")
                         .with_suggestion(|| "You can still use the list-voices command by specifying a region(authentication needed) or a custom voice list API url.")
                 )?;
-            let auth = match (auth_options.key(), auth_options.token()) {
-                (_, Some(token)) => Some(VoiceListAPIAuth::AuthToken(token)),
-                (Some(key), None) => Some(VoiceListAPIAuth::SubscriptionKey(key)),
-                (None, None) => None,
-            };
-            let voices_result = Voice::request_available_voices_with_additional_headers(
-                VoiceListAPIEndpoint::Url(url.as_ref()),
-                auth,
-                auth_options.proxy(),
-                Some(HeaderMap::from_iter(
-                    auth_options.headers().iter().map(Clone::clone),
-                )),
-            )
-            .await;
-            let voices = if let Err(VoiceListAPIError {
-                kind: VoiceListAPIErrorKind::Response,
-                ..
-            }) = voices_result
-            {
-                voices_result.with_note(|| "Maybe you are not authorized. Did you specify an auth token or a subscription key? Did the key/token expire?")?
+            let ttl = auth_config
+                .as_ref()
+                .and_then(|a| a.voice_list_cache_ttl)
+                .map_or(DEFAULT_VOICE_LIST_CACHE_TTL, Duration::from_secs);
+            let cache = (!ttl.is_zero())
+                .then(|| VoiceCache::new(&url, ttl))
+                .flatten();
+            let cached = cache
+                .as_ref()
+                .filter(|_| !refresh)
+                .and_then(|cache| cache.load());
+            let voices = if let Some(voices) = cached {
+                voices
             } else {
-                voices_result?
-            };
-            let voices = voices.iter();
-            let locale_id = locale.as_deref();
-            let voice_id = voice.as_deref();
-            let voices: Box<dyn Iterator<Item = &Voice>> = {
-                if locale_id.is_some() {
-                    Box::new(voices.filter(|voice| Some(voice.locale()) == locale_id))
-                } else if voice_id.is_some() {
-                    Box::new(voices.filter(|voice| Some(voice.short_name()) == voice_id))
+                let auth = match (auth_options.key(), auth_options.token()) {
+                    (_, Some(token)) => Some(VoiceListAPIAuth::AuthToken(token)),
+                    (Some(key), None) => Some(VoiceListAPIAuth::SubscriptionKey(key)),
+                    (None, None) => None,
+                };
+                let voices_result = Voice::request_available_voices_with_additional_headers(
+                    VoiceListAPIEndpoint::Url(url.as_ref()),
+                    auth,
+                    auth_options.proxy(),
+                    Some(HeaderMap::from_iter(
+                        auth_options.headers().iter().map(Clone::clone),
+                    )),
+                )
+                .await;
+                let voices = if let Err(VoiceListAPIError {
+                    kind: VoiceListAPIErrorKind::Response,
+                    ..
+                }) = voices_result
+                {
+                    voices_result.with_note(|| "Maybe you are not authorized. Did you specify an auth token or a subscription key? Did the key/token expire?")?
                 } else {
-                    Box::new(voices)
+                    voices_result?
+                };
+                if let Some(cache) = cache.as_ref() {
+                    if let Err(e) = cache.store(&voices) {
+                        warn!("Failed to cache the voice list: {e}");
+                    }
                 }
+                voices
             };
-            for voice in voices {
-                println!("{voice}");
+            let filter = VoiceFilter::new()
+                .locales(locale)
+                .optional_short_name(voice.as_deref())
+                .optional_gender(gender.as_deref())
+                .optional_style(style.as_deref());
+            let voices: Vec<&Voice> = filter.filter(&voices).collect();
+            if let Some(short_name) = voice.as_deref().filter(|_| styles || roles) {
+                return Cli::print_voice_styles(&voices, short_name, roles);
             }
+            let format = if json { VoiceListFormat::Json } else { format };
+            Cli::print_voices(&voices, format, verbose)?;
         }
         Command::ListQualities => {
+            let overrides = config
+                .as_ref()
+                .and_then(|c| c.output.as_ref())
+                .map(|o| o.quality_overrides())
+                .transpose()?
+                .unwrap_or_default();
             for (container, qualities) in QUALITY_MAP.into_iter() {
                 println!(
                     "{} {}:",
                     "Qualities for".cyan(),
                     container.to_uppercase().cyan()
                 );
+                let overrides = overrides.get(*container);
                 for (quality, format) in qualities.into_iter() {
+                    let format = overrides.and_then(|o| o.get(quality)).unwrap_or(format);
+                    println!("{:>3}: {}", quality, Into::<&str>::into(format));
+                }
+                // Quality levels that are only defined in the overrides
+                for (quality, format) in overrides
+                    .into_iter()
+                    .flatten()
+                    .filter(|(quality, _)| !qualities.contains_key(quality))
+                {
                     println!("{:>3}: {}", quality, Into::<&str>::into(format));
                 }
                 println!()
@@ -249,7 +841,27 @@ async fn main() -> color_eyre::eyre::Result<()> {
             ConfigCommand::Where => {
                 println!("{}", Config::default_location()?.display());
             }
+            ConfigCommand::ListProfiles { path } => {
+                let config = Config::load(path.as_ref())?
+                    .ok_or_else(|| anyhow!("The default profile does not exist"))?;
+                for name in config.profile_names() {
+                    println!("{name}");
+                }
+            }
         },
+        Command::Completions {
+            shell,
+            cached_voices,
+        } => {
+            if cached_voices {
+                for name in VoiceCache::cached_short_names() {
+                    println!("{name}");
+                }
+            } else if let Some(shell) = shell {
+                completions::generate(shell, &mut Cli::command(), &mut std::io::stdout())?;
+            }
+        }
+        Command::Synthesize { .. } => unreachable!("the synthesize command is routed above"),
     }
     Ok(())
 }
@@ -0,0 +1,363 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+/// Convert a duration in 100-nanosecond ticks (the unit used by the service) into a [`Duration`].
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::from_nanos(ticks.saturating_mul(100))
+}
+
+/// The kind of a boundary reported by the service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[non_exhaustive]
+pub enum BoundaryType {
+    #[serde(rename = "WordBoundary")]
+    Word,
+    #[serde(rename = "PunctuationBoundary")]
+    Punctuation,
+    #[serde(rename = "SentenceBoundary")]
+    Sentence,
+}
+
+/// A word(or punctuation/sentence) boundary reported by the service while synthesizing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordBoundary {
+    /// The text of this boundary
+    pub text: String,
+    /// Offset from the beginning of the audio
+    pub offset: Duration,
+    /// Duration of the audio of this boundary
+    pub duration: Duration,
+    /// The kind of this boundary
+    pub boundary_type: BoundaryType,
+}
+
+impl WordBoundary {
+    /// The offset where the audio of this boundary ends
+    pub fn end(&self) -> Duration {
+        self.offset + self.duration
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawMetadataMessage {
+    metadata: Vec<RawMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawMetadata {
+    #[serde(rename = "Type")]
+    kind: String,
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawBoundaryData {
+    offset: u64,
+    duration: u64,
+    #[serde(rename = "text")]
+    text: RawBoundaryText,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawBoundaryText {
+    text: String,
+    boundary_type: BoundaryType,
+}
+
+fn parse_boundaries_into(
+    body: &str,
+    kinds: &[&str],
+    boundaries: &mut Vec<WordBoundary>,
+) -> Result<(), MetadataError> {
+    let message: RawMetadataMessage = serde_json::from_str(body)?;
+    for item in message
+        .metadata
+        .into_iter()
+        .filter(|m| kinds.contains(&m.kind.as_str()))
+    {
+        let data: RawBoundaryData = serde_json::from_value(item.data)?;
+        boundaries.push(WordBoundary {
+            text: data.text.text,
+            offset: ticks_to_duration(data.offset),
+            duration: ticks_to_duration(data.duration),
+            boundary_type: data.text.boundary_type,
+        });
+    }
+    Ok(())
+}
+
+fn parse_boundaries<S: AsRef<str>>(
+    metadata: &[S],
+    kind: &str,
+) -> Result<Vec<WordBoundary>, MetadataError> {
+    let mut boundaries = Vec::new();
+    for body in metadata {
+        parse_boundaries_into(body.as_ref(), &[kind], &mut boundaries)?;
+    }
+    Ok(boundaries)
+}
+
+/// Parse all the word, punctuation and sentence boundaries out of a single raw audio metadata body,
+/// in the order they are reported.
+///
+/// This is useful for processing the boundaries as they arrive instead of collecting all of them first.
+pub fn parse_boundaries_in_message(body: &str) -> Result<Vec<WordBoundary>, MetadataError> {
+    let mut boundaries = Vec::new();
+    parse_boundaries_into(body, &["WordBoundary", "SentenceBoundary"], &mut boundaries)?;
+    Ok(boundaries)
+}
+
+/// Parse the word and punctuation boundaries out of the raw audio metadata bodies
+/// collected by [`crate::synthesizer::WebsocketSynthesizer`].
+pub fn parse_word_boundaries<S: AsRef<str>>(
+    metadata: &[S],
+) -> Result<Vec<WordBoundary>, MetadataError> {
+    parse_boundaries(metadata, "WordBoundary")
+}
+
+/// Parse the sentence boundaries out of the raw audio metadata bodies.
+///
+/// The text of a sentence boundary is the whole sentence.
+pub fn parse_sentence_boundaries<S: AsRef<str>>(
+    metadata: &[S],
+) -> Result<Vec<WordBoundary>, MetadataError> {
+    parse_boundaries(metadata, "SentenceBoundary")
+}
+
+/// An event reported by the service in the audio metadata while synthesizing.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SynthesisEvent {
+    /// A word or punctuation boundary
+    WordBoundary(WordBoundary),
+    /// A sentence boundary, whose text is the whole sentence
+    SentenceBoundary(WordBoundary),
+    /// A viseme, i.e. the position of the lips and face when producing a sound, for lip-sync and animation
+    Viseme {
+        /// The id of the viseme, see the viseme documentation of Azure TTS for the mapping to phonemes
+        id: u32,
+        /// Offset from the beginning of the audio
+        offset: Duration,
+        /// The raw animation data(e.g. blend shapes), if the requested viseme type reports any
+        animation: Option<String>,
+    },
+    /// A bookmark(`<bookmark mark="..."/>`) in the SSML is reached
+    Bookmark {
+        /// The name of the bookmark
+        name: String,
+        /// Offset from the beginning of the audio
+        offset: Duration,
+    },
+    /// The end of the synthesis session
+    SessionEnd {
+        /// Offset from the beginning of the audio
+        offset: Duration,
+    },
+    /// A kind of metadata that this crate does not know, kept as is
+    Unknown {
+        /// The `Type` of the metadata
+        kind: String,
+        /// The `Data` of the metadata
+        data: serde_json::Value,
+    },
+}
+
+impl SynthesisEvent {
+    /// Offset from the beginning of the audio, if the event has one
+    pub fn offset(&self) -> Option<Duration> {
+        match self {
+            Self::WordBoundary(b) | Self::SentenceBoundary(b) => Some(b.offset),
+            Self::Viseme { offset, .. }
+            | Self::Bookmark { offset, .. }
+            | Self::SessionEnd { offset } => Some(*offset),
+            Self::Unknown { .. } => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawVisemeData {
+    offset: u64,
+    #[serde(default)]
+    viseme_id: u32,
+    #[serde(default)]
+    animation_chunk: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawBookmarkData {
+    offset: u64,
+    bookmark: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawOffsetData {
+    offset: u64,
+}
+
+fn parse_events_into(body: &str, events: &mut Vec<SynthesisEvent>) -> Result<(), MetadataError> {
+    let message: RawMetadataMessage = serde_json::from_str(body)?;
+    for item in message.metadata {
+        let boundary = |data: serde_json::Value| -> Result<WordBoundary, MetadataError> {
+            let data: RawBoundaryData = serde_json::from_value(data)?;
+            Ok(WordBoundary {
+                text: data.text.text,
+                offset: ticks_to_duration(data.offset),
+                duration: ticks_to_duration(data.duration),
+                boundary_type: data.text.boundary_type,
+            })
+        };
+        events.push(match item.kind.as_str() {
+            "WordBoundary" => SynthesisEvent::WordBoundary(boundary(item.data)?),
+            "SentenceBoundary" => SynthesisEvent::SentenceBoundary(boundary(item.data)?),
+            "Viseme" => {
+                let data: RawVisemeData = serde_json::from_value(item.data)?;
+                SynthesisEvent::Viseme {
+                    id: data.viseme_id,
+                    offset: ticks_to_duration(data.offset),
+                    animation: data.animation_chunk.filter(|a| !a.is_empty()),
+                }
+            }
+            "Bookmark" => {
+                let data: RawBookmarkData = serde_json::from_value(item.data)?;
+                SynthesisEvent::Bookmark {
+                    name: data.bookmark,
+                    offset: ticks_to_duration(data.offset),
+                }
+            }
+            "SessionEnd" => {
+                let data: RawOffsetData = serde_json::from_value(item.data)?;
+                SynthesisEvent::SessionEnd {
+                    offset: ticks_to_duration(data.offset),
+                }
+            }
+            _ => SynthesisEvent::Unknown {
+                kind: item.kind,
+                data: item.data,
+            },
+        });
+    }
+    Ok(())
+}
+
+/// Parse all the events out of a single raw audio metadata body, in the order they are reported.
+///
+/// Metadata of unknown types is kept as [`SynthesisEvent::Unknown`].
+pub fn parse_events_in_message(body: &str) -> Result<Vec<SynthesisEvent>, MetadataError> {
+    let mut events = Vec::new();
+    parse_events_into(body, &mut events)?;
+    Ok(events)
+}
+
+/// Where the last of the events ends: the end of the last boundary, or the offset of any other event.
+///
+/// It estimates the duration of the audio when it can not be computed from the audio data(e.g. for Opus),
+/// but it misses any silence after the last boundary unless the service reports the end of the session.
+pub fn events_end(events: &[SynthesisEvent]) -> Option<Duration> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            SynthesisEvent::WordBoundary(b) | SynthesisEvent::SentenceBoundary(b) => {
+                Some(b.offset + b.duration)
+            }
+            event => event.offset(),
+        })
+        .max()
+}
+
+/// Parse all the events out of the raw audio metadata bodies
+/// collected by [`crate::synthesizer::WebsocketSynthesizer`], in the order they are reported.
+pub fn parse_synthesis_events<S: AsRef<str>>(
+    metadata: &[S],
+) -> Result<Vec<SynthesisEvent>, MetadataError> {
+    let mut events = Vec::new();
+    for body in metadata {
+        parse_events_into(body.as_ref(), &mut events)?;
+    }
+    Ok(events)
+}
+
+/// Merge punctuation boundaries into the adjacent words, e.g. for word-by-word captions
+/// where punctuation should not show up on its own.
+///
+/// Punctuation is appended to the preceding word, whose duration is extended to cover it.
+/// Punctuation before the first word is prepended to that word instead.
+pub fn merge_punctuation(boundaries: &[WordBoundary]) -> Vec<WordBoundary> {
+    let mut merged: Vec<WordBoundary> = Vec::with_capacity(boundaries.len());
+    let mut leading: Option<WordBoundary> = None;
+    for boundary in boundaries {
+        if boundary.boundary_type != BoundaryType::Punctuation {
+            let mut boundary = boundary.clone();
+            if let Some(punctuation) = leading.take() {
+                boundary.text.insert_str(0, &punctuation.text);
+                boundary.duration = boundary.end().saturating_sub(punctuation.offset);
+                boundary.offset = punctuation.offset;
+            }
+            merged.push(boundary);
+            continue;
+        }
+        match (merged.last_mut(), leading.as_mut()) {
+            (Some(word), _) => {
+                word.text.push_str(&boundary.text);
+                word.duration = word
+                    .duration
+                    .max(boundary.end().saturating_sub(word.offset));
+            }
+            (None, Some(punctuation)) => {
+                punctuation.text.push_str(&boundary.text);
+                punctuation.duration = boundary.end().saturating_sub(punctuation.offset);
+            }
+            (None, None) => leading = Some(boundary.clone()),
+        }
+    }
+    // Nothing but punctuation
+    merged.extend(leading);
+    merged
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+/// An error that occurred while parsing audio metadata
+pub struct MetadataError {
+    pub kind: MetadataErrorKind,
+    pub(crate) source: Option<anyhow::Error>,
+}
+
+impl Display for MetadataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "audio metadata {:?} error", self.kind)
+    }
+}
+
+impl Error for MetadataError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|e| e.as_ref() as _)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum MetadataErrorKind {
+    Json,
+}
+
+impl From<serde_json::Error> for MetadataError {
+    fn from(e: serde_json::Error) -> Self {
+        Self {
+            kind: MetadataErrorKind::Json,
+            source: Some(e.into()),
+        }
+    }
+}
@@ -91,6 +91,24 @@ impl<'a> TryFrom<&'a Message> for WebSocketMessage<'a> {
     }
 }
 
+/// Get the `X-RequestId` header of a text or binary message
+pub(crate) fn request_id(message: &Message) -> Option<&str> {
+    let header = match message {
+        Message::Text(text) => text.split_once("\r\n\r\n")?.0,
+        Message::Binary(data) => {
+            let header_len = u16::from_be_bytes([*data.first()?, *data.get(1)?]) as usize;
+            str::from_utf8(data.get(2..2 + header_len)?).ok()?
+        }
+        _ => return None,
+    };
+    header.split("\r\n").find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        k.trim()
+            .eq_ignore_ascii_case("X-RequestId")
+            .then(|| v.trim())
+    })
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct ParseError {
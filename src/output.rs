@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use crate::{
+    concat_audio, events_end, AudioDataError, AudioDataErrorKind, AudioFormat, SynthesisEvent,
+    WordBoundary,
+};
+
+/// The audio, word boundaries and other events produced by a single synthesis
+#[derive(Debug, Clone)]
+pub struct SynthesisOutput {
+    /// The synthesized audio
+    pub audio: Vec<u8>,
+    /// The format of the synthesized audio
+    pub audio_format: AudioFormat,
+    /// Word boundaries, with offsets relative to the start of `audio`
+    pub word_boundaries: Vec<WordBoundary>,
+    /// All the events reported by the service, including the word boundaries, in the order they are reported
+    pub events: Vec<SynthesisEvent>,
+}
+
+impl SynthesisOutput {
+    /// Create a new [`SynthesisOutput`]
+    pub fn new(
+        audio: Vec<u8>,
+        audio_format: AudioFormat,
+        word_boundaries: Vec<WordBoundary>,
+    ) -> Self {
+        Self {
+            audio,
+            audio_format,
+            word_boundaries,
+            events: Vec::new(),
+        }
+    }
+
+    /// Attach the events reported by the service
+    pub fn with_events(mut self, events: Vec<SynthesisEvent>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// The duration of the synthesized audio.
+    ///
+    /// It is computed from the audio data if possible, see [`AudioFormat::audio_duration`].
+    /// Otherwise it is estimated from the end of the last event(see [`events_end`]) or word boundary.
+    pub fn duration(&self) -> Option<Duration> {
+        self.exact_duration().or_else(|| {
+            events_end(&self.events).or_else(|| {
+                self.word_boundaries
+                    .iter()
+                    .map(|b| b.offset + b.duration)
+                    .max()
+            })
+        })
+    }
+
+    /// The duration of the synthesized audio, only if it can be computed from the audio data
+    fn exact_duration(&self) -> Option<Duration> {
+        self.audio_format.audio_duration(&self.audio)
+    }
+}
+
+/// Merge several synthesis outputs into one timeline.
+///
+/// The outputs are concatenated in order and the word boundaries of each output
+/// are rebased by the total audio duration of the outputs before it.
+/// All outputs must be in the same audio format and the duration of that format must be computable.
+pub fn merge_synthesis_outputs(
+    outputs: &[SynthesisOutput],
+) -> Result<(Vec<u8>, Vec<WordBoundary>), AudioDataError> {
+    let Some(format) = outputs.first().map(|o| o.audio_format) else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    let mut word_boundaries = Vec::new();
+    let mut elapsed = Duration::ZERO;
+    for output in outputs {
+        if output.audio_format != format {
+            return Err(AudioDataError {
+                kind: AudioDataErrorKind::FormatMismatch,
+            });
+        }
+        word_boundaries.extend(output.word_boundaries.iter().map(|b| WordBoundary {
+            offset: b.offset + elapsed,
+            ..b.clone()
+        }));
+        elapsed += output.exact_duration().ok_or(AudioDataError {
+            kind: AudioDataErrorKind::UnknownDuration(format),
+        })?;
+    }
+    let segments: Vec<&[u8]> = outputs.iter().map(|o| o.audio.as_slice()).collect();
+    Ok((concat_audio(format, &segments)?, word_boundaries))
+}
@@ -4,6 +4,19 @@ use std::{
     fmt::{self, Display, Formatter},
 };
 
+const PITCH_FORMATS: &str = "Accepted values of pitch:
+  +10%, -5.5%      relative change in percent
+  +80Hz, 600Hz     relative or absolute frequency
+  +2st, -1.5st     relative change in semitones
+  x-low, low, medium, high, x-high, default
+  0.5              a float, converted to a percentage(50.00%)";
+
+const RATE_FORMATS: &str = "Accepted values of rate:
+  +10%, -30%       relative change in percent
+  x-slow, slow, medium, fast, x-fast, default
+  1.5f             a raw float, i.e. a multiplier of the default rate
+  0.5              a float, converted to a percentage(50.00%)";
+
 fn is_float(s: &str) -> bool {
     s.parse::<f32>().is_ok()
 }
@@ -22,7 +35,7 @@ pub(crate) fn parse_pitch(arg: &str) -> Result<Cow<'_, str>, ParseError> {
         Ok(Cow::Owned(format!("{:.2}%", v * 100f32)))
     } else {
         Err(ParseError::new(format!(
-            "Invalid pitch: {arg}. Please read the documentation for possible values of pitch."
+            "Invalid pitch: {arg}.\n{PITCH_FORMATS}"
         )))
     }
 }
@@ -40,7 +53,7 @@ pub(crate) fn parse_rate(arg: &str) -> Result<Cow<'_, str>, ParseError> {
         Ok(Cow::Owned(format!("{:.2}%", v * 100f32)))
     } else {
         Err(ParseError::new(format!(
-            "Invalid rate: {arg}. Please read the documentation for possible values of rate."
+            "Invalid rate: {arg}.\n{RATE_FORMATS}"
         )))
     }
 }
@@ -67,6 +80,7 @@ pub(crate) fn validate_style_degree(degree: f32) -> bool {
 
 #[derive(Debug)]
 #[non_exhaustive]
+/// An error that occurred while parsing or validating options, e.g. an invalid pitch
 pub struct ParseError {
     pub reason: String,
     pub(crate) source: Option<anyhow::Error>,
@@ -95,6 +95,12 @@ impl SpeechService {
                             None
                         }
                     },
+                    lang: opts
+                        .get_item("lang")
+                        .map(|l| l.extract())
+                        .transpose()?
+                        .map(Cow::Borrowed),
+                    ..Default::default()
                 })
             })
             .transpose()
@@ -0,0 +1,228 @@
+use std::{
+    collections::VecDeque,
+    io,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Waker},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use log::debug;
+use rodio::{OutputStream, Sink, Source};
+use tokio::io::AsyncWrite;
+
+use crate::{AudioDataError, AudioDataErrorKind, AudioError, AudioFormat};
+
+struct State {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    writer_closed: bool,
+    source_dropped: bool,
+    writer_waker: Option<Waker>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    data_available: Condvar,
+}
+
+/// Create a ring buffer that holds at most `capacity` bytes of audio in the given format,
+/// to connect streaming synthesis to real-time playback.
+///
+/// The synthesizer writes into the [`AudioRingBufferWriter`] (see [`crate::WebsocketSynthesizer::synthesize_ssml_to_writer`])
+/// while a playback thread consumes the [`AudioRingBufferSource`] (see [`play_audio_ring_buffer`]).
+///
+/// Only raw 16 bit mono PCM formats(e.g. [`AudioFormat::Raw24Khz16BitMonoPcm`]) are supported
+/// because the audio is played without decoding.
+///
+/// # Latency
+///
+/// Playback starts as soon as the first audio chunk arrives from the service, which usually happens
+/// after the first sentence is synthesized, instead of after the whole input is synthesized.
+/// On top of that comes the buffering of the audio output device, usually tens of milliseconds.
+///
+/// The capacity of the ring buffer bounds how far synthesis may run ahead of playback:
+/// at most `capacity / (2 * sample_rate)` seconds of audio are buffered.
+/// When the buffer is full, the synthesizer stops receiving from the service until playback catches up.
+/// A larger capacity tolerates more network jitter but uses more memory; it does not delay playback.
+///
+/// If playback catches up with synthesis, silence is played until more audio arrives.
+///
+/// ```ignore
+/// use tokio::io::AsyncWriteExt;
+/// // Buffer at most one second of audio
+/// let (mut writer, source) = audio_ring_buffer(AudioFormat::Raw24Khz16BitMonoPcm, 48000)?;
+/// let playback = play_audio_ring_buffer(source);
+/// ws_syn.synthesize_ssml_to_writer(ssml, &mut writer).await?;
+/// writer.shutdown().await?;
+/// playback.join().unwrap()?;
+/// ```
+pub fn audio_ring_buffer(
+    format: AudioFormat,
+    capacity: usize,
+) -> Result<(AudioRingBufferWriter, AudioRingBufferSource), AudioDataError> {
+    let name: &str = format.into();
+    if !(name.starts_with("raw-") && name.ends_with("-16bit-mono-pcm")) {
+        return Err(AudioDataError {
+            kind: AudioDataErrorKind::UnsupportedFormat(format),
+        });
+    }
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            // At least one sample must fit into the buffer
+            capacity: capacity.max(2),
+            writer_closed: false,
+            source_dropped: false,
+            writer_waker: None,
+        }),
+        data_available: Condvar::new(),
+    });
+    Ok((
+        AudioRingBufferWriter {
+            shared: shared.clone(),
+        },
+        AudioRingBufferSource {
+            shared,
+            sample_rate: format.sample_rate(),
+        },
+    ))
+}
+
+/// The writing end of an audio ring buffer.
+///
+/// Writes are pending while the buffer is full. Shut it down or drop it when the synthesis is done
+/// so that the [`AudioRingBufferSource`] ends after playing the remaining audio.
+pub struct AudioRingBufferWriter {
+    shared: Arc<Shared>,
+}
+
+impl AudioRingBufferWriter {
+    fn close(&self) {
+        self.shared.state.lock().unwrap().writer_closed = true;
+        self.shared.data_available.notify_all();
+    }
+}
+
+impl AsyncWrite for AudioRingBufferWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.source_dropped {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        let available = state.capacity - state.buffer.len();
+        if available == 0 {
+            state.writer_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let len = available.min(buf.len());
+        state.buffer.extend(&buf[..len]);
+        drop(state);
+        self.shared.data_available.notify_all();
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for AudioRingBufferWriter {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// The reading end of an audio ring buffer, which can be played by [`rodio`].
+///
+/// It ends when the writer is closed and all buffered audio is played.
+pub struct AudioRingBufferSource {
+    shared: Arc<Shared>,
+    sample_rate: u32,
+}
+
+impl AudioRingBufferSource {
+    /// Block until there is audio to play or the writer is closed.
+    pub fn wait_for_data(&self) {
+        let state = self.shared.state.lock().unwrap();
+        let _state = self
+            .shared
+            .data_available
+            .wait_while(state, |s| s.buffer.len() < 2 && !s.writer_closed)
+            .unwrap();
+    }
+}
+
+impl Iterator for AudioRingBufferSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.buffer.len() < 2 {
+            // Never block the audio output, play silence if the synthesizer falls behind
+            return if state.writer_closed { None } else { Some(0) };
+        }
+        let sample = i16::from_le_bytes([
+            state.buffer.pop_front().unwrap(),
+            state.buffer.pop_front().unwrap(),
+        ]);
+        if let Some(waker) = state.writer_waker.take() {
+            waker.wake();
+        }
+        Some(sample)
+    }
+}
+
+impl Source for AudioRingBufferSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Drop for AudioRingBufferSource {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.source_dropped = true;
+        if let Some(waker) = state.writer_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Play the audio ring buffer on the default audio output device in a new thread.
+///
+/// The thread waits for the first audio chunk before it starts playing,
+/// and finishes when the writer is closed and all buffered audio is played.
+pub fn play_audio_ring_buffer(source: AudioRingBufferSource) -> JoinHandle<Result<(), AudioError>> {
+    std::thread::spawn(move || {
+        source.wait_for_data();
+        let (_stream, stream_handle) = OutputStream::try_default()?;
+        let sink = Sink::try_new(&stream_handle)?;
+        sink.append(source);
+        sink.sleep_until_end();
+        debug!("Done playing audio ring buffer");
+        Ok(())
+    })
+}
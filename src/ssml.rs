@@ -1,16 +1,42 @@
 use core::fmt;
 use std::{
+    borrow::Cow,
     error::Error,
     fmt::{Display, Formatter},
 };
 
-use crate::TextOptions;
+use crate::{
+    parse::{parse_pitch, parse_rate},
+    ParseError, TextOptions,
+};
+
+mod background_audio;
+mod breaks;
+mod builder;
+mod emphasis;
+mod hook;
+mod markup;
+mod minify;
+mod phoneme;
+mod say_as;
+mod split;
+mod template;
+pub use background_audio::*;
+pub use breaks::*;
+pub use builder::*;
+pub use emphasis::*;
+pub use hook::*;
+pub use minify::*;
+pub(crate) use minify::{tokenize, Token};
+pub use phoneme::*;
+pub use say_as::*;
+pub use split::*;
+pub use template::*;
+
+use markup::{split_markup, MarkupSegment};
 
 use log::info;
-use xml::{
-    writer::{events::StartElementBuilder, XmlEvent},
-    EventWriter,
-};
+use xml::writer::events::StartElementBuilder;
 
 trait StartElementBuilderExt<'a> {
     fn optional_attrs(self, attrs: &'a [(&str, Option<&str>)]) -> Self;
@@ -37,67 +63,249 @@ impl<'a> StartElementBuilderExt<'a> for StartElementBuilder<'a> {
 }
 
 const DEFAULT_PITCH_RATE_STR: &str = "0%";
+const DEFAULT_LANG: &str = "en-US";
+
+/// Get the locale of a voice from its short name(e.g. `fr-FR` for `fr-FR-DeniseNeural`,
+/// `zh-CN-henan` for `zh-CN-henan-YundengNeural`)
+/// or its full name(e.g. `en-US` for `Microsoft Server Speech Text to Speech Voice (en-US, JennyNeural)`).
+///
+/// Returns `None` if the name does not start with a locale.
+pub fn locale_of_voice(voice: &str) -> Option<&str> {
+    let locale = match voice.split_once('(') {
+        Some((_, rest)) => rest.split_once(',')?.0.trim(),
+        None => voice.rsplit_once('-')?.0,
+    };
+    let (language, region) = locale.split_once('-')?;
+    let is_valid = (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && !region.is_empty()
+        && region
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    is_valid.then_some(locale)
+}
+
+/// Check the pitch and rate before they are put into the SSML,
+/// so that invalid values are reported here instead of by an opaque error of the service.
+fn validate_prosody(pitch: Option<&str>, rate: Option<&str>) -> Result<(), SsmlError> {
+    let invalid = |value: &str, e: ParseError| SsmlError {
+        kind: SsmlErrorKind::InvalidProsody(value.to_string()),
+        source: Some(e.into()),
+    };
+    if let Some(pitch) = pitch {
+        parse_pitch(pitch).map_err(|e| invalid(pitch, e))?;
+    }
+    if let Some(rate) = rate {
+        parse_rate(rate).map_err(|e| invalid(rate, e))?;
+    }
+    Ok(())
+}
+
+/// Check that a URL(e.g. of a lexicon) is an absolute http(s) URL, which is all the service can fetch
+fn validate_http_url(url: &str, kind: fn(String) -> SsmlErrorKind) -> Result<(), SsmlError> {
+    let invalid = |source: Option<anyhow::Error>| SsmlError {
+        kind: kind(url.to_string()),
+        source,
+    };
+    let parsed = url::Url::parse(url).map_err(|e| invalid(Some(e.into())))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid(None));
+    }
+    Ok(())
+}
 
 /// Interpolate SSML from text and options
+///
+/// The text is XML escaped and characters that XML does not allow(e.g. control characters) are removed.
+/// The pitch and rate are validated, see [`SsmlErrorKind::InvalidProsody`].
+///
+/// Pauses can be inserted with the markup `[[<break>]]` in the text, e.g. `Hello[[500ms]]world` or `[[strong]]`,
+/// see [`SsmlBreak`] for the allowed forms. Malformed times are [`SsmlErrorKind::InvalidBreak`] errors.
+/// Interpretation hints are inserted with `[[<interpret-as>|<text>]]` or `[[<interpret-as>:<format>|<text>]]`,
+/// e.g. `[[characters|ABC123]]` or `[[date:mdy|10/16/2026]]`, see [`SayAs`],
+/// and pronunciations with `[[<alphabet>:<ph>|<text>]]`, e.g. `[[ipa:təˈmɑːtoʊ|tomato]]`, see [`Phoneme`].
+/// Words in the [lexicon](TextOptions::lexicon) are wrapped in `<phoneme>` elements too.
+/// Text is emphasized with `[[emphasis|<text>]]` or `[[emphasis:<level>|<text>]]`, see [`EmphasisLevel`].
+///
+/// `xml:lang` is set to [`TextOptions::lang`] if specified, otherwise to the locale of the voice.
+///
+/// If [`TextOptions::ssml_template`] is specified, the SSML is rendered from the template instead.
 pub fn interpolate_ssml(text: impl AsRef<str>, options: &TextOptions) -> Result<String, SsmlError> {
-    let mut buf = Vec::new();
-    let mut writer = EventWriter::new_with_config(
-        &mut buf,
-        xml::EmitterConfig::new().write_document_declaration(false),
-    );
-    writer.write({
-        XmlEvent::start_element("speak")
-            .default_ns("http://www.w3.org/2001/10/synthesis")
-            .optional_ns(
-                options.rich_ssml_options.is_some(),
-                "mstts",
-                "http://www.w3.org/2001/mstts",
-            )
-            .ns("emo", "http://www.w3.org/2009/10/emotionml")
-            .attr("version", "1.0")
-            .attr("xml:lang", "en-US")
-    })?;
-
-    writer.write(XmlEvent::start_element("voice").attr("name", &options.voice))?;
-
-    // Make the borrow checker happy
-    if let Some(rich_ssml_options) = options.rich_ssml_options.as_ref() {
-        let style_degree = rich_ssml_options.style_degree.map(|x| x.to_string());
-        writer.write(
-            XmlEvent::start_element("mstts:express-as")
-                .optional_attrs(&[
-                    ("role", rich_ssml_options.role.map(|role| role.into())),
-                    ("styledegree", style_degree.as_deref()),
-                ])
-                .attr(
-                    "style",
-                    rich_ssml_options.style.as_deref().unwrap_or("general"),
-                ),
-        )?;
+    if let Some(template) = options.ssml_template.as_ref() {
+        validate_prosody(options.pitch.as_deref(), options.rate.as_deref())?;
+        let ssml = template.render(text.as_ref(), options);
+        info!("Created SSML from template: {}", &ssml);
+        return Ok(ssml);
     }
-    writer.write(
-        XmlEvent::start_element("prosody")
-            .attr(
-                "pitch",
-                options.pitch.as_deref().unwrap_or(DEFAULT_PITCH_RATE_STR),
-            )
-            .attr(
-                "rate",
-                options.rate.as_deref().unwrap_or(DEFAULT_PITCH_RATE_STR),
-            ),
-    )?;
-    writer.write(XmlEvent::characters(text.as_ref()))?;
-    writer.write(XmlEvent::end_element())?;
-    if options.rich_ssml_options.is_some() {
-        writer.write(XmlEvent::end_element())?;
-    }
-    writer.write(XmlEvent::end_element())?;
-    writer.write(XmlEvent::end_element())?;
-    let ssml = String::from_utf8(buf).unwrap();
+    interpolate_ssml_fragments(&[(text, options)])
+}
+
+/// Remove the characters that are not allowed in XML 1.0 documents, e.g. control characters
+/// in text copied from a terminal, which would otherwise make the service reject the SSML.
+pub(crate) fn strip_invalid_xml_chars(text: &str) -> Cow<'_, str> {
+    let is_valid = |c: char| matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..);
+    if text.chars().all(is_valid) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.chars().filter(|c| is_valid(*c)).collect())
+    }
+}
+
+/// Escape the text of an element after removing the invalid characters. Unlike xml-rs, this
+/// escapes `>` as well, so markup-like text such as `<loud>` stays readable as text everywhere.
+pub(crate) fn escape_text(text: &str) -> Cow<'_, str> {
+    let text = strip_invalid_xml_chars(text);
+    if !text.contains(['&', '<', '>']) {
+        return text;
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// The language of the text, see [`TextOptions::lang`]
+fn lang_of<'a>(options: &'a TextOptions) -> &'a str {
+    options
+        .lang
+        .as_deref()
+        .or_else(|| locale_of_voice(&options.voice))
+        .unwrap_or(DEFAULT_LANG)
+}
+
+/// Interpolate a single SSML document from several fragments of text, each with its own options,
+/// e.g. for a dialogue between several speakers in one synthesis.
+///
+/// Every fragment gets its own `<voice>` element, wrapped in `<mstts:express-as>` only if it has rich SSML options.
+/// `xml:lang` and the [background audio](TextOptions::background_audio) of the document are those of the first fragment.
+/// Fragments that specify another [language](TextOptions::lang) get a `<lang>` element, which only multilingual voices support.
+/// Whether the voice speaks the language is not checked here, because it takes the metadata of the voice,
+/// see [`crate::Voice::check_text_options`].
+///
+/// Consecutive fragments that only differ in the text and the language share one `<voice>` element,
+/// so that a multilingual voice switches languages within one utterance:
+///
+/// ```
+/// use aspeak::{interpolate_ssml_fragments, TextOptionsBuilder};
+///
+/// let english = TextOptionsBuilder::new().voice("en-US-AvaMultilingualNeural").build();
+/// let chinese = TextOptionsBuilder::new().voice("en-US-AvaMultilingualNeural").lang("zh-CN").build();
+/// let ssml = interpolate_ssml_fragments(&[("Hello! ", &english), ("你好！", &chinese)]).unwrap();
+/// assert_eq!(ssml.matches("<voice").count(), 1);
+/// assert!(ssml.contains(r#"<lang xml:lang="zh-CN"><prosody pitch="0%" rate="0%">你好！</prosody></lang>"#));
+/// ```
+///
+/// [SSML templates](TextOptions::ssml_template) are not used because they describe a whole document.
+pub fn interpolate_ssml_fragments<T: AsRef<str>>(
+    fragments: &[(T, &TextOptions)],
+) -> Result<String, SsmlError> {
+    let lang = fragments
+        .first()
+        .map(|(_, options)| lang_of(options))
+        .unwrap_or(DEFAULT_LANG);
+    let mut ssml = Ssml::speak().lang(lang);
+    if let Some(background_audio) = fragments
+        .first()
+        .and_then(|(_, options)| options.background_audio.as_ref())
+    {
+        ssml = ssml.background_audio(background_audio.clone());
+    }
+    let mut previous: Option<&TextOptions> = None;
+    for (text, options) in fragments {
+        if previous.is_some_and(|previous| same_voice_settings(previous, options)) {
+            // Switch the language within the same <voice>
+            ssml = ssml.voice_lang(lang_of(options));
+            ssml = push_text(ssml, text.as_ref(), options)?;
+            continue;
+        }
+        previous = Some(options);
+        ssml = ssml.voice(options.voice.as_ref());
+        if let Some(lang) = options.lang.as_deref() {
+            ssml = ssml.voice_lang(lang);
+        }
+        if let Some(viseme_type) = options.viseme_type {
+            ssml = ssml.viseme(viseme_type);
+        }
+        if let Some(lexicon_url) = options.lexicon_url.as_deref() {
+            ssml = ssml.lexicon_url(lexicon_url);
+        }
+        if let Some(rich_ssml_options) = options.rich_ssml_options.as_ref() {
+            ssml = ssml.express_as(
+                rich_ssml_options.style.as_deref().unwrap_or("general"),
+                rich_ssml_options.role,
+                rich_ssml_options.style_degree,
+            );
+        }
+        if let Some(emphasis) = options.emphasis {
+            ssml = ssml.emphasis(emphasis);
+        }
+        ssml = ssml.prosody(
+            options.pitch.as_deref().unwrap_or(DEFAULT_PITCH_RATE_STR),
+            options.rate.as_deref().unwrap_or(DEFAULT_PITCH_RATE_STR),
+        );
+        ssml = push_text(ssml, text.as_ref(), options)?;
+    }
+    let ssml = ssml.render()?;
     info!("Created SSML: {}", &ssml);
     Ok(ssml)
 }
 
+/// Append the text with its markup to the last voice
+fn push_text<'a>(
+    mut ssml: Ssml<'a>,
+    text: &'a str,
+    options: &'a TextOptions,
+) -> Result<Ssml<'a>, SsmlError> {
+    for segment in split_markup(text)? {
+        ssml = match segment {
+            MarkupSegment::Text(text) => match options.lexicon.as_ref() {
+                Some(lexicon) => lexicon.split(text).into_iter().fold(
+                    ssml,
+                    |ssml, (text, phoneme)| match phoneme {
+                        Some(phoneme) => ssml.phoneme(phoneme.clone(), text),
+                        None => ssml.text(text),
+                    },
+                ),
+                None => ssml.text(text),
+            },
+            MarkupSegment::Phoneme(phoneme, text) => ssml.phoneme(phoneme, text),
+            MarkupSegment::Emphasis(level, text) => ssml.emphasized(level, text),
+            MarkupSegment::Break(ssml_break) => ssml.pause(ssml_break),
+            MarkupSegment::SayAs(say_as, text) => ssml.say_as(say_as, text),
+        };
+    }
+    Ok(ssml)
+}
+
+/// Whether two fragments share a voice and all the settings of the `<voice>` element,
+/// so that they can be spoken by the same `<voice>` element in different languages
+fn same_voice_settings(a: &TextOptions, b: &TextOptions) -> bool {
+    fn rich<'a>(
+        options: &'a TextOptions,
+    ) -> Option<(Option<&'a str>, Option<&'static str>, Option<f32>)> {
+        options.rich_ssml_options.as_ref().map(|rich| {
+            (
+                rich.style.as_deref(),
+                rich.role.map(<&str>::from),
+                rich.style_degree,
+            )
+        })
+    }
+    a.voice == b.voice
+        && a.pitch == b.pitch
+        && a.rate == b.rate
+        && a.viseme_type == b.viseme_type
+        && a.lexicon_url == b.lexicon_url
+        && a.emphasis == b.emphasis
+        && rich(a) == rich(b)
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 /// An error that occurred while interpolating SSML
@@ -122,6 +330,31 @@ impl Error for SsmlError {
 #[non_exhaustive]
 pub enum SsmlErrorKind {
     Xml,
+    /// The SSML is rejected by the [`SsmlHook`].
+    Hook,
+    /// The break is neither a valid time nor a valid strength, see [`SsmlBreak`].
+    InvalidBreak(String),
+    /// An element is not closed or closed in the wrong place, see [`split_ssml`].
+    UnbalancedTag(String),
+    /// The [`SsmlTemplate`] is malformed or misses a required placeholder.
+    InvalidTemplate(String),
+    /// The pitch or rate is not in any of the forms the service accepts.
+    /// The source lists the accepted forms.
+    InvalidProsody(String),
+    /// Content is added to an [`Ssml`] document before any voice.
+    MissingVoice,
+    /// The interpretation is unknown or does not take the format, see [`SayAs`].
+    InvalidSayAs(String),
+    /// The alphabet is unknown or the pronunciation is empty, see [`Phoneme`].
+    InvalidPhoneme(String),
+    /// The lexicon is not a JSON object of pronunciations, see [`Lexicon::from_json`].
+    InvalidLexicon,
+    /// The URL of a lexicon is not an absolute http(s) URL, see [`TextOptions::lexicon_url`].
+    InvalidLexiconUrl(String),
+    /// The level of emphasis is unknown, see [`EmphasisLevel`].
+    InvalidEmphasis(String),
+    /// The source, volume or a fade of the background audio is invalid, see [`BackgroundAudio`].
+    InvalidBackgroundAudio(String),
 }
 
 macro_rules! impl_from_for_ssml_error {
@@ -138,3 +371,110 @@ macro_rules! impl_from_for_ssml_error {
 }
 
 impl_from_for_ssml_error!(xml::writer::Error, Xml);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextOptionsBuilder;
+
+    #[test]
+    fn switches_languages_within_one_voice() {
+        let english = TextOptionsBuilder::new()
+            .voice("en-US-AvaMultilingualNeural")
+            .build();
+        let chinese = TextOptionsBuilder::new()
+            .voice("en-US-AvaMultilingualNeural")
+            .lang("zh-CN")
+            .build();
+        let ssml =
+            interpolate_ssml_fragments(&[("Hello! ", &english), ("你好！", &chinese)]).unwrap();
+        assert!(ssml.contains(r#"xml:lang="en-US""#), "{ssml}");
+        assert_eq!(ssml.matches("<voice").count(), 1, "{ssml}");
+        assert!(
+            ssml.contains(
+                r#"<lang xml:lang="zh-CN"><prosody pitch="0%" rate="0%">你好！</prosody></lang>"#
+            ),
+            "{ssml}"
+        );
+    }
+
+    #[test]
+    fn starts_a_new_voice_for_other_settings() {
+        let jenny = TextOptionsBuilder::new().voice("en-US-JennyNeural").build();
+        let guy = TextOptionsBuilder::new()
+            .voice("en-US-GuyNeural")
+            .lang("fr-FR")
+            .build();
+        let ssml = interpolate_ssml_fragments(&[("Hi.", &jenny), ("Salut.", &guy)]).unwrap();
+        assert_eq!(ssml.matches("<voice").count(), 2, "{ssml}");
+        assert!(
+            ssml.contains(r#"<voice name="en-US-GuyNeural"><lang xml:lang="fr-FR">"#),
+            "{ssml}"
+        );
+    }
+
+    #[test]
+    fn finds_the_locale_of_a_voice() {
+        assert_eq!(locale_of_voice("en-US-JennyNeural"), Some("en-US"));
+        assert_eq!(
+            locale_of_voice("zh-CN-henan-YundengNeural"),
+            Some("zh-CN-henan")
+        );
+        assert_eq!(
+            locale_of_voice("Microsoft Server Speech Text to Speech Voice (en-US, JennyNeural)"),
+            Some("en-US")
+        );
+        assert_eq!(
+            locale_of_voice(
+                "Microsoft Server Speech Text to Speech Voice (zh-CN-henan, YundengNeural)"
+            ),
+            Some("zh-CN-henan")
+        );
+        assert_eq!(locale_of_voice("JennyNeural"), None);
+        assert_eq!(locale_of_voice("My-CustomVoice"), None);
+        assert_eq!(locale_of_voice("Voice (no locale)"), None);
+    }
+
+    #[test]
+    fn takes_the_language_from_the_options_or_the_voice() {
+        let options = TextOptionsBuilder::new()
+            .voice("zh-CN-henan-YundengNeural")
+            .build();
+        assert_eq!(lang_of(&options), "zh-CN-henan");
+        let options = TextOptionsBuilder::new()
+            .voice("zh-CN-henan-YundengNeural")
+            .lang("en-GB")
+            .build();
+        assert_eq!(lang_of(&options), "en-GB");
+        let options = TextOptionsBuilder::new().voice("MyCustomVoice").build();
+        assert_eq!(lang_of(&options), DEFAULT_LANG);
+    }
+
+    #[test]
+    fn strips_invalid_xml_chars() {
+        let valid = "Tabs\t, new lines\n\r, 你好 and 🎉";
+        assert!(matches!(strip_invalid_xml_chars(valid), Cow::Borrowed(s) if s == valid));
+        assert_eq!(
+            strip_invalid_xml_chars("bell\u{7}, escape\u{1b}[0m, nul\0 and \u{FFFE}"),
+            "bell, escape[0m, nul and "
+        );
+    }
+
+    #[test]
+    fn escapes_markup_characters_in_the_text() {
+        let options = TextOptionsBuilder::new().voice("en-US-JennyNeural").build();
+        let ssml = interpolate_ssml("Tom & Jerry <loud> \"q\" 'a'\u{7}", &options).unwrap();
+        assert!(ssml.contains("Tom &amp; Jerry &lt;loud&gt;"), "{ssml}");
+        let mut text = String::new();
+        for event in xml::EventReader::from_str(&ssml) {
+            match event.unwrap() {
+                xml::reader::XmlEvent::Characters(s) => text.push_str(&s),
+                xml::reader::XmlEvent::StartElement { name, .. } => {
+                    assert_ne!(name.local_name, "loud", "{ssml}")
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(text, "Tom & Jerry <loud> \"q\" 'a'");
+    }
+}
@@ -0,0 +1,92 @@
+use std::{borrow::Cow, time::Duration};
+
+use super::{validate_http_url, SsmlError, SsmlErrorKind};
+
+/// The longest fade in or out the service accepts
+const MAX_FADE: Duration = Duration::from_secs(10);
+
+/// An audio track that plays under the speech of the whole document, i.e. a `<mstts:backgroundaudio>` element.
+///
+/// The audio is fetched by the service, so the source has to be a publicly accessible http(s) URL.
+/// The volume is in the range `0..=100` and defaults to 1, the fades are at most 10 seconds.
+/// They are validated when the SSML is rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundAudio<'a> {
+    pub(crate) src: Cow<'a, str>,
+    pub(crate) volume: Option<u8>,
+    pub(crate) fade_in: Option<Duration>,
+    pub(crate) fade_out: Option<Duration>,
+}
+
+impl<'a> BackgroundAudio<'a> {
+    /// Play the audio at the URL
+    pub fn new(src: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            src: src.into(),
+            volume: None,
+            fade_in: None,
+            fade_out: None,
+        }
+    }
+
+    /// Volume of the audio, in the range `0..=100`
+    pub fn with_volume(mut self, volume: u8) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    /// Duration of the fade in at the start, at most 10 seconds
+    pub fn with_fade_in(mut self, fade_in: Duration) -> Self {
+        self.fade_in = Some(fade_in);
+        self
+    }
+
+    /// Duration of the fade out at the end, at most 10 seconds
+    pub fn with_fade_out(mut self, fade_out: Duration) -> Self {
+        self.fade_out = Some(fade_out);
+        self
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn volume(&self) -> Option<u8> {
+        self.volume
+    }
+
+    pub fn fade_in(&self) -> Option<Duration> {
+        self.fade_in
+    }
+
+    pub fn fade_out(&self) -> Option<Duration> {
+        self.fade_out
+    }
+
+    /// Check the source, the volume and the fades against the ranges the service accepts
+    pub(crate) fn validate(&self) -> Result<(), SsmlError> {
+        validate_http_url(&self.src, SsmlErrorKind::InvalidBackgroundAudio)?;
+        let invalid = |value: String| SsmlError {
+            kind: SsmlErrorKind::InvalidBackgroundAudio(value),
+            source: None,
+        };
+        if let Some(volume) = self.volume.filter(|&volume| volume > 100) {
+            return Err(invalid(format!("volume {volume}")));
+        }
+        for fade in [self.fade_in, self.fade_out].into_iter().flatten() {
+            if fade > MAX_FADE {
+                return Err(invalid(format!("fade {}ms", fade.as_millis())));
+            }
+        }
+        Ok(())
+    }
+
+    /// The attributes of the element, in the order of the documentation of the service
+    pub(crate) fn attrs(&self) -> [(&'static str, Option<String>); 3] {
+        [
+            ("volume", self.volume.map(|volume| volume.to_string())),
+            ("fadein", self.fade_in.map(|d| d.as_millis().to_string())),
+            ("fadeout", self.fade_out.map(|d| d.as_millis().to_string())),
+        ]
+    }
+}
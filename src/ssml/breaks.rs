@@ -0,0 +1,157 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    time::Duration,
+};
+
+use strum::{EnumString, IntoStaticStr};
+
+use super::{SsmlError, SsmlErrorKind};
+
+/// The relative strength of a pause, from the weakest to the strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+#[non_exhaustive]
+pub enum BreakStrength {
+    /// No pause, e.g. to remove a pause that would normally occur
+    None,
+    XWeak,
+    Weak,
+    Medium,
+    Strong,
+    XStrong,
+}
+
+/// A pause in the speech, i.e. a `<break>` element.
+///
+/// A break is either an absolute duration(`<break time="500ms"/>`)
+/// or a strength relative to the normal pauses of the voice(`<break strength="strong"/>`).
+///
+/// It can be parsed from the time form(`500ms`, `1.5s`) or the strength form
+/// (`none`, `x-weak`, `weak`, `medium`, `strong` or `x-strong`),
+/// and is displayed as the `<break>` element.
+/// The service caps the duration at 20 seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SsmlBreak {
+    Time(Duration),
+    Strength(BreakStrength),
+}
+
+impl FromStr for SsmlBreak {
+    type Err = SsmlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(strength) = BreakStrength::from_str(s) {
+            return Ok(Self::Strength(strength));
+        }
+        let (number, unit) = if let Some(ms) = s.strip_suffix("ms") {
+            (ms, 1e-3)
+        } else if let Some(secs) = s.strip_suffix('s') {
+            (secs, 1.0)
+        } else {
+            return Err(invalid_break(s));
+        };
+        number
+            .parse::<f64>()
+            .ok()
+            .filter(|x| x.is_finite() && *x >= 0.0 && !number.starts_with('+'))
+            .map(|x| Self::Time(Duration::from_secs_f64(x * unit)))
+            .ok_or_else(|| invalid_break(s))
+    }
+}
+
+fn invalid_break(s: &str) -> SsmlError {
+    SsmlError {
+        kind: SsmlErrorKind::InvalidBreak(s.to_string()),
+        source: None,
+    }
+}
+
+impl Display for SsmlBreak {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Time(time) => write!(f, r#"<break time="{}ms"/>"#, time.as_millis()),
+            Self::Strength(strength) => {
+                write!(f, r#"<break strength="{}"/>"#, Into::<&str>::into(strength))
+            }
+        }
+    }
+}
+
+impl SsmlBreak {
+    /// The attribute of the `<break>` element, e.g. `("time", "500ms")`
+    pub(crate) fn attr(&self) -> (&'static str, Cow<'static, str>) {
+        match self {
+            Self::Time(time) => ("time", Cow::Owned(format!("{}ms", time.as_millis()))),
+            Self::Strength(strength) => ("strength", Cow::Borrowed(strength.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<SsmlBreak, SsmlError> {
+        s.parse()
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(
+            parse("500ms").unwrap(),
+            SsmlBreak::Time(Duration::from_millis(500))
+        );
+        assert_eq!(
+            parse(" 1.5s ").unwrap(),
+            SsmlBreak::Time(Duration::from_millis(1500))
+        );
+        assert_eq!(parse("0s").unwrap(), SsmlBreak::Time(Duration::ZERO));
+    }
+
+    #[test]
+    fn parses_strengths() {
+        assert_eq!(
+            parse("x-weak").unwrap(),
+            SsmlBreak::Strength(BreakStrength::XWeak)
+        );
+        assert_eq!(
+            parse("strong").unwrap(),
+            SsmlBreak::Strength(BreakStrength::Strong)
+        );
+        assert_eq!(
+            parse("none").unwrap(),
+            SsmlBreak::Strength(BreakStrength::None)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_breaks() {
+        for s in [
+            "", "500", "-1s", "+1s", "1.5 sec", "NaNs", "infs", "1e999s", "xstrong",
+        ] {
+            let err = parse(s).unwrap_err();
+            assert!(
+                matches!(err.kind, SsmlErrorKind::InvalidBreak(ref value) if value == s.trim()),
+                "{s:?} gives {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn displays_the_break_element() {
+        assert_eq!(
+            parse("1.5s").unwrap().to_string(),
+            r#"<break time="1500ms"/>"#
+        );
+        assert_eq!(
+            parse("x-strong").unwrap().to_string(),
+            r#"<break strength="x-strong"/>"#
+        );
+        assert_eq!(parse("250ms").unwrap().attr(), ("time", "250ms".into()));
+        assert_eq!(parse("weak").unwrap().attr(), ("strength", "weak".into()));
+    }
+}
@@ -0,0 +1,395 @@
+use std::borrow::Cow;
+
+use xml::{writer::XmlEvent, EventWriter};
+
+use super::{
+    escape_text, locale_of_voice, validate_http_url, validate_prosody, BackgroundAudio,
+    EmphasisLevel, Phoneme, SayAs, SsmlBreak, SsmlError, SsmlErrorKind, StartElementBuilderExt,
+    DEFAULT_LANG,
+};
+use crate::{Role, VisemeType};
+
+/// A typed SSML document, so that SSML does not need to be built by string formatting.
+///
+/// Every [`Ssml::voice`] starts a `<voice>` element. The other methods apply to the last voice:
+/// its content is wrapped in `<mstts:express-as>`(outermost), then `<prosody>`, then `<emphasis>`,
+/// and the text is escaped when the document is [rendered](Ssml::render).
+///
+/// ```
+/// use aspeak::Ssml;
+///
+/// let ssml = Ssml::speak()
+///     .voice("en-US-JennyNeural")
+///     .express_as("cheerful", None, Some(1.5))
+///     .prosody("+10%", "-5%")
+///     .text("Tom & Jerry")
+///     .render()
+///     .unwrap();
+/// assert!(ssml.contains(r#"<prosody pitch="+10%" rate="-5%">Tom &amp; Jerry</prosody>"#));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Ssml<'a> {
+    lang: Option<Cow<'a, str>>,
+    background_audio: Option<BackgroundAudio<'a>>,
+    voices: Vec<SsmlVoice<'a>>,
+    /// Whether a method that applies to the last voice is called before any voice
+    missing_voice: bool,
+}
+
+#[derive(Debug, Clone)]
+struct SsmlVoice<'a> {
+    name: Cow<'a, str>,
+    viseme_type: Option<VisemeType>,
+    lexicon_url: Option<Cow<'a, str>>,
+    express_as: Option<ExpressAs<'a>>,
+    prosody: Option<(Cow<'a, str>, Cow<'a, str>)>,
+    emphasis: Option<EmphasisLevel>,
+    /// At least one, see [`Ssml::voice_lang`]
+    sections: Vec<LangSection<'a>>,
+}
+
+/// Content of a voice in one language
+#[derive(Debug, Clone, Default)]
+struct LangSection<'a> {
+    lang: Option<Cow<'a, str>>,
+    content: Vec<SsmlNode<'a>>,
+}
+
+#[derive(Debug, Clone)]
+struct ExpressAs<'a> {
+    style: Cow<'a, str>,
+    role: Option<Role>,
+    style_degree: Option<f32>,
+}
+
+/// The content of a voice, in order
+#[derive(Debug, Clone)]
+enum SsmlNode<'a> {
+    Text(Cow<'a, str>),
+    Break(SsmlBreak),
+    SayAs(SayAs, Cow<'a, str>),
+    Phoneme(Phoneme, Cow<'a, str>),
+    Emphasis(EmphasisLevel, Cow<'a, str>),
+}
+
+impl<'a> Ssml<'a> {
+    /// Start an empty `<speak>` document
+    pub fn speak() -> Self {
+        Self::default()
+    }
+
+    /// The language of the document(`xml:lang`), default to the locale of the first voice
+    pub fn lang(mut self, lang: impl Into<Cow<'a, str>>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Play the audio under the speech of the whole document(`<mstts:backgroundaudio>`)
+    pub fn background_audio(mut self, background_audio: BackgroundAudio<'a>) -> Self {
+        self.background_audio = Some(background_audio);
+        self
+    }
+
+    /// Start a `<voice>` element with the voice name, e.g. `en-US-JennyNeural`
+    pub fn voice(mut self, name: impl Into<Cow<'a, str>>) -> Self {
+        self.voices.push(SsmlVoice {
+            name: name.into(),
+            viseme_type: None,
+            lexicon_url: None,
+            express_as: None,
+            prosody: None,
+            emphasis: None,
+            sections: vec![LangSection::default()],
+        });
+        self
+    }
+
+    /// Speak the following content of the last voice in another language with a `<lang>` element,
+    /// which only multilingual voices support. It is omitted if it is the language of the document.
+    ///
+    /// Once the voice has content, every call starts a new `<lang>` element, so that a multilingual voice
+    /// can switch languages within one `<voice>`:
+    ///
+    /// ```
+    /// use aspeak::Ssml;
+    ///
+    /// let ssml = Ssml::speak()
+    ///     .voice("en-US-AvaMultilingualNeural")
+    ///     .text("Hello! ")
+    ///     .voice_lang("zh-CN")
+    ///     .text("你好！")
+    ///     .render()
+    ///     .unwrap();
+    /// assert!(ssml.contains(r#"Hello! <lang xml:lang="zh-CN">你好！</lang></voice>"#));
+    /// ```
+    pub fn voice_lang(self, lang: impl Into<Cow<'a, str>>) -> Self {
+        self.with_voice(|voice| {
+            let lang = Some(lang.into());
+            match voice.sections.last_mut() {
+                Some(section) if section.content.is_empty() => section.lang = lang,
+                _ => voice.sections.push(LangSection {
+                    lang,
+                    content: Vec::new(),
+                }),
+            }
+        })
+    }
+
+    /// Request visemes of the type for the last voice(`<mstts:viseme>`)
+    pub fn viseme(self, viseme_type: VisemeType) -> Self {
+        self.with_voice(|voice| voice.viseme_type = Some(viseme_type))
+    }
+
+    /// Use the pronunciation lexicon(PLS) at the URL for the last voice(`<lexicon>`).
+    /// It is validated when the document is rendered.
+    pub fn lexicon_url(self, url: impl Into<Cow<'a, str>>) -> Self {
+        self.with_voice(|voice| voice.lexicon_url = Some(url.into()))
+    }
+
+    /// Speak the last voice in a style(`<mstts:express-as>`), optionally with a role and a style degree
+    pub fn express_as(
+        self,
+        style: impl Into<Cow<'a, str>>,
+        role: Option<Role>,
+        style_degree: Option<f32>,
+    ) -> Self {
+        self.with_voice(|voice| {
+            voice.express_as = Some(ExpressAs {
+                style: style.into(),
+                role,
+                style_degree,
+            })
+        })
+    }
+
+    /// Set the pitch and rate of the last voice(`<prosody>`). They are validated when the document is rendered.
+    pub fn prosody(self, pitch: impl Into<Cow<'a, str>>, rate: impl Into<Cow<'a, str>>) -> Self {
+        self.with_voice(|voice| voice.prosody = Some((pitch.into(), rate.into())))
+    }
+
+    /// Emphasize the content of the last voice(`<emphasis>`)
+    pub fn emphasis(self, level: EmphasisLevel) -> Self {
+        self.with_voice(|voice| voice.emphasis = Some(level))
+    }
+
+    /// Append text to the last voice
+    pub fn text(self, text: impl Into<Cow<'a, str>>) -> Self {
+        self.with_voice(|voice| voice.push(SsmlNode::Text(text.into())))
+    }
+
+    /// Append a pause(`<break>`) to the last voice
+    pub fn pause(self, ssml_break: SsmlBreak) -> Self {
+        self.with_voice(|voice| voice.push(SsmlNode::Break(ssml_break)))
+    }
+
+    /// Append text with an interpretation hint(`<say-as>`) to the last voice
+    pub fn say_as(self, say_as: SayAs, text: impl Into<Cow<'a, str>>) -> Self {
+        self.with_voice(|voice| voice.push(SsmlNode::SayAs(say_as, text.into())))
+    }
+
+    /// Append text with its pronunciation(`<phoneme>`) to the last voice
+    pub fn phoneme(self, phoneme: Phoneme, text: impl Into<Cow<'a, str>>) -> Self {
+        self.with_voice(|voice| voice.push(SsmlNode::Phoneme(phoneme, text.into())))
+    }
+
+    /// Append emphasized text(`<emphasis>`) to the last voice
+    pub fn emphasized(self, level: EmphasisLevel, text: impl Into<Cow<'a, str>>) -> Self {
+        self.with_voice(|voice| voice.push(SsmlNode::Emphasis(level, text.into())))
+    }
+
+    fn with_voice(mut self, f: impl FnOnce(&mut SsmlVoice<'a>)) -> Self {
+        match self.voices.last_mut() {
+            Some(voice) => f(voice),
+            None => self.missing_voice = true,
+        }
+        self
+    }
+
+    /// Render the document into SSML.
+    ///
+    /// The `mstts` namespace is only declared if it is used.
+    /// It fails with [`SsmlErrorKind::InvalidProsody`] if a pitch or rate is invalid,
+    /// with [`SsmlErrorKind::InvalidLexiconUrl`] if the URL of a lexicon is invalid,
+    /// with [`SsmlErrorKind::InvalidBackgroundAudio`] if the background audio is invalid,
+    /// and with [`SsmlErrorKind::MissingVoice`] if content is added before any voice.
+    pub fn render(&self) -> Result<String, SsmlError> {
+        if self.missing_voice {
+            return Err(SsmlError {
+                kind: SsmlErrorKind::MissingVoice,
+                source: None,
+            });
+        }
+        for (pitch, rate) in self.voices.iter().filter_map(|v| v.prosody.as_ref()) {
+            validate_prosody(Some(pitch), Some(rate))?;
+        }
+        if let Some(background_audio) = self.background_audio.as_ref() {
+            background_audio.validate()?;
+        }
+        for url in self.voices.iter().filter_map(|v| v.lexicon_url.as_deref()) {
+            validate_http_url(url, SsmlErrorKind::InvalidLexiconUrl)?;
+        }
+        let lang = self
+            .lang
+            .as_deref()
+            .or_else(|| self.voices.first().and_then(|v| locale_of_voice(&v.name)))
+            .unwrap_or(DEFAULT_LANG);
+        let needs_mstts = self.background_audio.is_some()
+            || self
+                .voices
+                .iter()
+                .any(|v| v.express_as.is_some() || v.viseme_type.is_some());
+        let mut buf = Vec::new();
+        let mut writer = EventWriter::new_with_config(
+            &mut buf,
+            xml::EmitterConfig::new().write_document_declaration(false),
+        );
+        writer.write({
+            XmlEvent::start_element("speak")
+                .default_ns("http://www.w3.org/2001/10/synthesis")
+                .optional_ns(needs_mstts, "mstts", "http://www.w3.org/2001/mstts")
+                .ns("emo", "http://www.w3.org/2009/10/emotionml")
+                .attr("version", "1.0")
+                .attr("xml:lang", lang)
+        })?;
+        // The background audio has to come before any voice
+        if let Some(background_audio) = self.background_audio.as_ref() {
+            let attrs = background_audio.attrs();
+            let attrs = attrs
+                .each_ref()
+                .map(|(name, value)| (*name, value.as_deref()));
+            writer.write(
+                XmlEvent::start_element("mstts:backgroundaudio")
+                    .attr("src", &background_audio.src)
+                    .optional_attrs(&attrs),
+            )?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        for voice in self.voices.iter() {
+            voice.write(&mut writer, lang)?;
+        }
+        writer.write(XmlEvent::end_element())?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+}
+
+impl<'a> SsmlVoice<'a> {
+    fn push(&mut self, node: SsmlNode<'a>) {
+        if let Some(section) = self.sections.last_mut() {
+            section.content.push(node);
+        }
+    }
+
+    fn write(
+        &self,
+        writer: &mut EventWriter<&mut Vec<u8>>,
+        document_lang: &str,
+    ) -> Result<(), SsmlError> {
+        writer.write(XmlEvent::start_element("voice").attr("name", &self.name))?;
+        // The lexicon has to come before the content of the voice
+        if let Some(url) = self.lexicon_url.as_deref() {
+            writer.write(XmlEvent::start_element("lexicon").attr("uri", url))?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        if let Some(viseme_type) = self.viseme_type {
+            writer
+                .write(XmlEvent::start_element("mstts:viseme").attr("type", viseme_type.into()))?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        for section in self.sections.iter() {
+            self.write_section(writer, section, document_lang)?;
+        }
+        writer.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    fn write_section(
+        &self,
+        writer: &mut EventWriter<&mut Vec<u8>>,
+        section: &LangSection,
+        document_lang: &str,
+    ) -> Result<(), SsmlError> {
+        let lang = section
+            .lang
+            .as_deref()
+            .filter(|lang| *lang != document_lang);
+        if let Some(lang) = lang {
+            writer.write(XmlEvent::start_element("lang").attr("xml:lang", lang))?;
+        }
+        if let Some(express_as) = self.express_as.as_ref() {
+            let style_degree = express_as.style_degree.map(|x| x.to_string());
+            writer.write(
+                XmlEvent::start_element("mstts:express-as")
+                    .optional_attrs(&[
+                        ("role", express_as.role.map(|role| role.into())),
+                        ("styledegree", style_degree.as_deref()),
+                    ])
+                    .attr("style", &express_as.style),
+            )?;
+        }
+        if let Some((pitch, rate)) = self.prosody.as_ref() {
+            writer.write(
+                XmlEvent::start_element("prosody")
+                    .attr("pitch", pitch)
+                    .attr("rate", rate),
+            )?;
+        }
+        if let Some(level) = self.emphasis {
+            writer.write(XmlEvent::start_element("emphasis").attr("level", level.into()))?;
+        }
+        for node in section.content.iter() {
+            match node {
+                SsmlNode::Text(text) => write_text(writer, text)?,
+                SsmlNode::Break(ssml_break) => {
+                    let (name, value) = ssml_break.attr();
+                    writer.write(XmlEvent::start_element("break").attr(name, &value))?;
+                    writer.write(XmlEvent::end_element())?;
+                }
+                SsmlNode::SayAs(say_as, text) => {
+                    writer.write(
+                        XmlEvent::start_element("say-as")
+                            .attr("interpret-as", say_as.interpret_as().into())
+                            .optional_attrs(&[("format", say_as.format())]),
+                    )?;
+                    write_text(writer, text)?;
+                    writer.write(XmlEvent::end_element())?;
+                }
+                SsmlNode::Phoneme(phoneme, text) => {
+                    writer.write(
+                        XmlEvent::start_element("phoneme")
+                            .attr("alphabet", phoneme.alphabet().into())
+                            .attr("ph", phoneme.ph()),
+                    )?;
+                    write_text(writer, text)?;
+                    writer.write(XmlEvent::end_element())?;
+                }
+                SsmlNode::Emphasis(level, text) => {
+                    writer.write(
+                        XmlEvent::start_element("emphasis").attr("level", (*level).into()),
+                    )?;
+                    write_text(writer, text)?;
+                    writer.write(XmlEvent::end_element())?;
+                }
+            }
+        }
+        let open_elements = [
+            self.emphasis.is_some(),
+            self.prosody.is_some(),
+            self.express_as.is_some(),
+            lang.is_some(),
+        ];
+        for _ in open_elements.into_iter().filter(|&open| open) {
+            writer.write(XmlEvent::end_element())?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the text of an element with [`escape_text`]. Writing empty characters first makes xml-rs
+/// close the pending start tag, the escaped text is then appended to the buffer as is.
+fn write_text(writer: &mut EventWriter<&mut Vec<u8>>, text: &str) -> Result<(), SsmlError> {
+    writer.write(XmlEvent::characters(""))?;
+    writer
+        .inner_mut()
+        .extend_from_slice(escape_text(text).as_bytes());
+    Ok(())
+}
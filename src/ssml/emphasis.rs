@@ -0,0 +1,18 @@
+use strum::{EnumString, IntoStaticStr};
+
+/// The level of emphasis of text, i.e. an `<emphasis>` element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+#[cfg_attr(feature = "binary", derive(clap::ValueEnum))]
+#[non_exhaustive]
+pub enum EmphasisLevel {
+    /// Speak faster and more quietly than normal
+    Reduced,
+    /// Speak without the emphasis the voice would add otherwise
+    None,
+    /// Speak slower and louder than normal
+    #[default]
+    Moderate,
+    /// Speak even slower and louder than [`EmphasisLevel::Moderate`]
+    Strong,
+}
@@ -0,0 +1,46 @@
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use super::{SsmlError, SsmlErrorKind};
+
+type SsmlHookFn =
+    dyn for<'a> Fn(&'a str) -> Result<Cow<'a, str>, Box<dyn Error + Send + Sync>> + Send + Sync;
+
+/// A hook that inspects or transforms the final SSML right before it is sent to the service.
+///
+/// The hook is called after interpolation, minification, etc., so it sees exactly what would be sent.
+/// Return [`Cow::Borrowed`] to send the SSML unchanged, [`Cow::Owned`] to send a modified one,
+/// or an error to abort the synthesis.
+///
+/// Use [`crate::SynthesizerConfig::with_ssml_hook`] to install a hook.
+#[derive(Clone)]
+pub struct SsmlHook(Arc<SsmlHookFn>);
+
+impl SsmlHook {
+    pub fn new(
+        hook: impl for<'a> Fn(&'a str) -> Result<Cow<'a, str>, Box<dyn Error + Send + Sync>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    /// Run the hook on the SSML.
+    pub fn apply<'a>(&self, ssml: &'a str) -> Result<Cow<'a, str>, SsmlError> {
+        (self.0)(ssml).map_err(|e| SsmlError {
+            kind: SsmlErrorKind::Hook,
+            source: Some(anyhow::anyhow!(e)),
+        })
+    }
+}
+
+impl Debug for SsmlHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("SsmlHook")
+    }
+}
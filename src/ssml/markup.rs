@@ -0,0 +1,189 @@
+use std::str::FromStr;
+
+use super::{
+    EmphasisLevel, InterpretAs, Phoneme, PhonemeAlphabet, SayAs, SsmlBreak, SsmlError,
+    SsmlErrorKind,
+};
+
+/// A segment of plain text with markup, see [`split_markup`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MarkupSegment<'a> {
+    Text(&'a str),
+    Break(SsmlBreak),
+    SayAs(SayAs, &'a str),
+    Phoneme(Phoneme, &'a str),
+    Emphasis(EmphasisLevel, &'a str),
+}
+
+/// Split plain text at the markup in double brackets:
+///
+/// - `[[<break>]]` for a pause, e.g. `Hello[[500ms]]world` or `Hello[[strong]]world`,
+///   where the break is in any form [`SsmlBreak`] parses.
+/// - `[[<interpret-as>|<text>]]` or `[[<interpret-as>:<format>|<text>]]` for an interpretation hint,
+///   e.g. `[[characters|ABC123]]` or `[[date:mdy|10/16/2026]]`, see [`SayAs`].
+/// - `[[<alphabet>:<ph>|<text>]]` for a pronunciation, e.g. `[[ipa:təˈmɑːtoʊ|tomato]]`, see [`Phoneme`].
+/// - `[[emphasis|<text>]]` or `[[emphasis:<level>|<text>]]` for emphasized text, e.g. `[[emphasis:strong|never]]`,
+///   see [`EmphasisLevel`].
+///
+/// Double brackets around anything else are kept as text, except for malformed times like `[[-1s]]` or `[[5 sec]]`
+/// formats an interpretation does not take, invalid pronunciations and unknown levels of emphasis, which are errors so that typos do not end up being read out.
+pub(crate) fn split_markup(text: &str) -> Result<Vec<MarkupSegment<'_>>, SsmlError> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    let mut text_start = 0;
+    while let Some(open) = rest.find("[[") {
+        let Some(close) = rest[open + 2..].find("]]").map(|i| open + 2 + i) else {
+            break;
+        };
+        let offset = text.len() - rest.len();
+        let Some(segment) = parse_markup(&rest[open + 2..close])? else {
+            // Not markup, but the text may still contain markup after the brackets
+            rest = &rest[open + 2..];
+            continue;
+        };
+        if text_start < offset + open {
+            segments.push(MarkupSegment::Text(&text[text_start..offset + open]));
+        }
+        segments.push(segment);
+        text_start = offset + close + 2;
+        rest = &rest[close + 2..];
+    }
+    if text_start < text.len() {
+        segments.push(MarkupSegment::Text(&text[text_start..]));
+    }
+    Ok(segments)
+}
+
+/// Parse the content of double brackets, `None` if it is not markup
+fn parse_markup(inner: &str) -> Result<Option<MarkupSegment<'_>>, SsmlError> {
+    if let Some((hint, content)) = inner.split_once('|') {
+        let (kind, arg) = match hint.split_once(':') {
+            Some((kind, arg)) => (kind.trim(), Some(arg.trim())),
+            None => (hint.trim(), None),
+        };
+        return if kind == "emphasis" {
+            let level = arg
+                .map(|level| {
+                    EmphasisLevel::from_str(level).map_err(|e| SsmlError {
+                        kind: SsmlErrorKind::InvalidEmphasis(level.to_string()),
+                        source: Some(e.into()),
+                    })
+                })
+                .transpose()?
+                .unwrap_or_default();
+            Ok(Some(MarkupSegment::Emphasis(level, content)))
+        } else if InterpretAs::from_str(kind).is_ok() {
+            Ok(Some(MarkupSegment::SayAs(hint.parse()?, content)))
+        } else if PhonemeAlphabet::from_str(kind).is_ok() {
+            Ok(Some(MarkupSegment::Phoneme(hint.parse()?, content)))
+        } else {
+            Ok(None)
+        };
+    }
+    match inner.parse::<SsmlBreak>() {
+        Ok(ssml_break) => Ok(Some(MarkupSegment::Break(ssml_break))),
+        Err(e) if looks_like_time(inner) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Whether the markup is meant to be a time, i.e. it starts with a number or a sign
+fn looks_like_time(s: &str) -> bool {
+    s.trim()
+        .starts_with(|c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{interpolate_ssml, BreakStrength, TextOptionsBuilder};
+
+    #[test]
+    fn splits_breaks() {
+        assert_eq!(
+            split_markup("Hello[[500ms]]world[[strong]]").unwrap(),
+            [
+                MarkupSegment::Text("Hello"),
+                MarkupSegment::Break(SsmlBreak::Time(Duration::from_millis(500))),
+                MarkupSegment::Text("world"),
+                MarkupSegment::Break(SsmlBreak::Strength(BreakStrength::Strong)),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_an_unclosed_opening_as_text() {
+        assert_eq!(
+            split_markup("a [[500ms b").unwrap(),
+            [MarkupSegment::Text("a [[500ms b")]
+        );
+        assert_eq!(
+            split_markup("a[[1s]] [[b").unwrap(),
+            [
+                MarkupSegment::Text("a"),
+                MarkupSegment::Break(SsmlBreak::Time(Duration::from_secs(1))),
+                MarkupSegment::Text(" [[b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_literal_double_brackets_as_text() {
+        assert_eq!(
+            split_markup("see [[wiki]] and [[Main Page]]").unwrap(),
+            [MarkupSegment::Text("see [[wiki]] and [[Main Page]]")]
+        );
+        // Markup after brackets that are not markup is still recognized
+        assert_eq!(
+            split_markup("[[x [[1s]]").unwrap(),
+            [
+                MarkupSegment::Text("[[x "),
+                MarkupSegment::Break(SsmlBreak::Time(Duration::from_secs(1))),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_at_the_first_pipe() {
+        assert_eq!(
+            split_markup("[[characters|A|B]]").unwrap(),
+            [MarkupSegment::SayAs("characters".parse().unwrap(), "A|B")]
+        );
+        assert_eq!(
+            split_markup("[[ipa:təˈmɑːtoʊ|to|mato]]").unwrap(),
+            [MarkupSegment::Phoneme(
+                "ipa:təˈmɑːtoʊ".parse().unwrap(),
+                "to|mato"
+            )]
+        );
+        assert_eq!(
+            split_markup("[[a|b|c]]").unwrap(),
+            [MarkupSegment::Text("[[a|b|c]]")]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_markup() {
+        for text in ["[[-1s]]", "[[5 sec]]", "[[emphasis:loud|x]]"] {
+            assert!(split_markup(text).is_err(), "{text}");
+        }
+        assert!(matches!(
+            split_markup("[[1.5 s]]").unwrap_err().kind,
+            SsmlErrorKind::InvalidBreak(_)
+        ));
+    }
+
+    #[test]
+    fn interpolates_markup() {
+        let options = TextOptionsBuilder::new().voice("en-US-JennyNeural").build();
+        let ssml = interpolate_ssml("Hi[[500ms]]there [[x]] [[weak]]<&>", &options).unwrap();
+        assert!(
+            ssml.contains(
+                r#"Hi<break time="500ms" />there [[x]] <break strength="weak" />&lt;&amp;&gt;"#
+            ),
+            "{ssml}"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+/// Elements that never make the whitespace around them significant
+const STRUCTURAL_ELEMENTS: &[&str] = &[
+    "speak",
+    "voice",
+    "p",
+    "s",
+    "lexicon",
+    "mstts:backgroundaudio",
+];
+
+pub(crate) enum Token<'a> {
+    /// A start, end or empty element tag
+    Tag {
+        name: &'a str,
+        raw: &'a str,
+    },
+    /// XML declaration, doctype, CDATA and other markup that is kept as is
+    Other(&'a str),
+    Comment(&'a str),
+    Text(&'a str),
+}
+
+impl Token<'_> {
+    /// Whether the whitespace next to this token can be removed
+    fn is_boundary(&self) -> bool {
+        match self {
+            Token::Tag { name, .. } => STRUCTURAL_ELEMENTS.contains(name),
+            Token::Other(raw) => !raw.starts_with("<![CDATA["),
+            Token::Comment(_) | Token::Text(_) => false,
+        }
+    }
+}
+
+/// Find the end of a tag starting at `start`, skipping `>` in quoted attribute values
+fn tag_end(ssml: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in ssml[start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return Some(start + i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+pub(crate) fn tokenize(ssml: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < ssml.len() {
+        let rest = &ssml[pos..];
+        if !rest.starts_with('<') {
+            let len = rest.find('<').unwrap_or(rest.len());
+            tokens.push(Token::Text(&rest[..len]));
+            pos += len;
+            continue;
+        }
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| pos + i + 3)
+        } else if rest.starts_with("<![CDATA[") {
+            rest.find("]]>").map(|i| pos + i + 3)
+        } else {
+            tag_end(ssml, pos)
+        }
+        // Keep malformed markup as is
+        .unwrap_or(ssml.len());
+        let raw = &ssml[pos..end];
+        tokens.push(if raw.starts_with("<!--") {
+            Token::Comment(raw)
+        } else if raw.starts_with("<!") || raw.starts_with("<?") {
+            Token::Other(raw)
+        } else {
+            let name = raw
+                .trim_start_matches(['<', '/'])
+                .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .next()
+                .unwrap_or_default();
+            Token::Tag { name, raw }
+        });
+        pos = end;
+    }
+    tokens
+}
+
+/// Remove insignificant whitespace and comments from SSML to reduce the payload size.
+///
+/// Runs of whitespace in text are collapsed into a single space,
+/// and whitespace next to the tags of structural elements (`speak`, `voice`, `p`, `s`, etc.) is removed.
+/// Whitespace between other elements like `<break/>` or `<emphasis>` and the text is kept as a single space,
+/// so words are never joined together. Tags are kept as is.
+pub fn minify_ssml(ssml: &str) -> String {
+    // Comments are removed, so the text on both sides of them is adjacent
+    let tokens: Vec<_> = tokenize(ssml)
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Comment(_)))
+        .collect();
+    let mut output = String::with_capacity(ssml.len());
+    // Whitespace that ends a text and starts the next one is collapsed into a single space as well
+    let push_space = |output: &mut String| {
+        if !output.ends_with(' ') {
+            output.push(' ');
+        }
+    };
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Tag { raw, .. } | Token::Other(raw) => output.push_str(raw),
+            Token::Comment(_) => {}
+            Token::Text(text) => {
+                let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                // Whitespace-only text left over from removed comments does not separate anything
+                let is_blank =
+                    |token: &&Token| matches!(token, Token::Text(t) if t.trim().is_empty());
+                let leading = text.starts_with(char::is_whitespace)
+                    && tokens[..i]
+                        .iter()
+                        .rev()
+                        .find(|prev| !is_blank(prev))
+                        .is_some_and(|prev| !prev.is_boundary());
+                let trailing = text.ends_with(char::is_whitespace)
+                    && tokens[i + 1..]
+                        .iter()
+                        .find(|next| !is_blank(next))
+                        .is_some_and(|next| !next.is_boundary());
+                if words.is_empty() {
+                    if leading && trailing {
+                        push_space(&mut output);
+                    }
+                    continue;
+                }
+                if leading {
+                    push_space(&mut output);
+                }
+                output.push_str(&words);
+                if trailing {
+                    output.push(' ');
+                }
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_whitespace_around_structural_elements() {
+        let ssml = r#"<?xml version="1.0"?>
+<speak version="1.0" xml:lang="en-US">
+    <voice name="en-US-JennyNeural">
+        <p>
+            <s>Hello,   world!</s>
+            <s>Bye.</s>
+        </p>
+    </voice>
+</speak>
+"#;
+        assert_eq!(
+            minify_ssml(ssml),
+            r#"<?xml version="1.0"?><speak version="1.0" xml:lang="en-US"><voice name="en-US-JennyNeural"><p><s>Hello, world!</s><s>Bye.</s></p></voice></speak>"#
+        );
+    }
+
+    #[test]
+    fn keeps_a_single_space_around_inline_elements() {
+        assert_eq!(
+            minify_ssml("<s>Wait \n <break time=\"1s\" />   then  <emphasis>go</emphasis>!</s>"),
+            "<s>Wait <break time=\"1s\" /> then <emphasis>go</emphasis>!</s>"
+        );
+        assert_eq!(
+            minify_ssml("<s><break/>  <break/></s>"),
+            "<s><break/> <break/></s>"
+        );
+    }
+
+    #[test]
+    fn removes_comments_without_doubling_spaces() {
+        assert_eq!(minify_ssml("foo <!-- c --> bar"), "foo bar");
+        assert_eq!(minify_ssml("foo <!-- c -->  <!-- d --> bar"), "foo bar");
+        assert_eq!(minify_ssml("foo<!-- c -->bar"), "foobar");
+        assert_eq!(minify_ssml("foo<!-- c --> bar"), "foo bar");
+        assert_eq!(
+            minify_ssml("<voice> <!-- c --> bar </voice>"),
+            "<voice>bar</voice>"
+        );
+    }
+
+    #[test]
+    fn keeps_tags_and_cdata_as_is() {
+        assert_eq!(
+            minify_ssml(r#"<s><prosody  rate="+10%"  pitch='a > b'>x</prosody></s>"#),
+            r#"<s><prosody  rate="+10%"  pitch='a > b'>x</prosody></s>"#
+        );
+        assert_eq!(
+            minify_ssml("<s>a <![CDATA[ b ]]> c</s>"),
+            "<s>a <![CDATA[ b ]]> c</s>"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use serde::Deserialize;
+use strum::{EnumString, IntoStaticStr};
+
+use super::{strip_invalid_xml_chars, SsmlError, SsmlErrorKind};
+
+/// The phonetic alphabet of a [`Phoneme`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+#[non_exhaustive]
+pub enum PhonemeAlphabet {
+    /// The International Phonetic Alphabet
+    #[default]
+    Ipa,
+    /// The SAPI phone set of the speech service, which depends on the language
+    Sapi,
+    /// The Universal Phone Set
+    Ups,
+    /// X-SAMPA, an ASCII transcription of IPA
+    XSampa,
+}
+
+/// The pronunciation of a word, i.e. a `<phoneme>` element,
+/// e.g. for names and technical terms that the voice mispronounces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phoneme {
+    alphabet: PhonemeAlphabet,
+    ph: String,
+}
+
+impl Phoneme {
+    /// Create a pronunciation in the alphabet, e.g. `təˈmɑːtoʊ` in [`PhonemeAlphabet::Ipa`].
+    ///
+    /// It fails with [`SsmlErrorKind::InvalidPhoneme`] if the pronunciation is empty
+    /// or contains characters that XML does not allow.
+    pub fn new(alphabet: PhonemeAlphabet, ph: impl Into<String>) -> Result<Self, SsmlError> {
+        let ph = ph.into();
+        if ph.trim().is_empty() || strip_invalid_xml_chars(&ph).len() != ph.len() {
+            return Err(invalid_phoneme(format!(
+                "{}:{ph}",
+                Into::<&str>::into(alphabet)
+            )));
+        }
+        Ok(Self { alphabet, ph })
+    }
+
+    pub fn alphabet(&self) -> PhonemeAlphabet {
+        self.alphabet
+    }
+
+    /// The pronunciation(`ph`)
+    pub fn ph(&self) -> &str {
+        &self.ph
+    }
+}
+
+impl FromStr for Phoneme {
+    type Err = SsmlError;
+
+    /// Parse `<alphabet>:<ph>`, e.g. `ipa:təˈmɑːtoʊ`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (alphabet, ph) = s.split_once(':').ok_or_else(|| invalid_phoneme(s))?;
+        let alphabet = PhonemeAlphabet::from_str(alphabet.trim()).map_err(|e| SsmlError {
+            kind: SsmlErrorKind::InvalidPhoneme(s.to_string()),
+            source: Some(e.into()),
+        })?;
+        Self::new(alphabet, ph)
+    }
+}
+
+fn invalid_phoneme(s: impl Into<String>) -> SsmlError {
+    SsmlError {
+        kind: SsmlErrorKind::InvalidPhoneme(s.into()),
+        source: None,
+    }
+}
+
+/// A pronunciation in a lexicon file, either a string in IPA or an object with the alphabet
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LexiconEntry {
+    Ipa(String),
+    Phoneme {
+        #[serde(default)]
+        alphabet: Option<String>,
+        ph: String,
+    },
+}
+
+/// Pronunciations of words that [`crate::interpolate_ssml`] wraps in `<phoneme>` elements.
+///
+/// Words are matched case-sensitively and only as whole words, the longest word first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lexicon {
+    /// Sorted by length in descending order, so that longer words win
+    entries: Vec<(String, Phoneme)>,
+}
+
+impl Lexicon {
+    /// Create an empty lexicon
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the pronunciation of a word, replacing the previous one. Empty words are ignored.
+    pub fn insert(&mut self, word: impl Into<String>, phoneme: Phoneme) {
+        let word = word.into();
+        if word.is_empty() {
+            return;
+        }
+        self.entries.retain(|(w, _)| *w != word);
+        let index = self.entries.partition_point(|(w, _)| w.len() >= word.len());
+        self.entries.insert(index, (word, phoneme));
+    }
+
+    /// Parse a JSON object that maps words to their pronunciations,
+    /// either an IPA string or an object like `{"alphabet": "sapi", "ph": "..."}`:
+    ///
+    /// ```json
+    /// {
+    ///     "tomato": "təˈmɑːtoʊ",
+    ///     "Azure": { "alphabet": "x-sampa", "ph": "{Z@r" }
+    /// }
+    /// ```
+    ///
+    /// It fails with [`SsmlErrorKind::InvalidLexicon`] if the JSON is malformed
+    /// and with [`SsmlErrorKind::InvalidPhoneme`] if a pronunciation is invalid.
+    pub fn from_json(json: &str) -> Result<Self, SsmlError> {
+        let entries: BTreeMap<String, LexiconEntry> =
+            serde_json::from_str(json).map_err(|e| SsmlError {
+                kind: SsmlErrorKind::InvalidLexicon,
+                source: Some(e.into()),
+            })?;
+        let mut lexicon = Self::new();
+        for (word, entry) in entries {
+            let phoneme = match entry {
+                LexiconEntry::Ipa(ph) => Phoneme::new(PhonemeAlphabet::Ipa, ph)?,
+                LexiconEntry::Phoneme { alphabet, ph } => Phoneme::new(
+                    alphabet
+                        .as_deref()
+                        .map(|a| {
+                            PhonemeAlphabet::from_str(a).map_err(|e| SsmlError {
+                                kind: SsmlErrorKind::InvalidPhoneme(format!("{a}:{ph}")),
+                                source: Some(e.into()),
+                            })
+                        })
+                        .transpose()?
+                        .unwrap_or_default(),
+                    ph,
+                )?,
+            };
+            lexicon.insert(word, phoneme);
+        }
+        Ok(lexicon)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Split text at the words in the lexicon, paired with their pronunciations
+    pub(crate) fn split<'a>(&'a self, text: &'a str) -> Vec<(&'a str, Option<&'a Phoneme>)> {
+        let mut segments = Vec::new();
+        let mut text_start = 0;
+        let mut i = 0;
+        while i < text.len() {
+            let at_boundary = !text[..i].chars().next_back().is_some_and(is_word_char);
+            let matched = at_boundary
+                .then(|| {
+                    self.entries.iter().find(|(word, _)| {
+                        text[i..].starts_with(word.as_str())
+                            && !text[i + word.len()..]
+                                .chars()
+                                .next()
+                                .is_some_and(is_word_char)
+                    })
+                })
+                .flatten();
+            match matched {
+                Some((word, phoneme)) => {
+                    if text_start < i {
+                        segments.push((&text[text_start..i], None));
+                    }
+                    segments.push((&text[i..i + word.len()], Some(phoneme)));
+                    i += word.len();
+                    text_start = i;
+                }
+                None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        if text_start < text.len() {
+            segments.push((&text[text_start..], None));
+        }
+        segments
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpolate_ssml, TextOptionsBuilder};
+
+    #[test]
+    fn parses_phonemes() {
+        let phoneme: Phoneme = "ipa:təˈmɑːtoʊ".parse().unwrap();
+        assert_eq!(phoneme.alphabet(), PhonemeAlphabet::Ipa);
+        assert_eq!(phoneme.ph(), "təˈmɑːtoʊ");
+        let phoneme: Phoneme = "x-sampa:{Z@r".parse().unwrap();
+        assert_eq!(phoneme.alphabet(), PhonemeAlphabet::XSampa);
+        for s in ["təˈmɑːtoʊ", "klingon:abc", "ipa: ", "ipa:a\u{1}b"] {
+            assert!(
+                matches!(
+                    s.parse::<Phoneme>().unwrap_err().kind,
+                    SsmlErrorKind::InvalidPhoneme(_)
+                ),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn escapes_the_pronunciation_and_the_text() {
+        let options = TextOptionsBuilder::new().voice("en-US-JennyNeural").build();
+        let ssml = interpolate_ssml(r#"[[ipa:ˈeɪ"<&>'|A&B]]"#, &options).unwrap();
+        assert!(
+            ssml.contains(
+                r#"<phoneme alphabet="ipa" ph="ˈeɪ&quot;&lt;&amp;&gt;&apos;">A&amp;B</phoneme>"#
+            ),
+            "{ssml}"
+        );
+    }
+
+    #[test]
+    fn applies_the_lexicon_to_whole_words() {
+        let lexicon = Lexicon::from_json(
+            r#"{"tomato": "təˈmɑːtoʊ", "to": {"alphabet": "sapi", "ph": "t uw"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            lexicon.split("tomatoes to tomato"),
+            [
+                ("tomatoes ", None),
+                (
+                    "to",
+                    Some(&Phoneme::new(PhonemeAlphabet::Sapi, "t uw").unwrap())
+                ),
+                (" ", None),
+                (
+                    "tomato",
+                    Some(&Phoneme::new(PhonemeAlphabet::Ipa, "təˈmɑːtoʊ").unwrap())
+                ),
+            ]
+        );
+        let options = TextOptionsBuilder::new()
+            .voice("en-US-JennyNeural")
+            .lexicon(lexicon)
+            .build();
+        let ssml = interpolate_ssml("a tomato", &options).unwrap();
+        assert!(
+            ssml.contains(r#"a <phoneme alphabet="ipa" ph="təˈmɑːtoʊ">tomato</phoneme>"#),
+            "{ssml}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lexicons() {
+        assert!(matches!(
+            Lexicon::from_json("[").unwrap_err().kind,
+            SsmlErrorKind::InvalidLexicon
+        ));
+        assert!(matches!(
+            Lexicon::from_json(r#"{"a": {"alphabet": "klingon", "ph": "x"}}"#)
+                .unwrap_err()
+                .kind,
+            SsmlErrorKind::InvalidPhoneme(_)
+        ));
+    }
+}
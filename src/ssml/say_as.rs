@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+use strum::{EnumString, IntoStaticStr};
+
+use super::{SsmlError, SsmlErrorKind};
+
+/// How the service should read the content of a `<say-as>` element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+#[non_exhaustive]
+pub enum InterpretAs {
+    Address,
+    /// A number, e.g. `10` as "ten"
+    Cardinal,
+    /// Letter by letter, e.g. `ABC123` as "A B C one two three"
+    Characters,
+    /// A date, optionally with the order of the components as the format
+    Date,
+    /// Digit by digit, e.g. `123` as "one two three"
+    Digits,
+    /// A duration, optionally with the components as the format
+    Duration,
+    Fraction,
+    Name,
+    /// An ordinal number, e.g. `3` as "third"
+    Ordinal,
+    /// Letter by letter, like [`InterpretAs::Characters`]
+    SpellOut,
+    Telephone,
+    /// A time of day, optionally with the 12 or 24 hour clock as the format
+    Time,
+}
+
+impl InterpretAs {
+    /// The formats the service accepts for the interpretation, empty if it does not take a format
+    pub fn formats(&self) -> &'static [&'static str] {
+        match self {
+            Self::Date => &[
+                "dmy", "mdy", "ymd", "ydm", "ym", "my", "md", "dm", "d", "m", "y",
+            ],
+            Self::Time => &["hms12", "hms24"],
+            Self::Duration => &["hms", "hm", "ms"],
+            _ => &[],
+        }
+    }
+}
+
+/// An interpretation hint for a piece of text, i.e. a `<say-as>` element,
+/// e.g. to read a serial code letter by letter or a number as a telephone number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SayAs {
+    interpret_as: InterpretAs,
+    format: Option<&'static str>,
+}
+
+impl SayAs {
+    /// Create a hint with an optional format.
+    ///
+    /// It fails with [`SsmlErrorKind::InvalidSayAs`] if the format is not one of [`InterpretAs::formats`].
+    pub fn new(interpret_as: InterpretAs, format: Option<&str>) -> Result<Self, SsmlError> {
+        let format = match format {
+            None => None,
+            Some(format) => Some(
+                interpret_as
+                    .formats()
+                    .iter()
+                    .copied()
+                    .find(|f| *f == format)
+                    .ok_or_else(|| SsmlError {
+                        kind: SsmlErrorKind::InvalidSayAs(format!(
+                            "{}:{format}",
+                            Into::<&str>::into(interpret_as)
+                        )),
+                        source: None,
+                    })?,
+            ),
+        };
+        Ok(Self {
+            interpret_as,
+            format,
+        })
+    }
+
+    pub fn interpret_as(&self) -> InterpretAs {
+        self.interpret_as
+    }
+
+    pub fn format(&self) -> Option<&'static str> {
+        self.format
+    }
+}
+
+impl FromStr for SayAs {
+    type Err = SsmlError;
+
+    /// Parse `<interpret-as>` or `<interpret-as>:<format>`, e.g. `digits` or `date:mdy`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (interpret_as, format) = match s.trim().split_once(':') {
+            Some((interpret_as, format)) => (interpret_as, Some(format)),
+            None => (s.trim(), None),
+        };
+        let interpret_as = InterpretAs::from_str(interpret_as).map_err(|e| SsmlError {
+            kind: SsmlErrorKind::InvalidSayAs(s.to_string()),
+            source: Some(e.into()),
+        })?;
+        Self::new(interpret_as, format)
+    }
+}
@@ -0,0 +1,263 @@
+use super::minify::{tokenize, Token};
+use crate::{SsmlError, SsmlErrorKind};
+
+/// Elements that the SSML can be split after
+const SPLITTABLE_ELEMENTS: &[&str] = &["s", "p"];
+
+/// A piece of SSML between two points where it can be split safely
+struct Segment<'a> {
+    /// The name and start tag of the elements that are open before the segment
+    context: Vec<(&'a str, &'a str)>,
+    /// The name and start tag of the elements that are open after the segment
+    end: Vec<(&'a str, &'a str)>,
+    body: String,
+    /// Whether the segment contains anything to speak,
+    /// as opposed to end tags, comments and whitespace
+    has_content: bool,
+}
+
+impl<'a> Segment<'a> {
+    fn new(context: &[(&'a str, &'a str)]) -> Self {
+        Self {
+            context: context.to_vec(),
+            end: Vec::new(),
+            body: String::new(),
+            has_content: false,
+        }
+    }
+
+    fn closing_tags(&self) -> String {
+        self.end
+            .iter()
+            .rev()
+            .map(|(name, _)| format!("</{name}>"))
+            .collect()
+    }
+}
+
+fn unbalanced(name: &str) -> SsmlError {
+    SsmlError {
+        kind: SsmlErrorKind::UnbalancedTag(name.to_string()),
+        source: None,
+    }
+}
+
+/// Split large SSML into smaller SSML documents of at most `max_len` bytes each,
+/// so that each of them fits into a single request.
+///
+/// The SSML is only split after a top-level `<s>` or `<p>` element inside a `<voice>`,
+/// or between `<voice>` elements. Each chunk is re-wrapped in the elements that are open at the split point,
+/// e.g. `<speak>`, `<voice>`, `<prosody>` and `<mstts:express-as>` with all their attributes,
+/// so every chunk is valid SSML that is spoken in the same voice, style and prosody as in the original document.
+///
+/// A chunk may still exceed `max_len` if there is no split point within it.
+/// Tags are not validated except that they must be balanced.
+pub fn split_ssml(ssml: &str, max_len: usize) -> Result<Vec<String>, SsmlError> {
+    // The XML declaration and everything else before the root element
+    let mut prolog = String::new();
+    let mut in_prolog = true;
+    let mut stack: Vec<(&str, &str)> = Vec::new();
+    let mut segments = Vec::new();
+    let mut current = Segment::new(&stack);
+    for token in tokenize(ssml) {
+        match token {
+            Token::Tag { name, raw } => {
+                in_prolog = false;
+                current.body.push_str(raw);
+                if raw.starts_with("</") {
+                    match stack.pop() {
+                        Some((open, _)) if open == name => {}
+                        _ => return Err(unbalanced(name)),
+                    }
+                    let is_split_point = if SPLITTABLE_ELEMENTS.contains(&name) {
+                        stack.iter().any(|(n, _)| *n == "voice")
+                            && !stack.iter().any(|(n, _)| SPLITTABLE_ELEMENTS.contains(n))
+                    } else {
+                        name == "voice" && stack.len() == 1
+                    };
+                    if is_split_point {
+                        let mut next = Segment::new(&stack);
+                        std::mem::swap(&mut current, &mut next);
+                        next.end = stack.clone();
+                        segments.push(next);
+                    }
+                } else if raw.ends_with("/>") {
+                    // Empty elements like <break/> and <audio/>
+                    current.has_content = true;
+                } else {
+                    stack.push((name, raw));
+                }
+            }
+            Token::Other(raw) => {
+                if in_prolog {
+                    prolog.push_str(raw);
+                }
+                current.body.push_str(raw);
+            }
+            Token::Comment(raw) => current.body.push_str(raw),
+            Token::Text(text) => {
+                current.has_content |= !text.trim().is_empty();
+                current.body.push_str(text);
+            }
+        }
+    }
+    if let Some((name, _)) = stack.last() {
+        return Err(unbalanced(name));
+    }
+    segments.push(current);
+
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut last: Option<&Segment> = None;
+    for segment in &segments {
+        if let Some(prev) = last {
+            let len = chunk.len() + segment.body.len() + segment.closing_tags().len();
+            // Never leave a chunk with nothing to speak
+            if len <= max_len || !segment.has_content {
+                chunk.push_str(&segment.body);
+                last = Some(segment);
+                continue;
+            }
+            chunk.push_str(&prev.closing_tags());
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        if !segment.context.is_empty() {
+            chunk.push_str(&prolog);
+        }
+        for (_, raw) in &segment.context {
+            chunk.push_str(raw);
+        }
+        chunk.push_str(&segment.body);
+        last = Some(segment);
+    }
+    if let Some(prev) = last {
+        chunk.push_str(&prev.closing_tags());
+        chunks.push(chunk);
+    }
+    Ok(chunks)
+}
+
+/// Whether `c` ends a sentence, given the text that follows it.
+///
+/// ASCII punctuation only ends a sentence if it is followed by whitespace, so that e.g. `3.14` is not split.
+fn is_sentence_end(c: char, following: &str) -> bool {
+    match c {
+        '.' | '!' | '?' | ';' => following.chars().next().is_none_or(char::is_whitespace),
+        '\n' | '。' | '！' | '？' | '；' | '…' => true,
+        _ => false,
+    }
+}
+
+/// Split long plain text into chunks of at most `max_chars` characters each,
+/// so that each chunk is synthesized into audio under the length limit of the service.
+///
+/// The text is split after the last sentence end(`.`, `!`, `?`, `。`, line breaks etc.) that fits into a chunk.
+/// If a single sentence is longer than `max_chars`, it is split at the last whitespace that fits,
+/// or in the middle of a word as a last resort.
+/// Leading and trailing whitespace of the chunks is trimmed.
+pub fn split_text(text: &str, max_chars: usize) -> Vec<&str> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while let Some((limit, _)) = rest.char_indices().nth(max_chars) {
+        let window = &rest[..limit];
+        let last_break = |is_break: &dyn Fn(char, &str) -> bool| {
+            window
+                .char_indices()
+                .rev()
+                .map(|(i, c)| (i + c.len_utf8(), c))
+                .find(|&(end, c)| is_break(c, &rest[end..]))
+                .map(|(end, _)| end)
+        };
+        let split_at = last_break(&is_sentence_end)
+            .or_else(|| last_break(&|c, _| c.is_whitespace()))
+            .unwrap_or(limit);
+        let (chunk, tail) = rest.split_at(split_at);
+        chunks.push(chunk.trim_end());
+        rest = tail.trim_start();
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEAK: &str = r#"<speak version="1.0" xmlns="http://www.w3.org/2001/10/synthesis" xmlns:mstts="https://www.w3.org/2001/mstts" xml:lang="en-US">"#;
+    const VOICE: &str = r#"<voice name="en-US-JennyNeural">"#;
+    const EXPRESS_AS: &str = r#"<mstts:express-as style="cheerful" styledegree="2">"#;
+    const PROSODY: &str = r#"<prosody rate="+10%" pitch="-5%">"#;
+
+    #[test]
+    fn keeps_small_ssml_in_one_chunk() {
+        let ssml = format!("{SPEAK}{VOICE}<s>Hello.</s><s>World.</s></voice></speak>");
+        assert_eq!(split_ssml(&ssml, 4096).unwrap(), [ssml]);
+    }
+
+    #[test]
+    fn rewraps_every_chunk_in_the_open_elements() {
+        let ssml = format!(
+            "{SPEAK}{VOICE}{EXPRESS_AS}{PROSODY}<s>One.</s> <s>Two.</s> <s>Three.</s></prosody></mstts:express-as></voice></speak>"
+        );
+        let open = format!("{SPEAK}{VOICE}{EXPRESS_AS}{PROSODY}");
+        let close = "</prosody></mstts:express-as></voice></speak>";
+        let chunks = split_ssml(&ssml, open.len() + close.len() + 12).unwrap();
+        assert_eq!(
+            chunks,
+            [
+                format!("{open}<s>One.</s>{close}"),
+                format!("{open} <s>Two.</s>{close}"),
+                format!("{open} <s>Three.</s>{close}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_between_voices() {
+        let ssml = format!(
+            r#"<?xml version="1.0"?>{SPEAK}{VOICE}<s>Hi.</s></voice><voice name="en-US-GuyNeural">{PROSODY}<p>Hey.</p></prosody></voice></speak>"#
+        );
+        let chunks = split_ssml(&ssml, 1).unwrap();
+        assert_eq!(
+            chunks,
+            [
+                format!(r#"<?xml version="1.0"?>{SPEAK}{VOICE}<s>Hi.</s></voice></speak>"#),
+                format!(
+                    r#"<?xml version="1.0"?>{SPEAK}<voice name="en-US-GuyNeural">{PROSODY}<p>Hey.</p></prosody></voice></speak>"#
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_split_inside_a_paragraph() {
+        let ssml = format!("{SPEAK}{VOICE}<p><s>One.</s><s>Two.</s></p></voice></speak>");
+        assert_eq!(split_ssml(&ssml, 1).unwrap(), [ssml]);
+    }
+
+    #[test]
+    fn rejects_unbalanced_tags() {
+        for ssml in [
+            format!("{SPEAK}{VOICE}<s>Hi.</p></voice></speak>"),
+            format!("{SPEAK}{VOICE}<s>Hi.</s></voice>"),
+        ] {
+            assert!(matches!(
+                split_ssml(&ssml, 4096).unwrap_err().kind,
+                SsmlErrorKind::UnbalancedTag(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn splits_text_at_sentence_ends() {
+        assert_eq!(
+            split_text("Pi is 3.14. It is irrational! Yes", 20),
+            ["Pi is 3.14.", "It is irrational!", "Yes"]
+        );
+        assert_eq!(split_text("aaaa bbbb cccc", 9), ["aaaa", "bbbb cccc"]);
+        assert_eq!(split_text("abcdef", 4), ["abcd", "ef"]);
+    }
+}
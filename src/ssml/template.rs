@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+
+use xml::escape::escape_str_attribute;
+
+use super::{lang_of, SsmlError, SsmlErrorKind, DEFAULT_PITCH_RATE_STR};
+use crate::TextOptions;
+
+/// The structure [`crate::interpolate_ssml`] generates when no rich SSML options are given.
+pub const DEFAULT_SSML_TEMPLATE: &str = r#"<speak xmlns="http://www.w3.org/2001/10/synthesis" xmlns:emo="http://www.w3.org/2009/10/emotionml" version="1.0" xml:lang="{lang}"><voice name="{voice}"><prosody pitch="{pitch}" rate="{rate}">{text}</prosody></voice></speak>"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Text,
+    Voice,
+    Lang,
+    Pitch,
+    Rate,
+    Style,
+    Role,
+    StyleDegree,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "text" => Self::Text,
+            "voice" => Self::Voice,
+            "lang" => Self::Lang,
+            "pitch" => Self::Pitch,
+            "rate" => Self::Rate,
+            "style" => Self::Style,
+            "role" => Self::Role,
+            "style_degree" => Self::StyleDegree,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// A template for the SSML that [`crate::interpolate_ssml`] generates from text,
+/// for users who need full control over the structure of the SSML.
+///
+/// Placeholders are written as `{name}` and use `{{`/`}}` for literal braces.
+/// The supported placeholders are
+///
+/// - `{text}`(required): the text to speak
+/// - `{voice}`: the voice
+/// - `{lang}`: the language, see [`TextOptions::lang`]
+/// - `{pitch}` and `{rate}`: default to `0%`
+/// - `{style}`: defaults to `general`
+/// - `{role}` and `{style_degree}`: empty if not specified
+///
+/// All values are XML escaped. See [`DEFAULT_SSML_TEMPLATE`] for an example.
+#[derive(Debug, Clone)]
+pub struct SsmlTemplate {
+    segments: Vec<Segment>,
+}
+
+impl SsmlTemplate {
+    /// Parse a template, failing on unknown or missing required placeholders.
+    pub fn new(template: &str) -> Result<Self, SsmlError> {
+        let invalid = |reason: String| SsmlError {
+            kind: SsmlErrorKind::InvalidTemplate(reason),
+            source: None,
+        };
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(pos) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..pos]);
+            let brace = rest.as_bytes()[pos];
+            rest = &rest[pos + 1..];
+            if rest.as_bytes().first() == Some(&brace) {
+                literal.push(brace as char);
+                rest = &rest[1..];
+                continue;
+            }
+            if brace == b'}' {
+                return Err(invalid("unmatched `}`".into()));
+            }
+            let end = rest
+                .find('}')
+                .ok_or_else(|| invalid("unclosed `{`".into()))?;
+            let name = &rest[..end];
+            let placeholder = Placeholder::parse(name)
+                .ok_or_else(|| invalid(format!("unknown placeholder `{{{name}}}`")))?;
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &rest[end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        if !segments
+            .iter()
+            .any(|s| matches!(s, Segment::Placeholder(Placeholder::Text)))
+        {
+            return Err(invalid("missing required placeholder `{text}`".into()));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Fill the template with the text and options.
+    pub fn render(&self, text: &str, options: &TextOptions) -> String {
+        let rich = options.rich_ssml_options.as_ref();
+        let mut ssml = String::new();
+        for segment in &self.segments {
+            let placeholder = match segment {
+                Segment::Literal(literal) => {
+                    ssml.push_str(literal);
+                    continue;
+                }
+                Segment::Placeholder(p) => p,
+            };
+            let value: Cow<str> = match placeholder {
+                Placeholder::Text => {
+                    ssml.push_str(&super::escape_text(text));
+                    continue;
+                }
+                Placeholder::Voice => Cow::Borrowed(&options.voice),
+                Placeholder::Lang => Cow::Borrowed(lang_of(options)),
+                Placeholder::Pitch => {
+                    Cow::Borrowed(options.pitch.as_deref().unwrap_or(DEFAULT_PITCH_RATE_STR))
+                }
+                Placeholder::Rate => {
+                    Cow::Borrowed(options.rate.as_deref().unwrap_or(DEFAULT_PITCH_RATE_STR))
+                }
+                Placeholder::Style => {
+                    Cow::Borrowed(rich.and_then(|r| r.style.as_deref()).unwrap_or("general"))
+                }
+                Placeholder::Role => Cow::Borrowed(
+                    rich.and_then(|r| r.role)
+                        .map(|role| role.into())
+                        .unwrap_or_default(),
+                ),
+                Placeholder::StyleDegree => rich
+                    .and_then(|r| r.style_degree)
+                    .map(|x| Cow::Owned(x.to_string()))
+                    .unwrap_or_default(),
+            };
+            ssml.push_str(&escape_str_attribute(&value));
+        }
+        ssml
+    }
+}
+
+impl Default for SsmlTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_SSML_TEMPLATE).expect("the default SSML template is valid")
+    }
+}
@@ -0,0 +1,380 @@
+use std::{fmt::Write, path::Path, time::Duration};
+
+use crate::{BoundaryType, WordBoundary};
+
+/// Subtitle file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum SubtitleFormat {
+    /// SubRip (`.srt`)
+    #[default]
+    Srt,
+    /// WebVTT (`.vtt`)
+    WebVtt,
+}
+
+impl SubtitleFormat {
+    /// Guess the subtitle format from the extension of a file path, defaulting to SRT.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("vtt") => Self::WebVtt,
+            _ => Self::Srt,
+        }
+    }
+}
+
+/// A single subtitle cue
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleCue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+const SENTENCE_TERMINATORS: &[char] = &['.', '!', '?', '。', '！', '？', '…'];
+
+/// Groups word boundaries into subtitle cues and renders them as subtitles.
+#[derive(Debug, Clone)]
+pub struct SubtitleGenerator {
+    /// Maximum characters in a single cue
+    pub(crate) max_chars_per_cue: usize,
+    /// Maximum duration of a single cue
+    pub(crate) max_cue_duration: Duration,
+    /// Whether to start a new cue at sentence boundaries
+    pub(crate) break_on_sentence: bool,
+}
+
+impl Default for SubtitleGenerator {
+    fn default() -> Self {
+        Self {
+            max_chars_per_cue: 42,
+            max_cue_duration: Duration::from_secs(7),
+            break_on_sentence: true,
+        }
+    }
+}
+
+impl SubtitleGenerator {
+    /// Maximum characters in a single cue
+    pub fn max_chars_per_cue(&self) -> usize {
+        self.max_chars_per_cue
+    }
+
+    /// Maximum duration of a single cue
+    pub fn max_cue_duration(&self) -> Duration {
+        self.max_cue_duration
+    }
+
+    /// Whether to start a new cue at sentence boundaries
+    pub fn break_on_sentence(&self) -> bool {
+        self.break_on_sentence
+    }
+
+    /// Create a builder for [`SubtitleGenerator`]
+    pub fn builder() -> SubtitleGeneratorBuilder {
+        SubtitleGeneratorBuilder::new()
+    }
+
+    /// Group word boundaries into cues.
+    ///
+    /// `sentences` are the sentence boundaries of the same synthesis, which may be empty.
+    /// If there are no sentence boundaries, sentence ends are detected by punctuation.
+    /// A single word that alone exceeds the limits still gets a cue of its own.
+    pub fn cues(&self, words: &[WordBoundary], sentences: &[WordBoundary]) -> Vec<SubtitleCue> {
+        let mut cues: Vec<SubtitleCue> = Vec::new();
+        let mut current: Option<SubtitleCue> = None;
+        let mut previous_offset = Duration::ZERO;
+        for word in words {
+            let is_punctuation = word.boundary_type == BoundaryType::Punctuation;
+            if let Some(cue) = current.as_ref() {
+                let starts_sentence = self.break_on_sentence
+                    && sentences
+                        .iter()
+                        .any(|s| s.offset > previous_offset && s.offset <= word.offset);
+                let len = cue.text.chars().count()
+                    + usize::from(!is_punctuation)
+                    + word.text.chars().count();
+                let too_long = len > self.max_chars_per_cue
+                    || word.end().saturating_sub(cue.start) > self.max_cue_duration;
+                if !is_punctuation && (starts_sentence || too_long) {
+                    cues.extend(current.take());
+                }
+            }
+            match current.as_mut() {
+                Some(cue) => {
+                    if !is_punctuation {
+                        cue.text.push(' ');
+                    }
+                    cue.text.push_str(&word.text);
+                    cue.end = cue.end.max(word.end());
+                }
+                // Punctuation should never start a cue
+                None if is_punctuation && !cues.is_empty() => {
+                    let cue = cues.last_mut().unwrap();
+                    cue.text.push_str(&word.text);
+                    cue.end = cue.end.max(word.end());
+                }
+                None => {
+                    current = Some(SubtitleCue {
+                        start: word.offset,
+                        end: word.end(),
+                        text: word.text.clone(),
+                    })
+                }
+            }
+            if self.break_on_sentence
+                && sentences.is_empty()
+                && word.text.ends_with(SENTENCE_TERMINATORS)
+            {
+                cues.extend(current.take());
+            }
+            previous_offset = word.offset;
+        }
+        cues.extend(current);
+        cues
+    }
+
+    /// Group word boundaries into cues and render them in the given format.
+    pub fn generate(
+        &self,
+        words: &[WordBoundary],
+        sentences: &[WordBoundary],
+        format: SubtitleFormat,
+    ) -> String {
+        render_subtitles(&self.cues(words, sentences), format)
+    }
+}
+
+/// Builder for [`SubtitleGenerator`]
+#[derive(Default)]
+pub struct SubtitleGeneratorBuilder {
+    max_chars_per_cue: Option<usize>,
+    max_cue_duration: Option<Duration>,
+    break_on_sentence: Option<bool>,
+}
+
+impl SubtitleGeneratorBuilder {
+    /// Create a new builder
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Maximum characters in a single cue, default to 42
+    pub fn max_chars_per_cue(mut self, max_chars_per_cue: usize) -> Self {
+        self.max_chars_per_cue = Some(max_chars_per_cue);
+        self
+    }
+
+    /// Maximum characters in a single cue, default to 42
+    pub fn optional_max_chars_per_cue(mut self, max_chars_per_cue: Option<usize>) -> Self {
+        self.max_chars_per_cue = max_chars_per_cue;
+        self
+    }
+
+    /// Maximum duration of a single cue, default to 7 seconds
+    pub fn max_cue_duration(mut self, max_cue_duration: Duration) -> Self {
+        self.max_cue_duration = Some(max_cue_duration);
+        self
+    }
+
+    /// Maximum duration of a single cue, default to 7 seconds
+    pub fn optional_max_cue_duration(mut self, max_cue_duration: Option<Duration>) -> Self {
+        self.max_cue_duration = max_cue_duration;
+        self
+    }
+
+    /// Whether to start a new cue at sentence boundaries, default to `true`
+    pub fn break_on_sentence(mut self, break_on_sentence: bool) -> Self {
+        self.break_on_sentence = Some(break_on_sentence);
+        self
+    }
+
+    /// Build [`SubtitleGenerator`]
+    pub fn build(self) -> SubtitleGenerator {
+        let default = SubtitleGenerator::default();
+        SubtitleGenerator {
+            max_chars_per_cue: self.max_chars_per_cue.unwrap_or(default.max_chars_per_cue),
+            max_cue_duration: self.max_cue_duration.unwrap_or(default.max_cue_duration),
+            break_on_sentence: self.break_on_sentence.unwrap_or(default.break_on_sentence),
+        }
+    }
+}
+
+fn format_timestamp(time: Duration, millis_separator: char) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis_separator,
+        millis % 1000
+    )
+}
+
+/// Render subtitle cues in the given format.
+pub fn render_subtitles(cues: &[SubtitleCue], format: SubtitleFormat) -> String {
+    let mut output = String::new();
+    let separator = match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::WebVtt => {
+            output.push_str("WEBVTT\n\n");
+            '.'
+        }
+    };
+    for (index, cue) in cues.iter().enumerate() {
+        if format == SubtitleFormat::Srt {
+            writeln!(output, "{}", index + 1).unwrap();
+        }
+        writeln!(
+            output,
+            "{} --> {}\n{}\n",
+            format_timestamp(cue.start, separator),
+            format_timestamp(cue.end, separator),
+            cue.text
+        )
+        .unwrap();
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boundary(text: &str, offset_ms: u64, duration_ms: u64) -> WordBoundary {
+        WordBoundary {
+            text: text.to_string(),
+            offset: Duration::from_millis(offset_ms),
+            duration: Duration::from_millis(duration_ms),
+            boundary_type: if text.chars().all(|c| c.is_ascii_punctuation()) {
+                BoundaryType::Punctuation
+            } else {
+                BoundaryType::Word
+            },
+        }
+    }
+
+    fn texts(cues: &[SubtitleCue]) -> Vec<&str> {
+        cues.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn splits_by_max_chars() {
+        let words = [
+            boundary("aaaa", 0, 100),
+            boundary("bbbb", 100, 100),
+            boundary("cccc", 200, 100),
+            boundary("dddd", 300, 100),
+        ];
+        let generator = SubtitleGenerator::builder().max_chars_per_cue(9).build();
+        let cues = generator.cues(&words, &[]);
+        assert_eq!(texts(&cues), ["aaaa bbbb", "cccc dddd"]);
+        assert_eq!(cues[1].start, Duration::from_millis(200));
+        assert_eq!(cues[1].end, Duration::from_millis(400));
+    }
+
+    #[test]
+    fn keeps_a_long_word_in_its_own_cue() {
+        let words = [
+            boundary("a", 0, 100),
+            boundary("supercalifragilistic", 100, 100),
+        ];
+        let generator = SubtitleGenerator::builder().max_chars_per_cue(5).build();
+        assert_eq!(
+            texts(&generator.cues(&words, &[])),
+            ["a", "supercalifragilistic"]
+        );
+    }
+
+    #[test]
+    fn splits_by_max_duration() {
+        let words = [
+            boundary("one", 0, 1000),
+            boundary("two", 1000, 1000),
+            boundary("three", 2000, 1000),
+        ];
+        let generator = SubtitleGenerator::builder()
+            .max_cue_duration(Duration::from_secs(2))
+            .build();
+        assert_eq!(texts(&generator.cues(&words, &[])), ["one two", "three"]);
+    }
+
+    #[test]
+    fn splits_by_punctuation() {
+        let words = [
+            boundary("Hi", 0, 100),
+            boundary(".", 100, 10),
+            boundary("Bye", 200, 100),
+            boundary("now", 300, 100),
+            boundary("!", 400, 10),
+        ];
+        let generator = SubtitleGenerator::default();
+        let cues = generator.cues(&words, &[]);
+        assert_eq!(texts(&cues), ["Hi.", "Bye now!"]);
+        assert_eq!(cues[0].end, Duration::from_millis(110));
+
+        let generator = SubtitleGenerator::builder()
+            .break_on_sentence(false)
+            .build();
+        assert_eq!(texts(&generator.cues(&words, &[])), ["Hi. Bye now!"]);
+    }
+
+    #[test]
+    fn splits_by_sentence_boundaries() {
+        let words = [
+            boundary("Dr", 0, 100),
+            boundary(".", 100, 10),
+            boundary("Who", 200, 100),
+            boundary("left", 300, 100),
+        ];
+        let sentences = [boundary("Dr. Who", 0, 300), boundary("left", 300, 100)];
+        let generator = SubtitleGenerator::default();
+        assert_eq!(
+            texts(&generator.cues(&words, &sentences)),
+            ["Dr. Who", "left"]
+        );
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let time = Duration::from_millis(3_723_045);
+        assert_eq!(format_timestamp(time, ','), "01:02:03,045");
+        assert_eq!(format_timestamp(time, '.'), "01:02:03.045");
+        assert_eq!(format_timestamp(Duration::ZERO, ','), "00:00:00,000");
+    }
+
+    #[test]
+    fn renders_srt_and_webvtt() {
+        let cues = [
+            SubtitleCue {
+                start: Duration::from_millis(50),
+                end: Duration::from_millis(1500),
+                text: "Hello world.".to_string(),
+            },
+            SubtitleCue {
+                start: Duration::from_millis(61_000),
+                end: Duration::from_millis(62_250),
+                text: "Bye.".to_string(),
+            },
+        ];
+        assert_eq!(
+            render_subtitles(&cues, SubtitleFormat::Srt),
+            "1\n00:00:00,050 --> 00:00:01,500\nHello world.\n\n\
+             2\n00:01:01,000 --> 00:01:02,250\nBye.\n\n"
+        );
+        assert_eq!(
+            render_subtitles(&cues, SubtitleFormat::WebVtt),
+            "WEBVTT\n\n\
+             00:00:00.050 --> 00:00:01.500\nHello world.\n\n\
+             00:01:01.000 --> 00:01:02.250\nBye.\n\n"
+        );
+    }
+
+    #[test]
+    fn guesses_the_format_from_the_path() {
+        assert_eq!(SubtitleFormat::from_path("a.VTT"), SubtitleFormat::WebVtt);
+        assert_eq!(SubtitleFormat::from_path("a.srt"), SubtitleFormat::Srt);
+        assert_eq!(SubtitleFormat::from_path("a"), SubtitleFormat::Srt);
+    }
+}
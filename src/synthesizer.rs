@@ -1,16 +1,26 @@
+use std::{borrow::Cow, error::Error, time::Duration};
+
 use log::info;
 
-use crate::{AudioFormat, AuthOptions};
+use crate::{AudioFormat, AuthOptions, SsmlHook};
 
+#[cfg(feature = "unified-synthesizer")]
+mod batch;
 #[cfg(feature = "rest-synthesizer")]
 mod rest;
+#[cfg(any(feature = "unified-synthesizer", feature = "websocket-synthesizer"))]
+mod retry;
 #[cfg(feature = "unified-synthesizer")]
 mod unified;
 #[cfg(feature = "websocket-synthesizer")]
 mod websocket;
 
+#[cfg(feature = "unified-synthesizer")]
+pub use batch::*;
 #[cfg(feature = "rest-synthesizer")]
 pub use rest::*;
+#[cfg(any(feature = "unified-synthesizer", feature = "websocket-synthesizer"))]
+pub use retry::*;
 #[cfg(feature = "unified-synthesizer")]
 pub use unified::*;
 #[cfg(feature = "websocket-synthesizer")]
@@ -23,8 +33,25 @@ pub struct SynthesizerConfig<'a> {
     pub(crate) auth: AuthOptions<'a>,
     /// The audio format of the output audio.
     pub(crate) audio_format: AudioFormat,
+    /// The hook to run on the SSML right before it is sent.
+    pub(crate) ssml_hook: Option<SsmlHook>,
+    /// The kinds of metadata to request from the websocket service.
+    #[cfg(feature = "websocket-synthesizer")]
+    pub(crate) metadata_options: MetadataOptions,
+    /// Give up connecting after this long, `None` to wait forever.
+    pub(crate) connect_timeout: Option<Duration>,
+    /// Give up a websocket synthesis if no message arrives for this long, `None` to wait forever.
+    pub(crate) idle_timeout: Option<Duration>,
+    /// The provider of fresh tokens, which takes precedence over the token in `auth`.
+    #[cfg(any(feature = "rest-synthesizer", feature = "websocket-synthesizer"))]
+    pub(crate) token_provider: Option<crate::token::SharedTokenProvider>,
 }
 
+/// The default of [`SynthesizerConfig::with_connect_timeout`]
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The default of [`SynthesizerConfig::with_idle_timeout`]
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[cfg(feature = "websocket-synthesizer")]
 const CLIENT_INFO_PAYLOAD: &str = r#"{"context":{"system":{"version":"1.25.0","name":"SpeechSDK","build":"Windows-x64"},"os":{"platform":"Windows","name":"Client","version":"10"}}}"#; // r#"{"context":{"system":{"name":"SpeechSDK","version":"1.12.1-rc.1","build":"JavaScript","lang":"JavaScript","os":{"platform":"Browser/Linux x86_64","name":"Mozilla/5.0 (X11; Linux x86_64; rv:78.0) Gecko/20100101 Firefox/78.0","version":"5.0 (X11)"}}}}"#;
 
@@ -32,12 +59,100 @@ impl<'a> SynthesizerConfig<'a> {
     /// Create a new [`SynthesizerConfig`] with the given [`AuthOptions`] and [`AudioFormat`].
     pub fn new(auth: AuthOptions<'a>, audio_format: AudioFormat) -> Self {
         info!("Successfully created SynthesizerConfig");
-        Self { auth, audio_format }
+        Self {
+            auth,
+            audio_format,
+            ssml_hook: None,
+            #[cfg(feature = "websocket-synthesizer")]
+            metadata_options: Default::default(),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            #[cfg(any(feature = "rest-synthesizer", feature = "websocket-synthesizer"))]
+            token_provider: None,
+        }
+    }
+
+    /// Convert into a [`SynthesizerConfig`] that owns all the data, so that it can be stored for later use.
+    pub fn into_owned(self) -> SynthesizerConfig<'static> {
+        SynthesizerConfig {
+            auth: self.auth.into_owned(),
+            audio_format: self.audio_format,
+            ssml_hook: self.ssml_hook,
+            #[cfg(feature = "websocket-synthesizer")]
+            metadata_options: self.metadata_options,
+            connect_timeout: self.connect_timeout,
+            idle_timeout: self.idle_timeout,
+            #[cfg(any(feature = "rest-synthesizer", feature = "websocket-synthesizer"))]
+            token_provider: self.token_provider,
+        }
+    }
+
+    /// Install a hook that inspects or transforms the final SSML right before it is sent to the service.
+    ///
+    /// See [`SsmlHook`] for details.
+    pub fn with_ssml_hook(
+        mut self,
+        hook: impl for<'b> Fn(&'b str) -> Result<Cow<'b, str>, Box<dyn Error + Send + Sync>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.ssml_hook = Some(SsmlHook::new(hook));
+        self
+    }
+
+    /// Choose the kinds of metadata(e.g. visemes) the websocket service reports, see [`MetadataOptions`].
+    #[cfg(feature = "websocket-synthesizer")]
+    pub fn with_metadata_options(mut self, metadata_options: MetadataOptions) -> Self {
+        self.metadata_options = metadata_options;
+        self
+    }
+
+    /// Give up connecting(including the TLS and proxy handshakes) with a timeout error after this long.
+    /// It is 10 seconds by default, `None` waits forever.
+    pub fn with_connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Give up a websocket synthesis with a timeout error if the service sends nothing for this long,
+    /// e.g. when the service stalls. It is 30 seconds by default, `None` waits forever.
+    ///
+    /// Unlike the deadline of [`WebsocketSynthesizer::synthesize_ssml_with_deadline`],
+    /// it does not limit how long a long synthesis takes as long as the service keeps sending audio.
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Fetch fresh tokens from the provider when connecting, e.g. short-lived Azure AD tokens,
+    /// instead of using the token in the [`AuthOptions`], see [`crate::TokenProvider`].
+    ///
+    /// The websocket synthesizer fetches a token every time it connects or reconnects,
+    /// and the RESTful synthesizer fetches one for every request.
+    #[cfg(any(feature = "rest-synthesizer", feature = "websocket-synthesizer"))]
+    pub fn with_token_provider(mut self, provider: impl crate::TokenProvider + 'static) -> Self {
+        self.token_provider = Some(crate::token::SharedTokenProvider(std::sync::Arc::new(
+            provider,
+        )));
+        self
+    }
+
+    /// The token from the provider if there is one, otherwise the token in the [`AuthOptions`]
+    #[cfg(feature = "websocket-synthesizer")]
+    pub(crate) async fn current_token(
+        &self,
+    ) -> Result<Option<Cow<'_, str>>, crate::TokenProviderError> {
+        Ok(match self.token_provider.as_ref() {
+            Some(provider) => Some(Cow::Owned(provider.0.token().await?)),
+            None => self.auth.token.as_deref().map(Cow::Borrowed),
+        })
     }
 
     #[cfg(feature = "websocket-synthesizer")]
     fn generate_client_request(
         &self,
+        token: Option<&str>,
     ) -> Result<tokio_tungstenite::tungstenite::handshake::client::Request, WebsocketSynthesizerError>
     {
         use hyper::http::HeaderValue;
@@ -51,7 +166,7 @@ impl<'a> SynthesizerConfig<'a> {
             let mut url = url::Url::parse(&self.auth.endpoint)?;
             url.query_pairs_mut()
                 .append_pair("X-ConnectionId", &request_id);
-            if let Some(auth_token) = &self.auth.token {
+            if let Some(auth_token) = token {
                 url.query_pairs_mut()
                     .append_pair("Authorization", auth_token);
             }
@@ -76,10 +191,90 @@ impl<'a> SynthesizerConfig<'a> {
     }
 
     /// Connect to the Azure Speech Service and return a [`WebsocketSynthesizer`] on success.
+    ///
+    /// Connect ahead of time to take the connection cost off of the first synthesis,
+    /// see [`WebsocketSynthesizer::warm_up`].
     #[cfg(feature = "websocket-synthesizer")]
     pub async fn connect_websocket(
         self,
     ) -> Result<WebsocketSynthesizer, WebsocketSynthesizerError> {
+        let stream = self.connect_websocket_stream().await?;
+        Ok(self.into_websocket_synthesizer(stream))
+    }
+
+    #[cfg(feature = "websocket-synthesizer")]
+    fn into_websocket_synthesizer(self, stream: crate::net::WsStream) -> WebsocketSynthesizer {
+        info!("Successfully created Synthesizer");
+        WebsocketSynthesizer {
+            audio_format: self.audio_format,
+            stream,
+            audio_metadata: None,
+            flush_interval: std::time::Duration::ZERO,
+            config: self.into_owned(),
+            connected_at: std::time::Instant::now(),
+            close_stats: Default::default(),
+            on_start: None,
+            interrupted: false,
+        }
+    }
+
+    /// Connect to the Azure Speech Service like [`Self::connect_websocket`],
+    /// retrying with backoff if the connection is reset while connecting, see [`ReconnectPolicy`].
+    #[cfg(feature = "websocket-synthesizer")]
+    pub async fn connect_websocket_with_reconnect(
+        self,
+        policy: &ReconnectPolicy,
+    ) -> Result<WebsocketSynthesizer, WebsocketSynthesizerError> {
+        let mut retries = 0;
+        loop {
+            match self.connect_websocket_stream().await {
+                Err(e) if e.is_connection_reset() && retries < policy.max_retries => {
+                    retries += 1;
+                    let backoff = policy.backoff(retries);
+                    log::warn!(
+                        "The connection was reset while connecting({e}), retrying({retries}/{}) in {backoff:?}",
+                        policy.max_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(e) => return Err(e),
+                Ok(stream) => return Ok(self.into_websocket_synthesizer(stream)),
+            }
+        }
+    }
+
+    /// Connect to the Azure Speech Service and return a [`WebsocketSynthesizer`] on success,
+    /// giving up with a [`WebsocketSynthesizerErrorKind::Timeout`] error if it does not finish before the deadline.
+    #[cfg(feature = "websocket-synthesizer")]
+    pub async fn connect_websocket_with_deadline(
+        self,
+        deadline: std::time::Instant,
+    ) -> Result<WebsocketSynthesizer, WebsocketSynthesizerError> {
+        tokio::time::timeout_at(deadline.into(), self.connect_websocket())
+            .await
+            .map_err(|_| WebsocketSynthesizerError::deadline_exceeded())?
+    }
+
+    /// Open a websocket connection to the service and send the speech config, within the connect timeout.
+    #[cfg(feature = "websocket-synthesizer")]
+    pub(crate) async fn connect_websocket_stream(
+        &self,
+    ) -> Result<crate::net::WsStream, WebsocketSynthesizerError> {
+        match self.connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.connect_websocket_stream_impl())
+                .await
+                .map_err(|_| WebsocketSynthesizerError {
+                    kind: WebsocketSynthesizerErrorKind::Timeout,
+                    source: Some(anyhow::anyhow!("failed to connect in {timeout:?}")),
+                })?,
+            None => self.connect_websocket_stream_impl().await,
+        }
+    }
+
+    #[cfg(feature = "websocket-synthesizer")]
+    async fn connect_websocket_stream_impl(
+        &self,
+    ) -> Result<crate::net::WsStream, WebsocketSynthesizerError> {
         use crate::errors::{ConnectError, ConnectErrorKind};
         use crate::net::{self, connect_directly};
         use chrono::Utc;
@@ -87,7 +282,8 @@ impl<'a> SynthesizerConfig<'a> {
         use tokio_tungstenite::tungstenite::Message;
         use uuid::Uuid;
 
-        let request = self.generate_client_request()?;
+        let token = self.current_token().await?;
+        let request = self.generate_client_request(token.as_deref())?;
         let proxy_url = self
             .auth
             .proxy
@@ -95,11 +291,11 @@ impl<'a> SynthesizerConfig<'a> {
             .map(reqwest::Url::parse)
             .transpose()
             .map_err(|e| ConnectError {
-                kind: ConnectErrorKind::BadUrl(self.auth.proxy.unwrap().to_string()),
+                kind: ConnectErrorKind::BadUrl(self.auth.proxy.as_deref().unwrap().to_string()),
                 source: Some(e.into()),
             })?;
         let mut wss = match proxy_url.as_ref().map(|x| x.scheme()) {
-            Some("socks5") => {
+            Some("socks5") | Some("socks5h") => {
                 net::connect_via_socks5_proxy(request, proxy_url.as_ref().unwrap()).await?
             }
             Some("http") | Some("https") => {
@@ -120,12 +316,7 @@ impl<'a> SynthesizerConfig<'a> {
         wss.send(Message::Text(format!(
             "Path: speech.config\r\nX-RequestId: {request_id}\r\nX-Timestamp: {now:?}Content-Type: application/json\r\n\r\n{CLIENT_INFO_PAYLOAD}"
         ))).await?;
-        info!("Successfully created Synthesizer");
-        Ok(WebsocketSynthesizer {
-            audio_format: self.audio_format,
-            stream: wss,
-            audio_metadata: None,
-        })
+        Ok(wss)
     }
 
     #[cfg(feature = "rest-synthesizer")]
@@ -138,6 +329,7 @@ impl<'a> SynthesizerConfig<'a> {
         Ok(RestSynthesizer {
             client: reqwest::Client::builder()
                 .user_agent("aspeak")
+                .optional_connect_timeout(self.connect_timeout)
                 .default_headers(header::HeaderMap::from_iter(
                     [
                         Some((
@@ -183,6 +375,10 @@ impl<'a> SynthesizerConfig<'a> {
                     source: Some(e.into()),
                 })?,
             endpoint: self.auth.endpoint.to_string(),
+            ssml_hook: self.ssml_hook.clone(),
+            token_provider: self.token_provider.clone(),
+            #[cfg(feature = "unified-synthesizer")]
+            audio_format: self.audio_format,
         })
     }
 }
@@ -0,0 +1,305 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{UnifiedSynthesizer, UnifiedSynthesizerError};
+
+type ItemResult = Result<Vec<u8>, UnifiedSynthesizerError>;
+type OnSuccess<'a> = dyn Fn(usize, &[u8]) -> Result<(), UnifiedSynthesizerError> + Sync + 'a;
+
+/// What [`synthesize_batch`] does when an item fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum BatchPolicy {
+    /// Stop at the first failure and return its error.
+    ///
+    /// The syntheses that are still in progress are cancelled, which leaves their websocket connections
+    /// in the middle of a synthesis, so reconnect them before using them again.
+    #[default]
+    FailFast,
+    /// Synthesize every item and return the result of each item.
+    ContinueOnError,
+}
+
+/// Synthesize a batch of SSML documents concurrently, each synthesizer working on one document at a time.
+///
+/// The results are in the same order as `ssmls`.
+/// With [`BatchPolicy::FailFast`](the default), either all results are `Ok` or the first error is returned.
+/// With [`BatchPolicy::ContinueOnError`], the outer result is always `Ok` and every item has its own result.
+///
+/// Nothing is synthesized if `synthesizers` is empty, in which case every item fails with
+/// [`UnifiedSynthesizerErrorKind::InvalidRequest`](super::UnifiedSynthesizerErrorKind::InvalidRequest).
+pub async fn synthesize_batch(
+    synthesizers: &mut [Box<dyn UnifiedSynthesizer>],
+    ssmls: &[impl AsRef<str> + Sync],
+    policy: BatchPolicy,
+) -> Result<Vec<ItemResult>, UnifiedSynthesizerError> {
+    let results = run_batch(
+        synthesizers,
+        ssmls,
+        policy,
+        &|_| false,
+        &|_, _| Ok(()),
+        true,
+    )
+    .await?;
+    Ok(results
+        .into_iter()
+        .map(|result| result.expect("no item is skipped"))
+        .collect())
+}
+
+/// Synthesize a batch like [`synthesize_batch`], passing the audio of each successful item to `save`
+/// as soon as it is synthesized, e.g. to write it to a file and report the progress.
+///
+/// The audio is not kept after it is saved, so large batches do not pile up in memory.
+/// An error from `save` is treated as a failure of the item.
+pub async fn synthesize_batch_with_callback(
+    synthesizers: &mut [Box<dyn UnifiedSynthesizer>],
+    ssmls: &[impl AsRef<str> + Sync],
+    policy: BatchPolicy,
+    save: impl Fn(usize, &[u8]) -> io::Result<()> + Sync,
+) -> Result<Vec<Result<(), UnifiedSynthesizerError>>, UnifiedSynthesizerError> {
+    let on_success = |index: usize, audio: &[u8]| -> Result<(), UnifiedSynthesizerError> {
+        Ok(save(index, audio)?)
+    };
+    let results = run_batch(synthesizers, ssmls, policy, &|_| false, &on_success, false).await?;
+    Ok(results
+        .into_iter()
+        .map(|result| result.expect("no item is skipped").map(|_| ()))
+        .collect())
+}
+
+/// Synthesize a batch like [`synthesize_batch`], skipping the items that are completed in the checkpoint
+/// so that a batch that failed or crashed midway can be resumed.
+///
+/// The audio of each successful item is passed to `save`(e.g. to write it to a file),
+/// then the item is marked as completed in the checkpoint, which is written to disk right away.
+/// An error from `save` or from writing the checkpoint is treated as a failure of the item.
+///
+/// Like with [`synthesize_batch_with_callback`], the audio is not kept after it is saved.
+/// The result of a skipped item is `None`.
+pub async fn synthesize_batch_with_checkpoint(
+    synthesizers: &mut [Box<dyn UnifiedSynthesizer>],
+    ssmls: &[impl AsRef<str> + Sync],
+    policy: BatchPolicy,
+    checkpoint: &BatchCheckpoint,
+    save: impl Fn(usize, &[u8]) -> io::Result<()> + Sync,
+) -> Result<Vec<Option<Result<(), UnifiedSynthesizerError>>>, UnifiedSynthesizerError> {
+    let skip = |index: usize| {
+        let completed = checkpoint.is_completed(index, ssmls[index].as_ref());
+        if completed {
+            debug!("Skipping item {index} of the batch, which is completed in the checkpoint");
+        }
+        completed
+    };
+    let on_success = |index: usize, audio: &[u8]| -> Result<(), UnifiedSynthesizerError> {
+        save(index, audio)?;
+        checkpoint.mark_completed(index, ssmls[index].as_ref())?;
+        Ok(())
+    };
+    let results = run_batch(synthesizers, ssmls, policy, &skip, &on_success, false).await?;
+    Ok(results
+        .into_iter()
+        .map(|result| result.map(|result| result.map(|_| ())))
+        .collect())
+}
+
+async fn run_batch(
+    synthesizers: &mut [Box<dyn UnifiedSynthesizer>],
+    ssmls: &[impl AsRef<str> + Sync],
+    policy: BatchPolicy,
+    skip: &(dyn Fn(usize) -> bool + Sync),
+    on_success: &OnSuccess<'_>,
+    keep_audio: bool,
+) -> Result<Vec<Option<ItemResult>>, UnifiedSynthesizerError> {
+    let pending = (0..ssmls.len()).filter(|&i| !skip(i)).collect::<Vec<_>>();
+    if synthesizers.is_empty() && !pending.is_empty() {
+        let no_synthesizer = || UnifiedSynthesizerError {
+            kind: super::UnifiedSynthesizerErrorKind::InvalidRequest,
+            source: Some(anyhow::anyhow!("no synthesizer is given for the batch")),
+        };
+        return match policy {
+            BatchPolicy::FailFast => Err(no_synthesizer()),
+            BatchPolicy::ContinueOnError => Ok((0..ssmls.len())
+                .map(|i| pending.contains(&i).then(|| Err(no_synthesizer())))
+                .collect()),
+        };
+    }
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<ItemResult>>> = Mutex::new(ssmls.iter().map(|_| None).collect());
+    let mut workers: FuturesUnordered<_> = synthesizers
+        .iter_mut()
+        .map(|synthesizer| {
+            let next = &next;
+            let results = &results;
+            let pending = &pending;
+            async move {
+                loop {
+                    let Some(&index) = pending.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                        return Ok(());
+                    };
+                    debug!("Synthesizing item {index} of the batch");
+                    let result = synthesizer
+                        .process_ssml(ssmls[index].as_ref())
+                        .await
+                        .and_then(|audio| {
+                            on_success(index, &audio).map(|_| {
+                                if keep_audio {
+                                    audio
+                                } else {
+                                    Vec::new()
+                                }
+                            })
+                        });
+                    let result = match (result, policy) {
+                        (Err(e), BatchPolicy::FailFast) => return Err(e),
+                        (result, _) => result,
+                    };
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            }
+        })
+        .collect();
+    while let Some(worker) = workers.next().await {
+        // Returning drops the other workers, which cancels their syntheses
+        worker?;
+    }
+    drop(workers);
+    Ok(results.into_inner().unwrap())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckpointData {
+    /// Hashes of the completed items by their index
+    completed: BTreeMap<usize, String>,
+}
+
+/// The progress of a batch, persisted to a file so that the batch can be resumed,
+/// see [`synthesize_batch_with_checkpoint`].
+///
+/// Items are tracked by their index and a hash of their SSML,
+/// so an item is synthesized again if the input changed since the checkpoint was written.
+/// The file is written atomically: a crash while writing leaves the previous checkpoint intact.
+#[derive(Debug)]
+pub struct BatchCheckpoint {
+    path: PathBuf,
+    data: Mutex<CheckpointData>,
+}
+
+impl BatchCheckpoint {
+    /// Load the checkpoint from the file, or start an empty one if the file does not exist.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let data = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => CheckpointData::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            data: Mutex::new(data),
+        })
+    }
+
+    /// The path of the checkpoint file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The number of completed items
+    pub fn completed(&self) -> usize {
+        self.data.lock().unwrap().completed.len()
+    }
+
+    /// Whether the item is completed, i.e. it is marked as completed with the same SSML
+    pub fn is_completed(&self, index: usize, ssml: &str) -> bool {
+        self.data.lock().unwrap().completed.get(&index) == Some(&hash(ssml))
+    }
+
+    /// Mark the item as completed and write the checkpoint to the file.
+    ///
+    /// If the file can not be written, the item is not marked as completed.
+    pub fn mark_completed(&self, index: usize, ssml: &str) -> io::Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let previous = data.completed.insert(index, hash(ssml));
+        // Keep the lock while writing so that concurrent writes do not interleave
+        let result = self.write(&data);
+        if result.is_err() {
+            match previous {
+                Some(previous) => data.completed.insert(index, previous),
+                None => data.completed.remove(&index),
+            };
+        }
+        result
+    }
+
+    /// Write the data to a temporary file and rename it over the checkpoint file
+    fn write(&self, data: &CheckpointData) -> io::Result<()> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(data)?)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)
+    }
+}
+
+/// A hash that is stable across runs and platforms(64-bit FNV-1a)
+fn hash(s: &str) -> String {
+    let hash = s.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_items_completed_only_after_writing_the_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("aspeak-checkpoint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("checkpoint.json");
+        let checkpoint = BatchCheckpoint::open(&path).unwrap();
+        checkpoint.mark_completed(0, "<speak>one</speak>").unwrap();
+        // The checkpoint can not be written once the directory is gone
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(checkpoint.mark_completed(1, "<speak>two</speak>").is_err());
+        assert!(checkpoint.mark_completed(0, "<speak>uno</speak>").is_err());
+        assert_eq!(checkpoint.completed(), 1);
+        assert!(checkpoint.is_completed(0, "<speak>one</speak>"));
+        assert!(!checkpoint.is_completed(0, "<speak>uno</speak>"));
+        assert!(!checkpoint.is_completed(1, "<speak>two</speak>"));
+    }
+
+    #[test]
+    fn resumes_from_the_checkpoint_file() {
+        let path = std::env::temp_dir().join(format!(
+            "aspeak-checkpoint-{}-resume.json",
+            std::process::id()
+        ));
+        let checkpoint = BatchCheckpoint::open(&path).unwrap();
+        checkpoint
+            .mark_completed(2, "<speak>three</speak>")
+            .unwrap();
+        let resumed = BatchCheckpoint::open(&path).unwrap();
+        assert_eq!(resumed.completed(), 1);
+        assert!(resumed.is_completed(2, "<speak>three</speak>"));
+        // The item changed since it was completed
+        assert!(!resumed.is_completed(2, "<speak>four</speak>"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,20 +1,32 @@
 use std::{
+    borrow::Cow,
     error::Error,
     fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
 };
 
 use bytes::Bytes;
-use hyper::header::{InvalidHeaderName, InvalidHeaderValue};
+use hyper::{
+    header::{self, InvalidHeaderName, InvalidHeaderValue},
+    http::HeaderValue,
+};
 use log::debug;
 use reqwest::{Client, StatusCode};
 use strum::AsRefStr;
 
-use crate::{interpolate_ssml, SsmlError, TextOptions};
+use crate::{
+    errors::parse_retry_after, interpolate_ssml, token::SharedTokenProvider, SsmlError, SsmlHook,
+    TextOptions, TokenProviderError,
+};
 
 /// The synthesizer that uses the RESTful API.
 pub struct RestSynthesizer {
     pub(super) client: Client,
     pub(super) endpoint: String,
+    pub(super) ssml_hook: Option<SsmlHook>,
+    pub(super) token_provider: Option<SharedTokenProvider>,
+    #[cfg(feature = "unified-synthesizer")]
+    pub(super) audio_format: crate::AudioFormat,
 }
 
 impl RestSynthesizer {
@@ -28,36 +40,89 @@ impl RestSynthesizer {
         &self,
         ssml: &str,
     ) -> Result<Bytes, RestSynthesizerError> {
-        let res = self
-            .client
-            .post(&self.endpoint)
-            .body(ssml.to_string())
-            .send()
-            .await
-            .map_err(|e| RestSynthesizerError {
-                kind: RestSynthesizerErrorKind::Connect,
-                source: Some(e.into()),
-            })?
-            .error_for_status()
-            .map_err(|e| {
-                use RestSynthesizerErrorKind::*;
-                let kind = match e.status() {
-                    Some(code) => match code {
-                        StatusCode::TOO_MANY_REQUESTS => TooManyRequests,
-                        StatusCode::UNAUTHORIZED => Unauthorized,
-                        StatusCode::BAD_REQUEST => InvalidRequest,
-                        StatusCode::UNSUPPORTED_MEDIA_TYPE => UnsupportedMediaType,
-                        _ => OtherHttp,
+        self.synthesize_ssml_to_bytes_impl(ssml, None).await
+    }
+
+    /// Synthesize the given SSML into audio([`Vec<u8>`]), giving up with a [`RestSynthesizerErrorKind::Timeout`] error
+    /// if it does not finish before the deadline, e.g. when the caller has already given up.
+    pub async fn synthesize_ssml_with_deadline(
+        &self,
+        ssml: &str,
+        deadline: Instant,
+    ) -> Result<Vec<u8>, RestSynthesizerError> {
+        Ok(self
+            .synthesize_ssml_to_bytes_impl(ssml, Some(deadline))
+            .await?
+            .to_vec())
+    }
+
+    async fn synthesize_ssml_to_bytes_impl(
+        &self,
+        ssml: &str,
+        deadline: Option<Instant>,
+    ) -> Result<Bytes, RestSynthesizerError> {
+        let ssml = match self.ssml_hook.as_ref() {
+            Some(hook) => hook.apply(ssml)?,
+            None => Cow::Borrowed(ssml),
+        };
+        let mut request = self.client.post(&self.endpoint).body(ssml.into_owned());
+        if let Some(provider) = self.token_provider.as_ref() {
+            // Takes precedence over the static token in the default headers
+            let token = provider.0.token().await?;
+            request = request.header(header::AUTHORIZATION, HeaderValue::from_str(&token)?);
+        }
+        if let Some(deadline) = deadline {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                return Err(RestSynthesizerError::deadline_exceeded());
+            }
+            // The timeout covers both the request and reading the response body
+            request = request.timeout(timeout);
+        }
+        let res = request.send().await.map_err(|e| RestSynthesizerError {
+            kind: if e.is_timeout() {
+                RestSynthesizerErrorKind::Timeout
+            } else {
+                RestSynthesizerErrorKind::Connect
+            },
+            source: Some(e.into()),
+        })?;
+        if let Err(e) = res.error_for_status_ref().map(|_| ()) {
+            use RestSynthesizerErrorKind::*;
+            let kind = match e.status() {
+                Some(code) => match code {
+                    StatusCode::TOO_MANY_REQUESTS => TooManyRequests {
+                        retry_after: res
+                            .headers()
+                            .get(header::RETRY_AFTER)
+                            .and_then(|value| value.to_str().ok())
+                            .and_then(parse_retry_after),
                     },
-                    None => OtherHttp,
-                };
-                RestSynthesizerError {
-                    kind,
-                    source: Some(e.into()),
+                    StatusCode::UNAUTHORIZED => Unauthorized,
+                    StatusCode::BAD_REQUEST => InvalidRequest,
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE => UnsupportedMediaType,
+                    _ => OtherHttp,
+                },
+                None => OtherHttp,
+            };
+            // The body of a BadRequest response tells what is wrong with the request
+            let source = match res.text().await {
+                Ok(body) if kind == InvalidRequest && !body.trim().is_empty() => {
+                    anyhow::Error::from(e).context(body.trim().to_string())
                 }
-            })?;
+                _ => e.into(),
+            };
+            return Err(RestSynthesizerError {
+                kind,
+                source: Some(source),
+            });
+        }
         let bytes = res.bytes().await.map_err(|e| RestSynthesizerError {
-            kind: RestSynthesizerErrorKind::Connection,
+            kind: if e.is_timeout() {
+                RestSynthesizerErrorKind::Timeout
+            } else {
+                RestSynthesizerErrorKind::Connection
+            },
             source: Some(e.into()),
         })?;
         Ok(bytes)
@@ -94,6 +159,41 @@ pub struct RestSynthesizerError {
     pub(crate) source: Option<anyhow::Error>,
 }
 
+impl RestSynthesizerError {
+    /// The delay the service asks for before retrying a rate-limited request, if any
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.kind {
+            RestSynthesizerErrorKind::TooManyRequests { retry_after } => retry_after,
+            _ => None,
+        }
+    }
+
+    fn deadline_exceeded() -> Self {
+        Self {
+            kind: RestSynthesizerErrorKind::Timeout,
+            source: Some(anyhow::anyhow!("the deadline has passed")),
+        }
+    }
+
+    /// The HTTP status code of the response if the server responded with an unsuccessful status.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.source
+            .as_ref()?
+            .chain()
+            .find_map(|e| e.downcast_ref::<reqwest::Error>().and_then(|e| e.status()))
+    }
+
+    /// Whether the request failed because the requested voice is not available,
+    /// e.g. the voice does not exist or is not available in the region.
+    pub fn is_voice_unavailable(&self) -> bool {
+        self.kind == RestSynthesizerErrorKind::InvalidRequest
+            && self.source.as_ref().is_some_and(|e| {
+                e.chain()
+                    .any(|e| e.to_string().to_lowercase().contains("voice"))
+            })
+    }
+}
+
 impl Display for RestSynthesizerError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         use RestSynthesizerErrorKind::*;
@@ -108,6 +208,13 @@ impl Display for RestSynthesizerError {
                 f,
                 "you are unauthorized. Did you set up the auth key/token?"
             ),
+            TooManyRequests {
+                retry_after: Some(retry_after),
+            } => write!(
+                f,
+                "the requests are rate limited, retry after {}s",
+                retry_after.as_secs()
+            ),
             _ => write!(f, "{} error", self.kind.as_ref()),
         }
     }
@@ -138,14 +245,17 @@ pub enum RestSynthesizerErrorKind {
     Unauthorized,
     /// The server returned a 415 Unsupported Media Type response.
     UnsupportedMediaType,
-    /// The server returned a 429 Too Many Requests response.
-    TooManyRequests,
+    /// The server returned a 429 Too Many Requests response,
+    /// optionally with the delay it asks for in the `Retry-After` header.
+    TooManyRequests { retry_after: Option<Duration> },
     /// Other HTTP errors.
     OtherHttp,
     /// Connection errors.
     Connection,
     /// Errors when interpolating SSML.
     Ssml,
+    /// The deadline passed before the synthesis finished.
+    Timeout,
 }
 
 macro_rules! impl_from_for_rest_synthesizer_error {
@@ -164,3 +274,4 @@ macro_rules! impl_from_for_rest_synthesizer_error {
 impl_from_for_rest_synthesizer_error!(InvalidHeaderValue, InvalidRequest);
 impl_from_for_rest_synthesizer_error!(InvalidHeaderName, InvalidRequest);
 impl_from_for_rest_synthesizer_error!(SsmlError, Ssml);
+impl_from_for_rest_synthesizer_error!(TokenProviderError, Connect);
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+/// When to re-run a synthesis that succeeded with empty or suspiciously short audio,
+/// see [`UnifiedSynthesizer::process_text_with_short_audio_retry`](super::UnifiedSynthesizer::process_text_with_short_audio_retry).
+///
+/// Transient server issues sometimes end a turn successfully without (much) audio.
+/// The audio is considered too short if it is empty, or if it is shorter than `min_ratio` of the expected duration.
+/// Because the expected duration is only a rough estimate(see [`crate::estimate_speech_duration`]),
+/// input expected to be shorter than `min_expected_duration` is only retried if the audio is empty,
+/// so that legitimately short input is not synthesized again and again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShortAudioRetryPolicy {
+    pub(crate) max_retries: u32,
+    pub(crate) min_ratio: f64,
+    pub(crate) min_expected_duration: Duration,
+}
+
+impl Default for ShortAudioRetryPolicy {
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl ShortAudioRetryPolicy {
+    /// Retry at most `max_retries` times with the default thresholds:
+    /// audio shorter than a quarter of the expected duration is too short, input expected to be shorter than 2 seconds
+    /// is only retried on empty audio.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            min_ratio: 0.25,
+            min_expected_duration: Duration::from_secs(2),
+        }
+    }
+
+    /// The maximum number of retries
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Audio shorter than this ratio of the expected duration is too short
+    pub fn min_ratio(&self) -> f64 {
+        self.min_ratio
+    }
+
+    /// Audio shorter than this ratio of the expected duration is too short
+    pub fn with_min_ratio(mut self, min_ratio: f64) -> Self {
+        self.min_ratio = min_ratio;
+        self
+    }
+
+    /// Input expected to be shorter than this is only retried if the audio is empty
+    pub fn min_expected_duration(&self) -> Duration {
+        self.min_expected_duration
+    }
+
+    /// Input expected to be shorter than this is only retried if the audio is empty
+    pub fn with_min_expected_duration(mut self, min_expected_duration: Duration) -> Self {
+        self.min_expected_duration = min_expected_duration;
+        self
+    }
+
+    /// Why the audio is too short, or `None` if it is fine.
+    #[cfg(feature = "unified-synthesizer")]
+    pub(crate) fn check(
+        &self,
+        format: crate::AudioFormat,
+        audio: &[u8],
+        expected: Duration,
+    ) -> Option<String> {
+        let duration = format.audio_duration(audio);
+        if audio.is_empty() || duration == Some(Duration::ZERO) {
+            return Some("the audio is empty".to_string());
+        }
+        let duration = duration?;
+        (expected >= self.min_expected_duration
+            && duration.as_secs_f64() < expected.as_secs_f64() * self.min_ratio)
+            .then(|| {
+                format!(
+                    "the audio({:.1}s) is much shorter than expected({:.1}s)",
+                    duration.as_secs_f32(),
+                    expected.as_secs_f32()
+                )
+            })
+    }
+}
+
+/// How to reconnect and retry when the connection to the service is reset,
+/// see [`WebsocketSynthesizer::synthesize_ssml_with_reconnect`](super::WebsocketSynthesizer::synthesize_ssml_with_reconnect).
+///
+/// The trial endpoint in particular sometimes drops the connection in the middle of a synthesis
+/// without a closing handshake. Only such errors are retried, see
+/// [`WebsocketSynthesizerError::is_connection_reset`](super::WebsocketSynthesizerError::is_connection_reset),
+/// as well as reconnections that are [rate limited](super::WebsocketSynthesizerErrorKind::RateLimited),
+/// for which the backoff is at least the `Retry-After` delay the service asks for.
+///
+/// The backoff before the n-th retry is `initial_backoff * 2^(n-1)`, capped at `max_backoff`,
+/// of which a random half is added as jitter so that many clients do not reconnect at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub(crate) max_retries: u32,
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl ReconnectPolicy {
+    /// Retry at most `max_retries` times, with backoff starting at 500ms and capped at 10s.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+
+    /// The maximum number of retries
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// The backoff before the first retry
+    pub fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    /// The backoff before the first retry
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// The maximum backoff before a retry, before adding jitter
+    pub fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    /// The maximum backoff before a retry, before adding jitter
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// The backoff before the `retry`-th retry(starting from 1), with jitter
+    #[cfg(feature = "websocket-synthesizer")]
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        use std::{
+            collections::hash_map::RandomState,
+            hash::{BuildHasher, Hasher},
+        };
+
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_backoff);
+        // A random number without pulling in a random number generator
+        let random = RandomState::new().build_hasher().finish();
+        let jitter = (random as f64 / u64::MAX as f64) * 0.5;
+        backoff + backoff.mul_f64(jitter)
+    }
+}
@@ -1,18 +1,71 @@
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
 };
 
 use async_trait::async_trait;
-use log::debug;
+use log::{debug, warn};
 use strum::AsRefStr;
 
-use crate::{interpolate_ssml, SsmlError, TextOptions};
+use super::ShortAudioRetryPolicy;
+use crate::{
+    concat_audio, estimate_speech_duration, interpolate_ssml, split_text, truncate_audio,
+    AudioDataError, AudioFormat, SsmlError, TextOptions,
+};
 
 #[async_trait]
 pub trait UnifiedSynthesizer: Send {
     /// Synthesize the given SSML into audio([`Vec<u8>`]).
     async fn process_ssml(&mut self, ssml: &str) -> Result<Vec<u8>, UnifiedSynthesizerError>;
+    /// Synthesize the given SSML into audio([`Vec<u8>`]), giving up with a [`UnifiedSynthesizerErrorKind::Timeout`] error
+    /// if it does not finish before the deadline.
+    async fn process_ssml_with_deadline(
+        &mut self,
+        ssml: &str,
+        deadline: Instant,
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError>;
+    /// Synthesize the given SSML into audio([`Vec<u8>`]) of at most `max_bytes`
+    /// and return whether the audio was truncated, see [`truncate_audio`].
+    ///
+    /// By default, the audio is truncated after the whole synthesis.
+    /// Websocket synthesizers stop the synthesis as soon as the limit is reached.
+    async fn process_ssml_with_max_bytes(
+        &mut self,
+        ssml: &str,
+        max_bytes: usize,
+    ) -> Result<(Vec<u8>, bool), UnifiedSynthesizerError> {
+        let mut audio = self.process_ssml(ssml).await?;
+        let truncated = truncate_audio(self.audio_format(), &mut audio, max_bytes);
+        Ok((audio, truncated))
+    }
+    /// Synthesize the given SSML into audio([`Vec<u8>`]) and report the number of bytes received so far
+    /// to `on_progress`, e.g. to show a progress bar.
+    ///
+    /// By default, the progress is only reported once after the whole synthesis.
+    /// Websocket synthesizers report it on every audio chunk.
+    async fn process_ssml_with_progress(
+        &mut self,
+        ssml: &str,
+        on_progress: &mut (dyn FnMut(usize) + Send),
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+        let audio = self.process_ssml(ssml).await?;
+        on_progress(audio.len());
+        Ok(audio)
+    }
+    /// Synthesize the given SSML and pass the audio to `on_audio`, e.g. to write it into a file or a pipe
+    /// without holding all of it in memory. Errors returned by `on_audio` abort the synthesis.
+    ///
+    /// By default, the whole audio is passed once after the synthesis.
+    /// Websocket synthesizers pass every audio chunk as soon as it arrives.
+    async fn process_ssml_streamed(
+        &mut self,
+        ssml: &str,
+        on_audio: &mut (dyn for<'a> FnMut(&'a [u8]) -> std::io::Result<()> + Send),
+    ) -> Result<(), UnifiedSynthesizerError> {
+        let audio = self.process_ssml(ssml).await?;
+        Ok(on_audio(&audio)?)
+    }
     /// This is a convenience method that interpolates the SSML for you.
     async fn process_text(
         &mut self,
@@ -23,6 +76,75 @@ pub trait UnifiedSynthesizer: Send {
         let ssml = interpolate_ssml(text, options)?;
         self.process_ssml(&ssml).await
     }
+    /// Split long text into chunks of at most `max_chars` characters with [`split_text`],
+    /// synthesize them one by one over this synthesizer(i.e. the same connection for websocket synthesizers)
+    /// and concatenate the audio with [`concat_audio`].
+    ///
+    /// It works around the limit of the service on the duration of the audio of a single request(10 minutes).
+    /// Raw, RIFF, Ogg and MP3 audio can be concatenated, but WebM can not.
+    async fn process_text_in_chunks(
+        &mut self,
+        text: &str,
+        options: &TextOptions<'_>,
+        max_chars: usize,
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+        let chunks = split_text(text, max_chars);
+        if chunks.len() <= 1 {
+            return self.process_text(text, options).await;
+        }
+        debug!("Split the text into {} chunks", chunks.len());
+        let mut segments = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            segments.push(self.process_text(chunk, options).await?);
+        }
+        Ok(concat_audio(self.audio_format(), &segments)?)
+    }
+    /// Synthesize the given SSML like [`Self::process_ssml`], but synthesize it again
+    /// if the audio is empty or much shorter than `expected`, see [`ShortAudioRetryPolicy`].
+    ///
+    /// The audio of the last attempt is returned even if it is still too short.
+    async fn process_ssml_with_short_audio_retry(
+        &mut self,
+        ssml: &str,
+        expected: Duration,
+        policy: &ShortAudioRetryPolicy,
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+        let mut retries = 0;
+        loop {
+            let audio = self.process_ssml(ssml).await?;
+            let Some(reason) = policy.check(self.audio_format(), &audio, expected) else {
+                return Ok(audio);
+            };
+            if retries >= policy.max_retries {
+                warn!("Giving up after {retries} retries, {reason}");
+                return Ok(audio);
+            }
+            retries += 1;
+            warn!(
+                "Retrying the synthesis({retries}/{}) because {reason}",
+                policy.max_retries
+            );
+        }
+    }
+    /// Synthesize the text like [`Self::process_text`], but synthesize it again if the audio is empty
+    /// or much shorter than the duration estimated by [`estimate_speech_duration`], see [`ShortAudioRetryPolicy`].
+    async fn process_text_with_short_audio_retry(
+        &mut self,
+        text: &str,
+        options: &TextOptions<'_>,
+        policy: &ShortAudioRetryPolicy,
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+        let ssml = interpolate_ssml(text, options)?;
+        let expected = estimate_speech_duration(text, options.rate());
+        self.process_ssml_with_short_audio_retry(&ssml, expected, policy)
+            .await
+    }
+    /// The format of the synthesized audio
+    fn audio_format(&self) -> AudioFormat;
+    /// Raw audio metadata of the last synthesis, if the synthesizer collects it.
+    fn audio_metadata(&self) -> Option<&[String]> {
+        None
+    }
 }
 
 /// Errors that can occur when creating and using a [`UnifiedSynthesizer`].
@@ -33,6 +155,27 @@ pub struct UnifiedSynthesizerError {
     pub(crate) source: Option<anyhow::Error>,
 }
 
+impl UnifiedSynthesizerError {
+    /// Whether the request failed because the requested voice is not available,
+    /// e.g. the voice does not exist or is not available in the region.
+    // `source` is unused if neither synthesizer is enabled
+    #[allow(unused_variables)]
+    pub fn is_voice_unavailable(&self) -> bool {
+        let Some(source) = self.source.as_ref() else {
+            return false;
+        };
+        #[cfg(feature = "rest-synthesizer")]
+        if let Some(e) = source.downcast_ref::<super::RestSynthesizerError>() {
+            return e.is_voice_unavailable();
+        }
+        #[cfg(feature = "websocket-synthesizer")]
+        if let Some(e) = source.downcast_ref::<super::WebsocketSynthesizerError>() {
+            return e.is_voice_unavailable();
+        }
+        false
+    }
+}
+
 impl Display for UnifiedSynthesizerError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         use UnifiedSynthesizerErrorKind::*;
@@ -78,6 +221,14 @@ pub enum UnifiedSynthesizerErrorKind {
     InvalidMessage,
     /// Errors that occur while processing SSML.
     Ssml,
+    /// I/O errors.
+    Io,
+    /// The deadline passed before the operation finished.
+    Timeout,
+    /// The operation was cancelled.
+    Cancelled,
+    /// Errors while processing the synthesized audio.
+    Audio,
 }
 
 macro_rules! impl_from_for_unified_synthesizer_error {
@@ -94,6 +245,8 @@ macro_rules! impl_from_for_unified_synthesizer_error {
 }
 
 impl_from_for_unified_synthesizer_error!(SsmlError, Ssml);
+impl_from_for_unified_synthesizer_error!(std::io::Error, Io);
+impl_from_for_unified_synthesizer_error!(AudioDataError, Audio);
 
 #[cfg(feature = "rest-synthesizer")]
 impl From<super::RestSynthesizerError> for UnifiedSynthesizerError {
@@ -110,7 +263,7 @@ impl From<super::RestSynthesizerError> for UnifiedSynthesizerError {
                 source: Some(value.into()),
             },
             RestKind::Unauthorized
-            | RestKind::TooManyRequests
+            | RestKind::TooManyRequests { .. }
             | RestKind::UnsupportedMediaType
             | RestKind::OtherHttp => Self {
                 kind: Http,
@@ -124,6 +277,10 @@ impl From<super::RestSynthesizerError> for UnifiedSynthesizerError {
                 kind: Ssml,
                 source: Some(value.into()),
             },
+            RestKind::Timeout => Self {
+                kind: Timeout,
+                source: Some(value.into()),
+            },
         }
     }
 }
@@ -138,6 +295,10 @@ impl From<super::WebsocketSynthesizerError> for UnifiedSynthesizerError {
                 kind: Connect,
                 source: Some(value.into()),
             },
+            WsKind::RateLimited { .. } => Self {
+                kind: Http,
+                source: Some(value.into()),
+            },
             WsKind::WebsocketConnectionClosed { code: _, reason: _ } => Self {
                 kind: Connection,
                 source: Some(value.into()),
@@ -158,6 +319,18 @@ impl From<super::WebsocketSynthesizerError> for UnifiedSynthesizerError {
                 kind: Ssml,
                 source: Some(value.into()),
             },
+            WsKind::Io => Self {
+                kind: Io,
+                source: Some(value.into()),
+            },
+            WsKind::Timeout => Self {
+                kind: Timeout,
+                source: Some(value.into()),
+            },
+            WsKind::Cancelled => Self {
+                kind: Cancelled,
+                source: Some(value.into()),
+            },
         }
     }
 }
@@ -168,6 +341,18 @@ impl UnifiedSynthesizer for super::RestSynthesizer {
     async fn process_ssml(&mut self, ssml: &str) -> Result<Vec<u8>, UnifiedSynthesizerError> {
         Ok(self.synthesize_ssml(ssml).await?)
     }
+
+    async fn process_ssml_with_deadline(
+        &mut self,
+        ssml: &str,
+        deadline: Instant,
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+        Ok(self.synthesize_ssml_with_deadline(ssml, deadline).await?)
+    }
+
+    fn audio_format(&self) -> AudioFormat {
+        self.audio_format
+    }
 }
 
 #[cfg(feature = "websocket-synthesizer")]
@@ -176,4 +361,51 @@ impl UnifiedSynthesizer for super::WebsocketSynthesizer {
     async fn process_ssml(&mut self, ssml: &str) -> Result<Vec<u8>, UnifiedSynthesizerError> {
         Ok(self.synthesize_ssml(ssml).await?)
     }
+
+    async fn process_ssml_with_deadline(
+        &mut self,
+        ssml: &str,
+        deadline: Instant,
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+        Ok(self.synthesize_ssml_with_deadline(ssml, deadline).await?)
+    }
+
+    async fn process_ssml_with_max_bytes(
+        &mut self,
+        ssml: &str,
+        max_bytes: usize,
+    ) -> Result<(Vec<u8>, bool), UnifiedSynthesizerError> {
+        Ok(self.synthesize_ssml_with_max_bytes(ssml, max_bytes).await?)
+    }
+
+    async fn process_ssml_with_progress(
+        &mut self,
+        ssml: &str,
+        on_progress: &mut (dyn FnMut(usize) + Send),
+    ) -> Result<Vec<u8>, UnifiedSynthesizerError> {
+        let mut buffer = Vec::new();
+        self.synthesize_ssml_streamed(ssml, |chunk| {
+            buffer.extend_from_slice(chunk);
+            on_progress(buffer.len());
+            Ok(())
+        })
+        .await?;
+        Ok(buffer)
+    }
+
+    async fn process_ssml_streamed(
+        &mut self,
+        ssml: &str,
+        on_audio: &mut (dyn for<'a> FnMut(&'a [u8]) -> std::io::Result<()> + Send),
+    ) -> Result<(), UnifiedSynthesizerError> {
+        Ok(self.synthesize_ssml_streamed(ssml, on_audio).await?)
+    }
+
+    fn audio_format(&self) -> AudioFormat {
+        self.audio_format
+    }
+
+    fn audio_metadata(&self) -> Option<&[String]> {
+        self.audio_metadata.as_deref()
+    }
 }
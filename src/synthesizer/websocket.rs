@@ -1,39 +1,716 @@
+use std::borrow::{BorrowMut, Cow};
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
-use crate::errors::ConnectError;
+use crate::errors::{ConnectError, ConnectErrorKind};
 use crate::msg;
 use crate::net::WsStream;
-use crate::{interpolate_ssml, msg::WebSocketMessage, AudioFormat, TextOptions};
+use crate::{
+    interpolate_ssml, msg::WebSocketMessage, parse_boundaries_in_message, parse_events_in_message,
+    parse_sentence_boundaries, parse_synthesis_events, parse_word_boundaries,
+    split_audio_by_sentences, split_audio_by_words, truncate_audio, AudioDataError,
+    AudioDataErrorKind, AudioFileWriter, AudioFormat, MetadataError, ReconnectPolicy,
+    SubtitleFormat, SubtitleGenerator, SynthesisEvent, SynthesisOutput, SynthesizerConfig,
+    TextOptions, WordBoundary,
+};
+use bytes::Bytes;
 use chrono::Utc;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{stream, SinkExt, Stream, StreamExt};
 use hyper::header::InvalidHeaderValue;
 use log::{debug, info, warn};
+use serde::Serialize;
 
 use strum::AsRefStr;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+/// Whether the error means that the connection is gone without a close frame
+fn is_connection_reset(e: &tokio_tungstenite::tungstenite::Error) -> bool {
+    use tokio_tungstenite::tungstenite::{error::ProtocolError, Error as WsError};
+    matches!(
+        e,
+        WsError::ConnectionClosed
+            | WsError::AlreadyClosed
+            | WsError::Io(_)
+            | WsError::Protocol(ProtocolError::ResetWithoutClosingHandshake)
+    )
+}
+
+/// How long [`WebsocketSynthesizer::warm_up`] waits for a pong before reconnecting
+const WARM_UP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A buffer that refuses to grow beyond its capacity
+struct CappedWriter {
+    buffer: Vec<u8>,
+    capacity: usize,
+}
+
+impl AsyncWrite for CappedWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let available = self.capacity - self.buffer.len();
+        if available == 0 && !buf.is_empty() {
+            return Poll::Ready(Err(std::io::Error::other(
+                "the maximum size of the audio is reached",
+            )));
+        }
+        let len = available.min(buf.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A writer that passes every write to a callback
+struct CallbackWriter<F> {
+    on_audio: F,
+}
+
+impl<F: FnMut(&[u8]) -> std::io::Result<()> + Unpin> AsyncWrite for CallbackWriter<F> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready((self.on_audio)(buf).map(|()| buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The state of a synthesis in progress
+struct Turn {
+    request_id: String,
+    sent_at: Instant,
+    started: bool,
+}
+
+enum AudioStreamState {
+    Pending,
+    Receiving(Turn, Vec<String>),
+    Done,
+}
+
+fn audio_stream(
+    synthesizer: impl BorrowMut<WebsocketSynthesizer> + Send,
+    ssml: impl AsRef<str> + Send,
+) -> impl Stream<Item = Result<Bytes, WebsocketSynthesizerError>> + Send {
+    stream::unfold(
+        (synthesizer, ssml, AudioStreamState::Pending),
+        |(mut synthesizer, ssml, state)| async move {
+            let syn = synthesizer.borrow_mut();
+            let (mut turn, mut metadata) = match state {
+                AudioStreamState::Done => return None,
+                AudioStreamState::Pending => match syn
+                    .start_turn(ssml.as_ref(), syn.config.metadata_options)
+                    .await
+                {
+                    Ok(turn) => (turn, Vec::new()),
+                    Err(e) => return Some((Err(e), (synthesizer, ssml, AudioStreamState::Done))),
+                },
+                AudioStreamState::Receiving(turn, metadata) => (turn, metadata),
+            };
+            let mut on_metadata = |body: &str| {
+                metadata.push(body.to_string());
+                Ok(())
+            };
+            match syn.next_audio_chunk(&mut turn, &mut on_metadata).await {
+                Ok(Some(data)) => Some((
+                    Ok(Bytes::from(data)),
+                    (
+                        synthesizer,
+                        ssml,
+                        AudioStreamState::Receiving(turn, metadata),
+                    ),
+                )),
+                Ok(None) => {
+                    syn.audio_metadata = Some(metadata);
+                    None
+                }
+                Err(e) => Some((Err(e), (synthesizer, ssml, AudioStreamState::Done))),
+            }
+        },
+    )
+}
+
+/// The kinds of metadata the service reports in the audio metadata while synthesizing,
+/// see [`SynthesizerConfig::with_metadata_options`] and [`SynthesisEvent`].
+///
+/// By default, word and punctuation boundaries are reported.
+/// Sentence boundaries, bookmarks and visemes have to be turned on explicitly. The type of the visemes is chosen in the SSML,
+/// see [`TextOptions::viseme_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MetadataOptions {
+    #[serde(rename = "wordBoundaryEnabled")]
+    pub(crate) word_boundary: bool,
+    #[serde(rename = "sentenceBoundaryEnabled")]
+    pub(crate) sentence_boundary: bool,
+    #[serde(rename = "sessionEndEnabled")]
+    pub(crate) session_end: bool,
+    #[serde(rename = "bookmarkEnabled", skip_serializing_if = "is_false")]
+    pub(crate) bookmark: bool,
+    #[serde(rename = "visemeEnabled", skip_serializing_if = "is_false")]
+    pub(crate) viseme: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self {
+            word_boundary: true,
+            sentence_boundary: false,
+            session_end: false,
+            bookmark: false,
+            viseme: false,
+        }
+    }
+}
+
+impl MetadataOptions {
+    /// Whether word and punctuation boundaries are reported
+    pub fn word_boundary(&self) -> bool {
+        self.word_boundary
+    }
+
+    /// Whether word and punctuation boundaries are reported
+    pub fn with_word_boundary(mut self, enabled: bool) -> Self {
+        self.word_boundary = enabled;
+        self
+    }
+
+    /// Whether sentence boundaries are reported
+    pub fn sentence_boundary(&self) -> bool {
+        self.sentence_boundary
+    }
+
+    /// Whether sentence boundaries are reported
+    pub fn with_sentence_boundary(mut self, enabled: bool) -> Self {
+        self.sentence_boundary = enabled;
+        self
+    }
+
+    /// Whether the end of the session is reported
+    pub fn session_end(&self) -> bool {
+        self.session_end
+    }
+
+    /// Whether the end of the session is reported
+    pub fn with_session_end(mut self, enabled: bool) -> Self {
+        self.session_end = enabled;
+        self
+    }
+
+    /// Whether the bookmarks in the SSML are reported
+    pub fn bookmark(&self) -> bool {
+        self.bookmark
+    }
+
+    /// Whether the bookmarks in the SSML are reported
+    pub fn with_bookmark(mut self, enabled: bool) -> Self {
+        self.bookmark = enabled;
+        self
+    }
+
+    /// Whether visemes are reported
+    pub fn viseme(&self) -> bool {
+        self.viseme
+    }
+
+    /// Whether visemes are reported
+    pub fn with_viseme(mut self, enabled: bool) -> Self {
+        self.viseme = enabled;
+        self
+    }
+}
+
 /// The main struct for interacting with the Azure Speech Service.
+///
+/// A synthesizer holds one websocket connection, which can be reused for any number of consecutive syntheses
+/// to save the cost of connecting for each of them, e.g. in batch jobs. Every synthesis gets its own request id,
+/// and [`Self::audio_metadata`] only ever holds the metadata of the last synthesis.
+///
+/// A synthesis can be abandoned at any await point, e.g. by dropping its future in `tokio::select!`,
+/// dropping its stream or [cancelling](Self::synthesize_ssml_with_cancellation) it.
+/// The synthesizer stays usable afterwards: the next synthesis notices that the last one did not finish
+/// and reconnects first, so no leftover of the abandoned synthesis can leak into it.
 pub struct WebsocketSynthesizer {
     pub(super) audio_format: AudioFormat,
     pub(super) stream: WsStream,
     pub audio_metadata: Option<Vec<String>>,
+    pub(super) flush_interval: Duration,
+    /// The config used to connect, kept for reconnecting
+    pub(super) config: SynthesizerConfig<'static>,
+    pub(super) connected_at: Instant,
+    pub(super) close_stats: CloseStats,
+    pub(super) on_start: Option<Box<OnStart>>,
+    /// Whether the last synthesis was interrupted before its `turn.end`,
+    /// which leaves the connection unfit for the next one
+    pub(super) interrupted: bool,
+}
+
+type OnStart = dyn Fn(Duration) + Send + Sync;
+
+/// Statistics about how the websocket connections of a [`WebsocketSynthesizer`] were closed,
+/// for diagnosing flaky endpoints.
+///
+/// A close is clean if the server sent a close frame with the normal closure code (1000),
+/// and abnormal if it sent another code, no close frame at all, or the connection was reset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloseStats {
+    pub clean_closes: u64,
+    pub abnormal_closes: u64,
+    /// The code of the last close frame, `None` if the last connection was closed without one
+    pub last_code: Option<u16>,
+    /// The reason of the last close frame
+    pub last_reason: Option<String>,
+    /// How long the last closed connection had been open
+    pub last_connection_age: Option<Duration>,
+}
+
+impl CloseStats {
+    fn record(&mut self, code: Option<u16>, reason: Option<String>, age: Duration) {
+        if code == Some(1000) {
+            self.clean_closes += 1;
+        } else {
+            self.abnormal_closes += 1;
+        }
+        debug!("Websocket connection closed after {age:?}, code: {code:?}, reason: {reason:?}");
+        self.last_code = code;
+        self.last_reason = reason;
+        self.last_connection_age = Some(age);
+    }
 }
 
 impl WebsocketSynthesizer {
+    /// The minimum interval between two flushes of the writer in [`Self::synthesize_ssml_to_writer`]
+    pub fn flush_interval(&self) -> Duration {
+        self.flush_interval
+    }
+
+    /// Set the minimum interval between two flushes of the writer in [`Self::synthesize_ssml_to_writer`].
+    ///
+    /// By default it is zero, which means the writer is flushed after every audio chunk
+    /// so that consumers like pipes and players receive the audio as soon as possible.
+    /// A longer interval saves syscalls at the cost of latency.
+    pub fn set_flush_interval(&mut self, flush_interval: Duration) {
+        self.flush_interval = flush_interval;
+    }
+
+    /// Set a hook that is called when the first response of a synthesis arrives,
+    /// which signals that the synthesis has started and audio is imminent.
+    ///
+    /// The hook receives the time elapsed since the SSML was sent, i.e. the time to first byte of the response.
+    /// It is called at most once per synthesis, before any audio is written.
+    pub fn set_on_start(&mut self, on_start: impl Fn(Duration) + Send + Sync + 'static) {
+        self.on_start = Some(Box::new(on_start));
+    }
+
+    /// Statistics about how the connections of this synthesizer were closed, including the ones before [reconnecting](Self::reconnect).
+    pub fn close_stats(&self) -> &CloseStats {
+        &self.close_stats
+    }
+
+    fn record_close(&mut self, code: Option<u16>, reason: Option<String>) {
+        let age = self.connected_at.elapsed();
+        self.close_stats.record(code, reason, age);
+    }
+
+    /// Re-establish the connection with the config that was used to connect in the first place,
+    /// e.g. after the connection is closed by the server or broken by a network error.
+    ///
+    /// The old connection is dropped without closing it gracefully.
+    pub async fn reconnect(&mut self) -> Result<(), WebsocketSynthesizerError> {
+        self.stream = self.config.connect_websocket_stream().await?;
+        self.connected_at = Instant::now();
+        self.audio_metadata = None;
+        self.interrupted = false;
+        info!("Successfully reconnected Synthesizer");
+        Ok(())
+    }
+
+    /// Make sure the connection is ready for the next synthesis, reconnecting if it is gone,
+    /// and return the round trip time of the check.
+    ///
+    /// [Connecting](SynthesizerConfig::connect_websocket) already establishes the websocket and sends the `speech.config`,
+    /// so connecting ahead of the first request takes the connect and handshake cost off of it.
+    /// But the service drops idle connections after a while (a few minutes at the time of writing),
+    /// so a held connection may be gone by the time it is used.
+    /// Call this method right before a latency-sensitive synthesis, or periodically as a keepalive,
+    /// to check the connection with a websocket ping and reconnect if there is no pong in time.
+    /// Compare the connect time with the synthesis time in the `--report` of the CLI to see the difference.
+    pub async fn warm_up(&mut self) -> Result<Duration, WebsocketSynthesizerError> {
+        let start = Instant::now();
+        if self.interrupted {
+            info!("The last synthesis was abandoned, reconnecting");
+            self.reconnect().await?;
+            return Ok(start.elapsed());
+        }
+        match tokio::time::timeout(WARM_UP_TIMEOUT, self.ping()).await {
+            Ok(Ok(())) => {
+                let rtt = start.elapsed();
+                debug!("The connection is alive, round trip time: {rtt:?}");
+                Ok(rtt)
+            }
+            Ok(Err(e)) => {
+                info!("The connection is gone({e}), reconnecting");
+                self.reconnect().await?;
+                Ok(start.elapsed())
+            }
+            Err(_) => {
+                info!("No pong received in {WARM_UP_TIMEOUT:?}, reconnecting");
+                self.reconnect().await?;
+                Ok(start.elapsed())
+            }
+        }
+    }
+
+    /// Send a ping and wait for the pong
+    async fn ping(&mut self) -> Result<(), WebsocketSynthesizerError> {
+        self.stream.send(Message::Ping(Vec::new())).await?;
+        while let Some(raw_msg) = self.stream.next().await.transpose()? {
+            match WebSocketMessage::try_from(&raw_msg)? {
+                WebSocketMessage::Pong => return Ok(()),
+                WebSocketMessage::Close(frame) => {
+                    self.record_close(
+                        frame.map(|fr| fr.code.into()),
+                        frame.map(|fr| fr.reason.to_string()),
+                    );
+                    return Err(WebsocketSynthesizerError::connection_closed(
+                        frame.map_or_else(|| "Unknown".to_string(), |fr| fr.code.to_string()),
+                        frame.map_or_else(String::new, |fr| fr.reason.to_string()),
+                    ));
+                }
+                msg => debug!("Ignoring a message while waiting for pong: {msg:?}"),
+            }
+        }
+        self.record_close(None, None);
+        Err(WebsocketSynthesizerError::connection_closed(
+            "Unknown".to_string(),
+            "The connection is closed".to_string(),
+        ))
+    }
+
     /// Synthesize the given SSML into audio([`Vec<u8>`]).
     pub async fn synthesize_ssml(
         &mut self,
         ssml: &str,
     ) -> Result<Vec<u8>, WebsocketSynthesizerError> {
+        let mut buffer = Vec::new();
+        self.synthesize_ssml_streamed(ssml, |chunk| {
+            buffer.extend_from_slice(chunk);
+            Ok(())
+        })
+        .await?;
+        Ok(buffer)
+    }
+
+    /// Synthesize the given SSML and pass every audio chunk to `on_audio` as soon as it arrives,
+    /// e.g. to pipe the audio to a player or a socket without waiting for the whole synthesis.
+    ///
+    /// The audio metadata is collected into [`Self::audio_metadata`] like [`Self::synthesize_ssml`] does.
+    /// Errors returned by `on_audio` abort the synthesis with a [`WebsocketSynthesizerErrorKind::Io`] error.
+    /// Use [`Self::synthesize_ssml_to_writer`] if the consumer needs backpressure.
+    pub async fn synthesize_ssml_streamed(
+        &mut self,
+        ssml: &str,
+        on_audio: impl FnMut(&[u8]) -> std::io::Result<()> + Unpin,
+    ) -> Result<(), WebsocketSynthesizerError> {
+        self.synthesize_ssml_to_writer(ssml, &mut CallbackWriter { on_audio })
+            .await
+    }
+
+    /// Synthesize the given SSML into audio([`Vec<u8>`]), reconnecting and sending the SSML again
+    /// if the connection is reset in the middle of the synthesis, see [`ReconnectPolicy`].
+    ///
+    /// Other errors, e.g. an invalid request, are returned right away.
+    pub async fn synthesize_ssml_with_reconnect(
+        &mut self,
+        ssml: &str,
+        policy: &ReconnectPolicy,
+    ) -> Result<Vec<u8>, WebsocketSynthesizerError> {
+        let mut retries = 0;
+        loop {
+            let result = if retries == 0 {
+                self.synthesize_ssml(ssml).await
+            } else {
+                match self.reconnect().await {
+                    Ok(()) => self.synthesize_ssml(ssml).await,
+                    Err(e) => Err(e),
+                }
+            };
+            match result {
+                Err(e) if e.is_connection_reset() && retries < policy.max_retries => {
+                    retries += 1;
+                    let backoff = policy.backoff(retries);
+                    warn!(
+                        "The connection was reset({e}), retrying({retries}/{}) in {backoff:?}",
+                        policy.max_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(e)
+                    if matches!(e.kind, WebsocketSynthesizerErrorKind::RateLimited { .. })
+                        && retries < policy.max_retries =>
+                {
+                    retries += 1;
+                    // Wait at least as long as the service asks for
+                    let backoff = policy
+                        .backoff(retries)
+                        .max(e.retry_after().unwrap_or_default());
+                    warn!(
+                        "The requests are rate limited, retrying({retries}/{}) in {backoff:?}",
+                        policy.max_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Synthesize the given SSML into audio([`Vec<u8>`]), giving up with a [`WebsocketSynthesizerErrorKind::Timeout`] error
+    /// if it does not finish before the deadline, e.g. when the caller has already given up.
+    ///
+    /// The connection is left in the middle of a synthesis when the deadline passes,
+    /// so the next synthesis reconnects first.
+    pub async fn synthesize_ssml_with_deadline(
+        &mut self,
+        ssml: &str,
+        deadline: std::time::Instant,
+    ) -> Result<Vec<u8>, WebsocketSynthesizerError> {
+        tokio::time::timeout_at(deadline.into(), self.synthesize_ssml(ssml))
+            .await
+            .map_err(|_| WebsocketSynthesizerError::deadline_exceeded())?
+    }
+
+    /// Synthesize the given SSML into audio([`Vec<u8>`]) of at most `max_bytes`,
+    /// which guards against pathological inputs producing huge outputs.
+    ///
+    /// Once the limit is reached, the synthesis is stopped and the websocket connection is closed,
+    /// so the next synthesis reconnects first.
+    /// The audio is truncated as described in [`truncate_audio`] and the returned flag tells whether it was truncated.
+    pub async fn synthesize_ssml_with_max_bytes(
+        &mut self,
+        ssml: &str,
+        max_bytes: usize,
+    ) -> Result<(Vec<u8>, bool), WebsocketSynthesizerError> {
+        let mut writer = CappedWriter {
+            buffer: Vec::new(),
+            // Accept one more byte to tell whether the audio exceeds the limit
+            capacity: max_bytes.saturating_add(1),
+        };
+        let result = self.synthesize_ssml_to_writer(ssml, &mut writer).await;
+        let mut audio = writer.buffer;
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind == WebsocketSynthesizerErrorKind::Io && audio.len() > max_bytes => {
+                info!("The audio exceeds {max_bytes} bytes, closing the websocket connection");
+                self.interrupted = true;
+                if let Err(e) = self.stream.close(None).await {
+                    debug!("Failed to close the websocket connection cleanly: {e}");
+                }
+            }
+            Err(e) => return Err(e),
+        }
+        let truncated = truncate_audio(self.audio_format, &mut audio, max_bytes);
+        Ok((audio, truncated))
+    }
+
+    /// Synthesize the given SSML into audio([`Vec<u8>`]), or stop with a
+    /// [`WebsocketSynthesizerErrorKind::Cancelled`] error as soon as the token is cancelled.
+    ///
+    /// The audio received before the cancellation is discarded,
+    /// use [`Self::synthesize_ssml_to_writer_with_cancellation`] to keep it.
+    pub async fn synthesize_ssml_with_cancellation(
+        &mut self,
+        ssml: &str,
+        token: &CancellationToken,
+    ) -> Result<Vec<u8>, WebsocketSynthesizerError> {
+        let mut buffer = Vec::new();
+        self.synthesize_ssml_to_writer_with_cancellation(ssml, &mut buffer, token)
+            .await?;
+        Ok(buffer)
+    }
+
+    /// Synthesize the given SSML and write the audio into `writer` like [`Self::synthesize_ssml_to_writer`],
+    /// or stop with a [`WebsocketSynthesizerErrorKind::Cancelled`] error as soon as the token is cancelled.
+    ///
+    /// On cancellation, the websocket connection is closed cleanly and the synthesizer reconnects
+    /// at the start of the next synthesis, so it can be reused right away. The audio written before the cancellation is kept in the writer
+    /// but it is not flushed, and it may end in the middle of a frame for compressed formats.
+    pub async fn synthesize_ssml_to_writer_with_cancellation(
+        &mut self,
+        ssml: &str,
+        writer: &mut (impl AsyncWrite + Unpin + ?Sized),
+        token: &CancellationToken,
+    ) -> Result<(), WebsocketSynthesizerError> {
+        tokio::select! {
+            result = self.synthesize_ssml_to_writer(ssml, writer) => return result,
+            _ = token.cancelled() => {}
+        }
+        info!("Synthesis cancelled, closing the websocket connection");
+        self.interrupted = true;
+        if let Err(e) = self.stream.close(None).await {
+            debug!("Failed to close the websocket connection cleanly: {e}");
+        }
+        Err(WebsocketSynthesizerError {
+            kind: WebsocketSynthesizerErrorKind::Cancelled,
+            source: None,
+        })
+    }
+
+    /// Synthesize the given SSML and write the audio into `writer` as soon as it arrives.
+    ///
+    /// If the writer is not ready to accept more data, e.g. a full [`crate::AudioRingBufferWriter`],
+    /// receiving from the server is paused until it is.
+    /// The writer is flushed after audio chunks according to the [flush interval](Self::set_flush_interval)
+    /// and when the synthesis finishes, but it is not shut down.
+    pub async fn synthesize_ssml_to_writer(
+        &mut self,
+        ssml: &str,
+        writer: &mut (impl AsyncWrite + Unpin + ?Sized),
+    ) -> Result<(), WebsocketSynthesizerError> {
+        let mut audio_metadata = Vec::new();
+        let metadata_options = self.config.metadata_options;
+        self.synthesize_ssml_with_metadata_handler(ssml, metadata_options, writer, |body| {
+            audio_metadata.push(body.to_string());
+            Ok(())
+        })
+        .await?;
+        self.audio_metadata = Some(audio_metadata);
+        Ok(())
+    }
+
+    /// Synthesize the given SSML and write the audio into the file at `path` as soon as it arrives,
+    /// e.g. for long audio like audiobook chapters, see [`AudioFileWriter`].
+    ///
+    /// The file is created or truncated. If the synthesis fails, the audio received so far is kept in it.
+    pub async fn synthesize_ssml_to_file(
+        &mut self,
+        ssml: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<(), WebsocketSynthesizerError> {
+        let mut writer = AudioFileWriter::create(path)?;
+        self.synthesize_ssml_to_writer(ssml, &mut writer).await?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Synthesize the given SSML, write the audio into `writer` and pass the word, punctuation and sentence boundaries
+    /// to `on_boundary`, both as soon as they arrive.
+    ///
+    /// Unlike [`Self::synthesize_ssml_to_writer`], the audio metadata is not collected into [`Self::audio_metadata`],
+    /// so neither the audio nor the metadata of a long input is fully buffered in memory.
+    /// Errors returned by `on_boundary`(e.g. when writing the boundaries to a file) abort the synthesis.
+    pub async fn synthesize_ssml_streaming(
+        &mut self,
+        ssml: &str,
+        writer: &mut (impl AsyncWrite + Unpin + ?Sized),
+        mut on_boundary: impl FnMut(WordBoundary) -> std::io::Result<()>,
+    ) -> Result<(), WebsocketSynthesizerError> {
+        let metadata_options = self.config.metadata_options;
+        self.synthesize_ssml_with_metadata_handler(ssml, metadata_options, writer, |body| {
+            for boundary in parse_boundaries_in_message(body)? {
+                on_boundary(boundary)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Synthesize the given SSML, write the audio into `writer` and pass every [`SynthesisEvent`](e.g. visemes
+    /// for lip-sync) to `on_event`, both as soon as they arrive.
+    ///
+    /// Like [`Self::synthesize_ssml_streaming`], the audio metadata is not collected into [`Self::audio_metadata`].
+    /// Which kinds of events the service reports depends on the metadata options of the synthesis context.
+    pub async fn synthesize_ssml_with_events(
+        &mut self,
+        ssml: &str,
+        writer: &mut (impl AsyncWrite + Unpin + ?Sized),
+        mut on_event: impl FnMut(SynthesisEvent) -> std::io::Result<()>,
+    ) -> Result<(), WebsocketSynthesizerError> {
+        let metadata_options = self.config.metadata_options;
+        self.synthesize_ssml_with_metadata_handler(ssml, metadata_options, writer, |body| {
+            for event in parse_events_in_message(body)? {
+                on_event(event)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn synthesize_ssml_with_metadata_handler(
+        &mut self,
+        ssml: &str,
+        metadata_options: MetadataOptions,
+        writer: &mut (impl AsyncWrite + Unpin + ?Sized),
+        mut on_metadata: impl FnMut(&str) -> Result<(), WebsocketSynthesizerError>,
+    ) -> Result<(), WebsocketSynthesizerError> {
+        let mut turn = self.start_turn(ssml, metadata_options).await?;
+        let mut last_flush = Instant::now();
+        while let Some(data) = self.next_audio_chunk(&mut turn, &mut on_metadata).await? {
+            writer.write_all(&data).await?;
+            if last_flush.elapsed() >= self.flush_interval {
+                writer.flush().await?;
+                last_flush = Instant::now();
+            }
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Send the synthesis context with the given metadata options and the SSML to start a synthesis
+    async fn start_turn(
+        &mut self,
+        ssml: &str,
+        metadata_options: MetadataOptions,
+    ) -> Result<Turn, WebsocketSynthesizerError> {
+        if self.interrupted {
+            info!("The last synthesis was abandoned, reconnecting before the next one");
+            self.reconnect().await?;
+        }
+        self.audio_metadata = None;
+        let ssml = match self.config.ssml_hook.as_ref() {
+            Some(hook) => hook.apply(ssml)?,
+            None => Cow::Borrowed(ssml),
+        };
         let uuid = Uuid::new_v4();
         let request_id = uuid.as_simple();
         let now = Utc::now();
-        let synthesis_context = format!(
-            r#"{{"synthesis":{{"audio":{{"metadataOptions":{{"sentenceBoundaryEnabled":false,"wordBoundaryEnabled":true,"sessionEndEnabled":false}},"outputFormat":"{}"}}}}}}"#,
-            Into::<&str>::into(self.audio_format)
-        );
+        let synthesis_context = serde_json::json!({
+            "synthesis": {
+                "audio": {
+                    "metadataOptions": metadata_options,
+                    "outputFormat": Into::<&str>::into(self.audio_format),
+                }
+            }
+        });
+        self.interrupted = true;
         self.stream.send(Message::Text(format!(
             "Path: synthesis.context\r\nX-RequestId: {request_id}\r\nX-Timestamp: {now:?}Content-Type: application/json\r\n\r\n{synthesis_context}", 
             request_id = &request_id)),
@@ -42,22 +719,71 @@ impl WebsocketSynthesizer {
         self.stream.send(Message::Text(format!(
             "Path: ssml\r\nX-RequestId: {request_id}\r\nX-Timestamp: {now:?}\r\nContent-Type: application/ssml+xml\r\n\r\n{ssml}"
         ))).await?;
-        let mut buffer = Vec::new();
-        let mut audio_metadata = Vec::new();
-        while let Some(raw_msg) = self.stream.next().await.transpose()? {
+        Ok(Turn {
+            request_id: request_id.to_string(),
+            sent_at: Instant::now(),
+            started: false,
+        })
+    }
+
+    /// Receive messages until the next audio chunk arrives, or return `None` at the end of the turn.
+    async fn next_audio_chunk(
+        &mut self,
+        turn: &mut Turn,
+        on_metadata: &mut impl FnMut(&str) -> Result<(), WebsocketSynthesizerError>,
+    ) -> Result<Option<Vec<u8>>, WebsocketSynthesizerError> {
+        loop {
+            let next = match self.config.idle_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, self.stream.next())
+                    .await
+                    .map_err(|_| WebsocketSynthesizerError {
+                        kind: WebsocketSynthesizerErrorKind::Timeout,
+                        source: Some(anyhow::anyhow!("no message is received in {timeout:?}")),
+                    })?,
+                None => self.stream.next().await,
+            };
+            let raw_msg = match next.transpose() {
+                Ok(Some(raw_msg)) => raw_msg,
+                Ok(None) => {
+                    self.record_close(None, None);
+                    return Ok(None);
+                }
+                Err(e) => {
+                    if is_connection_reset(&e) {
+                        self.record_close(None, Some(e.to_string()));
+                    }
+                    return Err(e.into());
+                }
+            };
+            if let Some(request_id) = msg::request_id(&raw_msg) {
+                if !request_id.eq_ignore_ascii_case(&turn.request_id) {
+                    debug!("Skipping a message of an earlier request {request_id}");
+                    continue;
+                }
+            }
             let msg = WebSocketMessage::try_from(&raw_msg)?;
             match msg {
-                WebSocketMessage::TurnStart | WebSocketMessage::Response { body: _ } => continue,
-                WebSocketMessage::Audio { data } => {
-                    buffer.extend_from_slice(data);
-                }
-                WebSocketMessage::AudioMetadata { body } => {
-                    audio_metadata.push(body.to_string());
+                WebSocketMessage::TurnStart => continue,
+                WebSocketMessage::Response { body: _ } => {
+                    if !turn.started {
+                        turn.started = true;
+                        debug!("Synthesis started after {:?}", turn.sent_at.elapsed());
+                        if let Some(on_start) = self.on_start.as_ref() {
+                            on_start(turn.sent_at.elapsed());
+                        }
+                    }
                 }
+                WebSocketMessage::Audio { data } => return Ok(Some(data.to_vec())),
+                WebSocketMessage::AudioMetadata { body } => on_metadata(body)?,
                 WebSocketMessage::TurnEnd => {
-                    break;
+                    self.interrupted = false;
+                    return Ok(None);
                 }
                 WebSocketMessage::Close(frame) => {
+                    self.record_close(
+                        frame.as_ref().map(|fr| fr.code.into()),
+                        frame.as_ref().map(|fr| fr.reason.to_string()),
+                    );
                     return Err(frame.map_or_else(
                         || {
                             WebsocketSynthesizerError::connection_closed(
@@ -76,9 +802,158 @@ impl WebsocketSynthesizer {
                 msg => warn!("Received a message that is not handled: {:?}", msg),
             }
         }
+    }
+
+    /// Synthesize the given SSML into a [`Stream`] of audio chunks that are yielded as soon as they arrive,
+    /// e.g. to forward the audio into a sink or to stream it to the client of a web server.
+    ///
+    /// The stream ends after the synthesis is finished, and the audio metadata is then collected into
+    /// [`Self::audio_metadata`]. Errors, including the server closing the connection, are yielded as the last item.
+    /// Wrap the stream with `tokio_util::io::StreamReader` to get an `AsyncRead`.
+    ///
+    /// Dropping the stream before it ends leaves the connection in the middle of a synthesis,
+    /// so the next synthesis reconnects first.
+    pub fn synthesize_ssml_stream<'a>(
+        &'a mut self,
+        ssml: &'a str,
+    ) -> impl Stream<Item = Result<Bytes, WebsocketSynthesizerError>> + Send + 'a {
+        audio_stream(self, ssml)
+    }
+
+    /// Like [`Self::synthesize_ssml_stream`], but the stream owns the synthesizer and the SSML,
+    /// for APIs that need a `'static` stream, e.g. the response body of a web framework.
+    pub fn into_ssml_stream(
+        self,
+        ssml: impl Into<String>,
+    ) -> impl Stream<Item = Result<Bytes, WebsocketSynthesizerError>> + Send + 'static {
+        audio_stream(self, ssml.into())
+    }
+
+    /// Synthesize the given SSML into a [`SynthesisOutput`] that carries the word boundaries and the other events
+    /// along with the audio, e.g. to tell the [duration](SynthesisOutput::duration) of the audio.
+    pub async fn synthesize_ssml_to_output(
+        &mut self,
+        ssml: &str,
+    ) -> Result<SynthesisOutput, WebsocketSynthesizerError> {
+        let audio = self.synthesize_ssml(ssml).await?;
+        let metadata = self.audio_metadata.as_deref().unwrap_or_default();
+        let word_boundaries = parse_word_boundaries(metadata)?;
+        let events = parse_synthesis_events(metadata)?;
+        Ok(SynthesisOutput::new(audio, self.audio_format, word_boundaries).with_events(events))
+    }
+
+    /// Synthesize the given SSML into audio([`Vec<u8>`]) along with all the [`SynthesisEvent`]s reported by the service.
+    pub async fn synthesize_ssml_to_events(
+        &mut self,
+        ssml: &str,
+    ) -> Result<(Vec<u8>, Vec<SynthesisEvent>), WebsocketSynthesizerError> {
+        let audio = self.synthesize_ssml(ssml).await?;
+        let events = parse_synthesis_events(self.audio_metadata.as_deref().unwrap_or_default())?;
+        Ok((audio, events))
+    }
+
+    /// Synthesize the given SSML and split the audio into one segment per sentence,
+    /// which is returned along with the text of the sentence.
+    ///
+    /// This is useful for playing the text sentence by sentence without synthesizing each sentence separately.
+    /// The audio format must be an uncompressed(raw or RIFF PCM) format, see [`split_audio_by_sentences`].
+    /// Sentence boundaries are requested for this synthesis regardless of the [`MetadataOptions`].
+    pub async fn synthesize_ssml_by_sentence(
+        &mut self,
+        ssml: &str,
+    ) -> Result<Vec<(String, Vec<u8>)>, WebsocketSynthesizerError> {
+        self.check_pcm_format()?;
+        // Only for this synthesis, the options of the synthesizer are left untouched
+        let metadata_options = self.config.metadata_options.with_sentence_boundary(true);
+        let mut audio = Vec::new();
+        let mut audio_metadata = Vec::new();
+        self.synthesize_ssml_with_metadata_handler(ssml, metadata_options, &mut audio, |body| {
+            audio_metadata.push(body.to_string());
+            Ok(())
+        })
+        .await?;
+        let sentences = parse_sentence_boundaries(&audio_metadata)?;
         self.audio_metadata = Some(audio_metadata);
+        split_audio_by_sentences(self.audio_format, &audio, &sentences).map_err(|e| {
+            WebsocketSynthesizerError {
+                kind: WebsocketSynthesizerErrorKind::InvalidMessage,
+                source: Some(e.into()),
+            }
+        })
+    }
 
-        Ok(buffer)
+    /// Synthesize the given SSML and split the audio into one segment per word(or punctuation),
+    /// which is returned along with its boundary.
+    ///
+    /// This is useful for playing or highlighting each word independently.
+    /// The audio format must be an uncompressed(raw or RIFF PCM) format, see [`split_audio_by_words`].
+    pub async fn synthesize_ssml_by_word(
+        &mut self,
+        ssml: &str,
+    ) -> Result<Vec<(WordBoundary, Vec<u8>)>, WebsocketSynthesizerError> {
+        self.check_pcm_format()?;
+        let audio = self.synthesize_ssml(ssml).await?;
+        let words = parse_word_boundaries(self.audio_metadata.as_deref().unwrap_or_default())?;
+        split_audio_by_words(self.audio_format, &audio, &words).map_err(|e| {
+            WebsocketSynthesizerError {
+                kind: WebsocketSynthesizerErrorKind::InvalidMessage,
+                source: Some(e.into()),
+            }
+        })
+    }
+
+    fn check_pcm_format(&self) -> Result<(), WebsocketSynthesizerError> {
+        if self.audio_format.pcm_byte_rate().is_none() {
+            return Err(WebsocketSynthesizerError {
+                kind: WebsocketSynthesizerErrorKind::InvalidRequest,
+                source: Some(
+                    AudioDataError {
+                        kind: AudioDataErrorKind::UnsupportedFormat(self.audio_format),
+                    }
+                    .into(),
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Synthesize the given text and split the audio into one segment per sentence.
+    /// This is a convenience method that interpolates the SSML for you.
+    ///
+    /// See [`Self::synthesize_ssml_by_sentence`] for details.
+    pub async fn synthesize_text_by_sentence(
+        &mut self,
+        text: impl AsRef<str>,
+        options: &TextOptions<'_>,
+    ) -> Result<Vec<(String, Vec<u8>)>, WebsocketSynthesizerError> {
+        debug!("Synthesizing text: {}", text.as_ref());
+        let ssml = interpolate_ssml(text, options)?;
+        self.synthesize_ssml_by_sentence(&ssml).await
+    }
+
+    /// Synthesize the given text and return everything needed to build an HTTP response:
+    /// the audio, its content type and the subtitles in `subtitle_format`(if requested).
+    ///
+    /// The subtitles are generated by the default [`SubtitleGenerator`].
+    pub async fn synthesize_text_for_http(
+        &mut self,
+        text: impl AsRef<str>,
+        options: &TextOptions<'_>,
+        subtitle_format: Option<SubtitleFormat>,
+    ) -> Result<(Vec<u8>, &'static str, Option<String>), WebsocketSynthesizerError> {
+        let audio = self.synthesize_text(text, options).await?;
+        let subtitles = match subtitle_format {
+            Some(format) => {
+                let metadata = self.audio_metadata.as_deref().unwrap_or_default();
+                Some(SubtitleGenerator::default().generate(
+                    &parse_word_boundaries(metadata)?,
+                    &parse_sentence_boundaries(metadata)?,
+                    format,
+                ))
+            }
+            None => None,
+        };
+        Ok((audio, self.audio_format.content_type(), subtitles))
     }
 
     /// Synthesize the given text into audio([`Vec<u8>`]).
@@ -103,12 +978,79 @@ pub struct WebsocketSynthesizerError {
 }
 
 impl WebsocketSynthesizerError {
+    pub(crate) fn deadline_exceeded() -> Self {
+        Self {
+            kind: WebsocketSynthesizerErrorKind::Timeout,
+            source: Some(anyhow::anyhow!("the deadline has passed")),
+        }
+    }
+
+    /// The delay the service asks for before connecting again after rate limiting, if any
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.kind {
+            WebsocketSynthesizerErrorKind::RateLimited { retry_after } => retry_after,
+            _ => None,
+        }
+    }
+
     fn connection_closed(code: String, reason: String) -> Self {
         Self {
             kind: WebsocketSynthesizerErrorKind::WebsocketConnectionClosed { code, reason },
             source: None,
         }
     }
+
+    /// Whether the connection was reset or closed without a closing handshake,
+    /// which is worth reconnecting and retrying, see [`ReconnectPolicy`].
+    ///
+    /// Errors that the service reports with a close frame(e.g. an invalid request) are not connection resets.
+    pub fn is_connection_reset(&self) -> bool {
+        use std::io::ErrorKind;
+        matches!(
+            self.kind,
+            WebsocketSynthesizerErrorKind::Connect
+                | WebsocketSynthesizerErrorKind::Websocket
+                | WebsocketSynthesizerErrorKind::Io
+        ) && self.source.as_ref().is_some_and(|source| {
+            source.chain().any(|e| {
+                if let Some(e) = e.downcast_ref::<tokio_tungstenite::tungstenite::Error>() {
+                    is_connection_reset(e)
+                } else if let Some(e) = e.downcast_ref::<std::io::Error>() {
+                    matches!(
+                        e.kind(),
+                        ErrorKind::ConnectionReset
+                            | ErrorKind::ConnectionAborted
+                            | ErrorKind::BrokenPipe
+                            | ErrorKind::UnexpectedEof
+                    )
+                } else {
+                    false
+                }
+            })
+        })
+    }
+
+    /// Whether the request failed because the requested voice is not available,
+    /// e.g. the voice does not exist or is not available in the region.
+    ///
+    /// The service closes the connection with a reason like `Unsupported voice xxx.` in this case.
+    /// Other errors that mention a voice, e.g. an unsupported style of the voice, are not matched.
+    pub fn is_voice_unavailable(&self) -> bool {
+        matches!(
+            &self.kind,
+            WebsocketSynthesizerErrorKind::WebsocketConnectionClosed { reason, .. }
+                if is_unsupported_voice_reason(reason)
+        )
+    }
+}
+
+/// Whether the reason of a close frame says that the voice is unsupported, e.g. `Unsupported voice en-US-FooNeural.`
+fn is_unsupported_voice_reason(reason: &str) -> bool {
+    const UNSUPPORTED_VOICE: &str = "unsupported voice";
+    reason
+        .trim_start()
+        .get(..UNSUPPORTED_VOICE.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(UNSUPPORTED_VOICE))
 }
 
 impl Display for WebsocketSynthesizerError {
@@ -123,6 +1065,13 @@ impl Display for WebsocketSynthesizerError {
                     code, reason
                 )
             }
+            RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(
+                f,
+                "the requests are rate limited, retry after {}s",
+                retry_after.as_secs()
+            ),
             InvalidMessage => write!(f, "aspeak cannot handle this message. Please report this bug to https://github.com/kxxt/aspeak/issues."),
             _ => write!(f, "{} error", self.kind.as_ref()),
         }
@@ -135,6 +1084,13 @@ impl Error for WebsocketSynthesizerError {
     }
 }
 
+/// Allows wrapping [`WebsocketSynthesizer::synthesize_ssml_stream`] with `tokio_util::io::StreamReader`
+impl From<WebsocketSynthesizerError> for std::io::Error {
+    fn from(value: WebsocketSynthesizerError) -> Self {
+        std::io::Error::other(value)
+    }
+}
+
 #[cfg(feature = "python")]
 impl From<WebsocketSynthesizerError> for pyo3::PyErr {
     fn from(value: WebsocketSynthesizerError) -> Self {
@@ -148,6 +1104,9 @@ impl From<WebsocketSynthesizerError> for pyo3::PyErr {
 pub enum WebsocketSynthesizerErrorKind {
     /// Failed to connect to the endpoint.
     Connect,
+    /// The service rejected the connection with 429 Too Many Requests,
+    /// optionally with the delay it asks for in the `Retry-After` header.
+    RateLimited { retry_after: Option<Duration> },
     /// The websocket connection was closed.
     WebsocketConnectionClosed { code: String, reason: String },
     /// Other websocket errors.
@@ -158,6 +1117,12 @@ pub enum WebsocketSynthesizerErrorKind {
     InvalidMessage,
     /// Errors that occur when interpolating SSML.
     Ssml,
+    /// Failed to write the audio into the writer.
+    Io,
+    /// The deadline passed before the operation finished.
+    Timeout,
+    /// The operation was cancelled by a [`CancellationToken`].
+    Cancelled,
 }
 
 macro_rules! impl_from_for_ws_synthesizer_error {
@@ -175,9 +1140,29 @@ macro_rules! impl_from_for_ws_synthesizer_error {
 
 impl_from_for_ws_synthesizer_error!(InvalidHeaderValue, InvalidRequest);
 impl_from_for_ws_synthesizer_error!(url::ParseError, InvalidRequest);
-impl_from_for_ws_synthesizer_error!(ConnectError, Connect);
+
+impl From<ConnectError> for WebsocketSynthesizerError {
+    fn from(e: ConnectError) -> Self {
+        let kind = match e.kind {
+            ConnectErrorKind::HandshakeRejected {
+                status: 429,
+                retry_after,
+                ..
+            } => WebsocketSynthesizerErrorKind::RateLimited { retry_after },
+            _ => WebsocketSynthesizerErrorKind::Connect,
+        };
+        Self {
+            kind,
+            source: Some(e.into()),
+        }
+    }
+}
+
 impl_from_for_ws_synthesizer_error!(tokio_tungstenite::tungstenite::Error, Websocket);
 impl_from_for_ws_synthesizer_error!(crate::ssml::SsmlError, Ssml);
+impl_from_for_ws_synthesizer_error!(MetadataError, InvalidMessage);
+impl_from_for_ws_synthesizer_error!(std::io::Error, Io);
+impl_from_for_ws_synthesizer_error!(crate::TokenProviderError, Connect);
 
 impl From<msg::ParseError> for WebsocketSynthesizerError {
     fn from(e: msg::ParseError) -> Self {
@@ -187,3 +1172,58 @@ impl From<msg::ParseError> for WebsocketSynthesizerError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_unavailable_voices_by_the_close_reason() {
+        let closed = |reason: &str| {
+            WebsocketSynthesizerError::connection_closed("1007".to_string(), reason.to_string())
+        };
+        for reason in [
+            "Unsupported voice en-US-FooNeural. websocket error code: 1007",
+            "unsupported voice xx-XX-BarNeural.",
+        ] {
+            assert!(closed(reason).is_voice_unavailable(), "{reason}");
+        }
+        for reason in [
+            "Voice en-US-JennyNeural does not support style angry.",
+            "Invalid SSML: the voice element is missing.",
+            "Internal server error",
+            "",
+        ] {
+            assert!(!closed(reason).is_voice_unavailable(), "{reason}");
+        }
+        let timeout = WebsocketSynthesizerError {
+            kind: WebsocketSynthesizerErrorKind::Timeout,
+            source: None,
+        };
+        assert!(!timeout.is_voice_unavailable());
+    }
+
+    #[test]
+    fn metadata_options_default_to_the_baseline() {
+        let options = MetadataOptions::default();
+        assert!(options.word_boundary());
+        assert!(!options.sentence_boundary());
+        assert_eq!(
+            serde_json::to_value(options).unwrap(),
+            serde_json::json!({
+                "wordBoundaryEnabled": true,
+                "sentenceBoundaryEnabled": false,
+                "sessionEndEnabled": false,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(options.with_sentence_boundary(true).with_viseme(true)).unwrap(),
+            serde_json::json!({
+                "wordBoundaryEnabled": true,
+                "sentenceBoundaryEnabled": true,
+                "sessionEndEnabled": false,
+                "visemeEnabled": true,
+            })
+        );
+    }
+}
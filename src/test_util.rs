@@ -0,0 +1,318 @@
+//! Utilities for testing code that uses the websocket synthesizer without hitting Azure.
+//!
+//! This module is only available with the `test-util` feature.
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, warn};
+use tokio::{net::TcpListener, sync::Notify, task::JoinHandle};
+use tokio_tungstenite::tungstenite::{
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
+
+#[derive(Debug, Clone, Default)]
+struct Script {
+    audio: Vec<Vec<u8>>,
+    metadata: Vec<String>,
+    close: Option<(u16, String)>,
+    close_mid_turn: Option<(u16, String)>,
+    resets: usize,
+    pause_after: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct Record {
+    connections: usize,
+    resets: usize,
+    contexts: Vec<String>,
+    ssml: Vec<String>,
+}
+
+/// A local websocket server that speaks the websocket protocol of Azure TTS.
+///
+/// It accepts the `speech.config`, `synthesis.context` and `ssml` messages and replies to every SSML
+/// with `turn.start`, `response`, the scripted `audio.metadata` and audio messages, and `turn.end`.
+/// Point the endpoint of the [`crate::AuthOptions`] to [`MockServer::endpoint`] to use it.
+/// Any key or token is accepted.
+///
+/// The server stops when it is dropped.
+///
+/// ```ignore
+/// let server = MockServer::builder()
+///     .audio_chunk(vec![0; 4800])
+///     .word_boundary("Hello", Duration::from_millis(50), Duration::from_millis(300))
+///     .start()
+///     .await?;
+/// let auth = AuthOptionsBuilder::new(server.endpoint()).build();
+/// let config = SynthesizerConfig::new(auth, AudioFormat::Raw24Khz16BitMonoPcm);
+/// let mut syn = config.connect_websocket().await?;
+/// assert_eq!(syn.synthesize_ssml(ssml).await?.len(), 4800);
+/// assert_eq!(server.received_ssml(), [ssml]);
+/// ```
+pub struct MockServer {
+    addr: SocketAddr,
+    record: Arc<Mutex<Record>>,
+    resume: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Create a builder for [`MockServer`]
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::new()
+    }
+
+    /// The address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The websocket endpoint of the server
+    pub fn endpoint(&self) -> String {
+        format!("ws://{}/cognitiveservices/websocket/v1", self.addr)
+    }
+
+    /// The number of websocket connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.record.lock().unwrap().connections
+    }
+
+    /// The SSML received so far, in order
+    pub fn received_ssml(&self) -> Vec<String> {
+        self.record.lock().unwrap().ssml.clone()
+    }
+
+    /// The bodies of the `synthesis.context` messages received so far, in order
+    pub fn received_synthesis_contexts(&self) -> Vec<String> {
+        self.record.lock().unwrap().contexts.clone()
+    }
+
+    /// Send the rest of the audio of a synthesis that is paused, see [`MockServerBuilder::pause_after_audio_chunks`].
+    /// If no synthesis is paused yet, the next one to pause is resumed right away.
+    pub fn resume_audio(&self) {
+        self.resume.notify_one();
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Builder for [`MockServer`]
+#[derive(Debug, Default)]
+pub struct MockServerBuilder {
+    script: Script,
+}
+
+impl MockServerBuilder {
+    /// Create a new builder
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Append a chunk of audio to send for every SSML. The audio is sent as is, in the order of the chunks.
+    pub fn audio_chunk(mut self, chunk: impl Into<Vec<u8>>) -> Self {
+        self.script.audio.push(chunk.into());
+        self
+    }
+
+    /// Append the body of an `audio.metadata` message to send for every SSML.
+    pub fn metadata(mut self, body: impl Into<String>) -> Self {
+        self.script.metadata.push(body.into());
+        self
+    }
+
+    /// Append an `audio.metadata` message that reports a word boundary.
+    pub fn word_boundary(self, text: &str, offset: Duration, duration: Duration) -> Self {
+        let ticks = |d: Duration| (d.as_nanos() / 100) as u64;
+        let body = serde_json::json!({
+            "Metadata": [{
+                "Type": "WordBoundary",
+                "Data": {
+                    "Offset": ticks(offset),
+                    "Duration": ticks(duration),
+                    "text": {
+                        "Text": text,
+                        "Length": text.chars().count(),
+                        "BoundaryType": "WordBoundary",
+                    },
+                },
+            }],
+        });
+        self.metadata(body.to_string())
+    }
+
+    /// Close the connection with the code and reason instead of synthesizing, to test error paths.
+    pub fn close_with(mut self, code: u16, reason: impl Into<String>) -> Self {
+        self.script.close = Some((code, reason.into()));
+        self
+    }
+
+    /// Close the connection with the code and reason after sending the metadata and audio but before `turn.end`,
+    /// like the service does when it fails in the middle of a synthesis.
+    pub fn close_mid_turn(mut self, code: u16, reason: impl Into<String>) -> Self {
+        self.script.close_mid_turn = Some((code, reason.into()));
+        self
+    }
+
+    /// Drop the connection without a closing handshake on receiving the first `resets` SSML,
+    /// like a flaky endpoint does, to test reconnecting.
+    pub fn reset_first(mut self, resets: usize) -> Self {
+        self.script.resets = resets;
+        self
+    }
+
+    /// Pause after sending the first `count` audio chunks of every SSML until [`MockServer::resume_audio`] is called,
+    /// like a slow synthesis, to test what the client does with the audio before the synthesis completes.
+    pub fn pause_after_audio_chunks(mut self, count: usize) -> Self {
+        self.script.pause_after = Some(count);
+        self
+    }
+
+    /// Start the server on a random local port.
+    pub async fn start(self) -> io::Result<MockServer> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let record = Arc::new(Mutex::new(Record::default()));
+        let resume = Arc::new(Notify::new());
+        let script = Arc::new(self.script);
+        let task = tokio::spawn({
+            let record = record.clone();
+            let resume = resume.clone();
+            async move {
+                while let Ok((stream, peer)) = listener.accept().await {
+                    debug!("Mock server accepted a connection from {peer}");
+                    let record = record.clone();
+                    let script = script.clone();
+                    let resume = resume.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve(stream, &script, &record, &resume).await {
+                            warn!("Mock server connection failed: {e}");
+                        }
+                    });
+                }
+            }
+        });
+        Ok(MockServer {
+            addr,
+            record,
+            resume,
+            task,
+        })
+    }
+}
+
+async fn serve(
+    stream: tokio::net::TcpStream,
+    script: &Script,
+    record: &Mutex<Record>,
+    resume: &Notify,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    let mut ws = tokio_tungstenite::accept_async(stream).await?;
+    record.lock().unwrap().connections += 1;
+    while let Some(msg) = ws.next().await {
+        let text = match msg? {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            // Pings are answered by tungstenite
+            _ => continue,
+        };
+        let Some((headers, body)) = text.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let request_id = header(headers, "X-RequestId").unwrap_or_default();
+        match header(headers, "Path") {
+            Some("ssml") => {}
+            Some("synthesis.context") => {
+                record.lock().unwrap().contexts.push(body.to_string());
+                continue;
+            }
+            _ => continue,
+        }
+        {
+            let mut record = record.lock().unwrap();
+            record.ssml.push(body.to_string());
+            if record.resets < script.resets {
+                record.resets += 1;
+                debug!("Mock server drops the connection");
+                return Ok(());
+            }
+        }
+        if let Some((code, reason)) = script.close.as_ref() {
+            ws.close(Some(CloseFrame {
+                code: CloseCode::from(*code),
+                reason: reason.clone().into(),
+            }))
+            .await?;
+            break;
+        }
+        let text_message =
+            |path: &str, body: &str| Message::Text(text_frame(request_id, path, body));
+        ws.send(text_message(
+            "turn.start",
+            r#"{"context":{"serviceTag":"mock"}}"#,
+        ))
+        .await?;
+        ws.send(text_message(
+            "response",
+            r#"{"context":{"serviceTag":"mock"},"audio":{"type":"inline","streamId":"mock"}}"#,
+        ))
+        .await?;
+        for body in script.metadata.iter() {
+            ws.send(text_message("audio.metadata", body)).await?;
+        }
+        for (index, chunk) in script.audio.iter().enumerate() {
+            if script.pause_after == Some(index) {
+                debug!("Mock server pauses after {index} audio chunks");
+                resume.notified().await;
+            }
+            ws.send(Message::Binary(audio_frame(request_id, chunk)))
+                .await?;
+        }
+        if let Some((code, reason)) = script.close_mid_turn.as_ref() {
+            ws.close(Some(CloseFrame {
+                code: CloseCode::from(*code),
+                reason: reason.clone().into(),
+            }))
+            .await?;
+            break;
+        }
+        ws.send(text_message(
+            "turn.end",
+            r#"{"context":{"serviceTag":"mock"}}"#,
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.split("\r\n").find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        (k.trim() == name).then(|| v.trim())
+    })
+}
+
+fn text_frame(request_id: &str, path: &str, body: &str) -> String {
+    format!(
+        "X-RequestId:{request_id}\r\nContent-Type:application/json; charset=utf-8\r\nPath:{path}\r\n\r\n{body}"
+    )
+}
+
+fn audio_frame(request_id: &str, chunk: &[u8]) -> Vec<u8> {
+    let header = format!("X-RequestId:{request_id}\r\nContent-Type:audio/x-wav\r\nPath:audio");
+    let mut frame = Vec::with_capacity(2 + header.len() + chunk.len());
+    frame.extend_from_slice(&(header.len() as u16).to_be_bytes());
+    frame.extend_from_slice(header.as_bytes());
+    frame.extend_from_slice(chunk);
+    frame
+}
@@ -0,0 +1,186 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use hyper::http::HeaderValue;
+use log::debug;
+use strum::AsRefStr;
+
+/// A source of fresh authentication tokens, e.g. short-lived Azure AD or STS tokens.
+///
+/// The token is used exactly like [`crate::AuthOptions::token`], so it should include the scheme,
+/// e.g. `Bearer <token>`. The synthesizers ask for a token every time they connect(or reconnect)
+/// for the websocket synthesizer and on every request for the RESTful synthesizer,
+/// so providers should cache the token while it is valid.
+///
+/// Use [`crate::synthesizer::SynthesizerConfig::with_token_provider`] to install a provider.
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// Get a token that is valid right now
+    async fn token(&self) -> Result<String, TokenProviderError>;
+}
+
+/// A shared [`TokenProvider`] in a [`crate::synthesizer::SynthesizerConfig`]
+#[derive(Clone)]
+pub(crate) struct SharedTokenProvider(pub(crate) std::sync::Arc<dyn TokenProvider>);
+
+impl Debug for SharedTokenProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenProvider")
+    }
+}
+
+/// How long an STS token is valid, see [`SubscriptionKeyTokenProvider`]
+const STS_TOKEN_LIFETIME: Duration = Duration::from_secs(10 * 60);
+/// Fetch a new token a bit before the old one expires
+const STS_TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// A [`TokenProvider`] that exchanges a subscription key for a token at the `issueToken` endpoint of the STS service.
+///
+/// The token is valid for 10 minutes and is cached for 9 minutes.
+pub struct SubscriptionKeyTokenProvider {
+    url: String,
+    key: String,
+    proxy: Option<String>,
+    cache: Mutex<Option<(String, Instant)>>,
+}
+
+impl SubscriptionKeyTokenProvider {
+    /// Exchange the key for tokens at the `issueToken` endpoint `url`
+    pub fn new(url: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            key: key.into(),
+            proxy: None,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Exchange the key for tokens at the `issueToken` endpoint of the region(e.g. `eastus`)
+    pub fn from_region(region: &str, key: impl Into<String>) -> Self {
+        Self::new(get_issue_token_endpoint_by_region(region), key)
+    }
+
+    /// Proxy server to use. Only http and socks5 proxy are supported by now.
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    fn cached(&self) -> Option<String> {
+        let cache = self.cache.lock().unwrap();
+        let (token, fetched_at) = cache.as_ref()?;
+        (fetched_at.elapsed() < STS_TOKEN_LIFETIME - STS_TOKEN_REFRESH_MARGIN)
+            .then(|| token.clone())
+    }
+
+    async fn issue_token(&self) -> Result<String, TokenProviderError> {
+        let mut client = reqwest::ClientBuilder::new().no_proxy();
+        if let Some(proxy) = self.proxy.as_deref() {
+            client = client.proxy(reqwest::Proxy::all(proxy).map_err(|e| TokenProviderError {
+                kind: TokenProviderErrorKind::Request,
+                source: Some(e.into()),
+            })?);
+        }
+        let request_error = |e: reqwest::Error| TokenProviderError {
+            kind: TokenProviderErrorKind::Request,
+            source: Some(e.into()),
+        };
+        let client = client.build().map_err(request_error)?;
+        let key = HeaderValue::from_str(&self.key).map_err(|e| TokenProviderError {
+            kind: TokenProviderErrorKind::Request,
+            source: Some(e.into()),
+        })?;
+        let response = client
+            .post(&self.url)
+            .header("Ocp-Apim-Subscription-Key", key)
+            .header(reqwest::header::CONTENT_LENGTH, 0)
+            .send()
+            .await
+            .map_err(request_error)?
+            .error_for_status()
+            .map_err(|e| TokenProviderError {
+                kind: TokenProviderErrorKind::Response,
+                source: Some(e.into()),
+            })?;
+        let token = response.text().await.map_err(request_error)?;
+        Ok(format!("Bearer {}", token.trim()))
+    }
+}
+
+#[async_trait]
+impl TokenProvider for SubscriptionKeyTokenProvider {
+    async fn token(&self) -> Result<String, TokenProviderError> {
+        if let Some(token) = self.cached() {
+            return Ok(token);
+        }
+        let token = self.issue_token().await?;
+        debug!("Issued a new token from {}", self.url);
+        *self.cache.lock().unwrap() = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+}
+
+impl Debug for SubscriptionKeyTokenProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubscriptionKeyTokenProvider")
+            .field("url", &self.url)
+            .field("proxy", &self.proxy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Get the official `issueToken` endpoint of the STS service by its region (e.g. `eastus`)
+pub fn get_issue_token_endpoint_by_region(region: &str) -> String {
+    format!("https://{region}.api.cognitive.microsoft.com/sts/v1.0/issueToken")
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+/// Errors that can occur while getting a token from a [`TokenProvider`]
+pub struct TokenProviderError {
+    pub kind: TokenProviderErrorKind,
+    pub(crate) source: Option<anyhow::Error>,
+}
+
+impl TokenProviderError {
+    /// An error from a custom [`TokenProvider`]
+    pub fn other(source: impl Into<anyhow::Error>) -> Self {
+        Self {
+            kind: TokenProviderErrorKind::Other,
+            source: Some(source.into()),
+        }
+    }
+}
+
+impl Display for TokenProviderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "token provider: {} error while getting a token",
+            self.kind.as_ref()
+        )
+    }
+}
+
+impl Error for TokenProviderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|e| e.as_ref() as _)
+    }
+}
+
+#[derive(Debug, AsRefStr, PartialEq, Clone)]
+#[strum(serialize_all = "title_case")]
+#[non_exhaustive]
+pub enum TokenProviderErrorKind {
+    /// Failed to construct or send the request
+    Request,
+    /// The token service responded with an unsuccessful status, e.g. because the key is invalid
+    Response,
+    /// Errors from custom token providers
+    Other,
+}
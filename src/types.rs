@@ -1,9 +1,88 @@
 use std::borrow::Cow;
 
 use serde::Deserialize;
-use strum::IntoStaticStr;
+use strum::{Display, EnumIter, EnumString, IntoStaticStr};
+
+use crate::{
+    get_default_voice_by_locale,
+    parse::{parse_pitch, parse_rate, validate_style_degree},
+    BackgroundAudio, EmphasisLevel, Lexicon, ParseError, SsmlTemplate,
+};
+
+/// Regions of the Azure Speech Service, to avoid typing endpoint urls by hand.
+///
+/// It converts from and into the region names like `eastus` used in endpoint urls.
+/// New regions may not be listed here, use [`crate::get_websocket_endpoint_by_region`] etc. for them.
+#[non_exhaustive]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    IntoStaticStr,
+    EnumIter,
+    EnumString,
+    Display,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    SouthAfricaNorth,
+    EastAsia,
+    SoutheastAsia,
+    AustraliaEast,
+    CentralIndia,
+    JapanEast,
+    JapanWest,
+    KoreaCentral,
+    CanadaCentral,
+    NorthEurope,
+    WestEurope,
+    FranceCentral,
+    GermanyWestCentral,
+    NorwayEast,
+    SwedenCentral,
+    SwitzerlandNorth,
+    SwitzerlandWest,
+    UkSouth,
+    UaeNorth,
+    BrazilSouth,
+    QatarCentral,
+    CentralUs,
+    EastUs,
+    EastUs2,
+    NorthCentralUs,
+    SouthCentralUs,
+    WestCentralUs,
+    WestUs,
+    WestUs2,
+    WestUs3,
+}
+
+impl Region {
+    /// The region name used in endpoint urls, e.g. `eastus`
+    pub fn as_str(&self) -> &'static str {
+        self.into()
+    }
+
+    /// The websocket endpoint of the region
+    pub fn websocket_endpoint(&self) -> String {
+        crate::get_websocket_endpoint_by_region(self.as_str())
+    }
 
-use crate::get_default_voice_by_locale;
+    /// The REST endpoint of the region
+    pub fn rest_endpoint(&self) -> String {
+        crate::get_rest_endpoint_by_region(self.as_str())
+    }
+
+    /// The voice list API endpoint of the region
+    pub fn voice_list_endpoint(&self) -> crate::VoiceListAPIEndpoint<'static> {
+        crate::VoiceListAPIEndpoint::Region(self.as_str())
+    }
+}
 
 /// Speech role
 #[cfg_attr(feature = "python", pyo3::pyclass)]
@@ -13,7 +92,7 @@ use crate::get_default_voice_by_locale;
     derive(clap::ValueEnum),
     clap(rename_all = "verbatim")
 )]
-#[derive(Debug, Clone, Copy, IntoStaticStr, Deserialize)]
+#[derive(Debug, Clone, Copy, IntoStaticStr, EnumIter, Deserialize)]
 pub enum Role {
     Girl,
     Boy,
@@ -25,6 +104,20 @@ pub enum Role {
     SeniorMale,
 }
 
+/// The type of visemes to request, see [`TextOptions::viseme_type`].
+///
+/// Without a viseme type, the service only reports the viseme ids.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoStaticStr, EnumIter)]
+pub enum VisemeType {
+    /// SVG animations of the lips, reported as [animation data](crate::SynthesisEvent::Viseme)
+    #[strum(serialize = "redlips_front")]
+    RedlipsFront,
+    /// Blend shapes of the face for 3D characters, reported as [animation data](crate::SynthesisEvent::Viseme)
+    #[strum(serialize = "FacialExpression")]
+    FacialExpression,
+}
+
 /// Options that are only available if rich ssml is enabled
 #[derive(Debug, Clone, Default)]
 pub struct RichSsmlOptions<'a> {
@@ -138,6 +231,20 @@ pub struct TextOptions<'a> {
     pub(crate) rate: Option<Cow<'a, str>>,
     /// Rich SSML options
     pub(crate) rich_ssml_options: Option<RichSsmlOptions<'a>>,
+    /// Language of the text(`xml:lang`), defaults to the locale of the voice
+    pub(crate) lang: Option<Cow<'a, str>>,
+    /// Template of the SSML, overrides the built-in structure
+    pub(crate) ssml_template: Option<SsmlTemplate>,
+    /// Type of the visemes to request(`<mstts:viseme>`)
+    pub(crate) viseme_type: Option<VisemeType>,
+    /// Pronunciations of words in the text
+    pub(crate) lexicon: Option<Lexicon>,
+    /// URL of a pronunciation lexicon(PLS) for the service to apply(`<lexicon>`)
+    pub(crate) lexicon_url: Option<Cow<'a, str>>,
+    /// Level of emphasis of the text(`<emphasis>`)
+    pub(crate) emphasis: Option<EmphasisLevel>,
+    /// Audio that plays under the speech(`<mstts:backgroundaudio>`)
+    pub(crate) background_audio: Option<BackgroundAudio<'a>>,
 }
 
 impl Default for TextOptions<'_> {
@@ -147,6 +254,13 @@ impl Default for TextOptions<'_> {
             pitch: Default::default(),
             rate: Default::default(),
             rich_ssml_options: Default::default(),
+            lang: Default::default(),
+            ssml_template: Default::default(),
+            viseme_type: Default::default(),
+            lexicon: Default::default(),
+            lexicon_url: Default::default(),
+            emphasis: Default::default(),
+            background_audio: Default::default(),
         }
     }
 }
@@ -183,7 +297,7 @@ impl<'a> TextOptions<'a> {
     }
 
     /// Rich SSML options
-    pub fn rich_ssml_options(&self) -> &Option<RichSsmlOptions> {
+    pub fn rich_ssml_options(&self) -> &Option<RichSsmlOptions<'a>> {
         &self.rich_ssml_options
     }
 
@@ -192,6 +306,86 @@ impl<'a> TextOptions<'a> {
         &mut self.rich_ssml_options
     }
 
+    /// Language of the text(`xml:lang`), defaults to the locale of the voice
+    pub fn lang(&self) -> Option<&str> {
+        self.lang.as_deref()
+    }
+
+    /// Language of the text(`xml:lang`), defaults to the locale of the voice
+    pub fn lang_mut(&mut self) -> &mut Option<Cow<'a, str>> {
+        &mut self.lang
+    }
+
+    /// Template of the SSML, overrides the built-in structure
+    pub fn ssml_template(&self) -> Option<&SsmlTemplate> {
+        self.ssml_template.as_ref()
+    }
+
+    /// Template of the SSML, overrides the built-in structure
+    pub fn ssml_template_mut(&mut self) -> &mut Option<SsmlTemplate> {
+        &mut self.ssml_template
+    }
+
+    /// Type of the visemes to request(`<mstts:viseme>`)
+    ///
+    /// Visemes are only reported if they are enabled in the metadata options of the synthesizer.
+    /// An [`SsmlTemplate`] has to include the `<mstts:viseme>` element itself.
+    pub fn viseme_type(&self) -> Option<VisemeType> {
+        self.viseme_type
+    }
+
+    /// Type of the visemes to request(`<mstts:viseme>`)
+    pub fn viseme_type_mut(&mut self) -> &mut Option<VisemeType> {
+        &mut self.viseme_type
+    }
+
+    /// Pronunciations of words in the text, wrapped in `<phoneme>` elements.
+    ///
+    /// It is not applied to an [`SsmlTemplate`].
+    pub fn lexicon(&self) -> Option<&Lexicon> {
+        self.lexicon.as_ref()
+    }
+
+    /// Pronunciations of words in the text, wrapped in `<phoneme>` elements.
+    pub fn lexicon_mut(&mut self) -> &mut Option<Lexicon> {
+        &mut self.lexicon
+    }
+
+    /// URL of a pronunciation lexicon(PLS) for the service to apply(`<lexicon>`).
+    ///
+    /// It has to be a publicly accessible http(s) URL. An [`SsmlTemplate`] has to include the `<lexicon>` element itself.
+    pub fn lexicon_url(&self) -> Option<&str> {
+        self.lexicon_url.as_deref()
+    }
+
+    /// URL of a pronunciation lexicon(PLS) for the service to apply(`<lexicon>`)
+    pub fn lexicon_url_mut(&mut self) -> &mut Option<Cow<'a, str>> {
+        &mut self.lexicon_url
+    }
+
+    /// Level of emphasis of the text(`<emphasis>`), nested inside `<prosody>`
+    pub fn emphasis(&self) -> Option<EmphasisLevel> {
+        self.emphasis
+    }
+
+    /// Level of emphasis of the text(`<emphasis>`), nested inside `<prosody>`
+    pub fn emphasis_mut(&mut self) -> &mut Option<EmphasisLevel> {
+        &mut self.emphasis
+    }
+
+    /// Audio that plays under the speech(`<mstts:backgroundaudio>`).
+    ///
+    /// It applies to the whole document, so only the background audio of the first fragment is used
+    /// by [`crate::interpolate_ssml_fragments`]. An [`SsmlTemplate`] has to include the element itself.
+    pub fn background_audio(&self) -> Option<&BackgroundAudio<'a>> {
+        self.background_audio.as_ref()
+    }
+
+    /// Audio that plays under the speech(`<mstts:backgroundaudio>`)
+    pub fn background_audio_mut(&mut self) -> &mut Option<BackgroundAudio<'a>> {
+        &mut self.background_audio
+    }
+
     /// Create a builder for [`TextOptions`]
     pub fn builder() -> TextOptionsBuilder<'a> {
         TextOptionsBuilder::new()
@@ -205,6 +399,13 @@ pub struct TextOptionsBuilder<'a> {
     pitch: Option<Cow<'a, str>>,
     rate: Option<Cow<'a, str>>,
     rich_ssml_options: Option<RichSsmlOptions<'a>>,
+    lang: Option<Cow<'a, str>>,
+    ssml_template: Option<SsmlTemplate>,
+    viseme_type: Option<VisemeType>,
+    lexicon: Option<Lexicon>,
+    lexicon_url: Option<Cow<'a, str>>,
+    emphasis: Option<EmphasisLevel>,
+    background_audio: Option<BackgroundAudio<'a>>,
 }
 
 impl<'a> TextOptionsBuilder<'a> {
@@ -264,6 +465,141 @@ impl<'a> TextOptionsBuilder<'a> {
         self
     }
 
+    /// Language of the text(`xml:lang`), defaults to the locale of the voice
+    pub fn lang(mut self, lang: impl Into<Cow<'a, str>>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Language of the text(`xml:lang`), defaults to the locale of the voice
+    pub fn optional_lang(mut self, lang: Option<impl Into<Cow<'a, str>>>) -> Self {
+        self.lang = lang.map(|l| l.into());
+        self
+    }
+
+    /// Template of the SSML, overrides the built-in structure
+    pub fn ssml_template(mut self, ssml_template: SsmlTemplate) -> Self {
+        self.ssml_template = Some(ssml_template);
+        self
+    }
+
+    /// Template of the SSML, overrides the built-in structure
+    pub fn optional_ssml_template(mut self, ssml_template: Option<SsmlTemplate>) -> Self {
+        self.ssml_template = ssml_template;
+        self
+    }
+
+    /// Type of the visemes to request(`<mstts:viseme>`)
+    pub fn viseme_type(mut self, viseme_type: VisemeType) -> Self {
+        self.viseme_type = Some(viseme_type);
+        self
+    }
+
+    /// Type of the visemes to request(`<mstts:viseme>`)
+    pub fn optional_viseme_type(mut self, viseme_type: Option<VisemeType>) -> Self {
+        self.viseme_type = viseme_type;
+        self
+    }
+
+    /// Pronunciations of words in the text, wrapped in `<phoneme>` elements.
+    pub fn lexicon(mut self, lexicon: Lexicon) -> Self {
+        self.lexicon = Some(lexicon);
+        self
+    }
+
+    /// Pronunciations of words in the text, wrapped in `<phoneme>` elements.
+    pub fn optional_lexicon(mut self, lexicon: Option<Lexicon>) -> Self {
+        self.lexicon = lexicon;
+        self
+    }
+
+    /// URL of a pronunciation lexicon(PLS) for the service to apply(`<lexicon>`)
+    pub fn lexicon_url(mut self, lexicon_url: impl Into<Cow<'a, str>>) -> Self {
+        self.lexicon_url = Some(lexicon_url.into());
+        self
+    }
+
+    /// URL of a pronunciation lexicon(PLS) for the service to apply(`<lexicon>`)
+    pub fn optional_lexicon_url(mut self, lexicon_url: Option<impl Into<Cow<'a, str>>>) -> Self {
+        self.lexicon_url = lexicon_url.map(|url| url.into());
+        self
+    }
+
+    /// Level of emphasis of the text(`<emphasis>`)
+    pub fn emphasis(mut self, emphasis: EmphasisLevel) -> Self {
+        self.emphasis = Some(emphasis);
+        self
+    }
+
+    /// Level of emphasis of the text(`<emphasis>`)
+    pub fn optional_emphasis(mut self, emphasis: Option<EmphasisLevel>) -> Self {
+        self.emphasis = emphasis;
+        self
+    }
+
+    /// Audio that plays under the speech(`<mstts:backgroundaudio>`)
+    pub fn background_audio(mut self, background_audio: BackgroundAudio<'a>) -> Self {
+        self.background_audio = Some(background_audio);
+        self
+    }
+
+    /// Audio that plays under the speech(`<mstts:backgroundaudio>`)
+    pub fn optional_background_audio(
+        mut self,
+        background_audio: Option<BackgroundAudio<'a>>,
+    ) -> Self {
+        self.background_audio = background_audio;
+        self
+    }
+
+    /// Speech style, which enables rich SSML options
+    pub fn style(mut self, style: impl Into<Cow<'a, str>>) -> Self {
+        self.rich_ssml_options
+            .get_or_insert_with(Default::default)
+            .style = Some(style.into());
+        self
+    }
+
+    /// Speech style, which enables rich SSML options if specified
+    pub fn optional_style(self, style: Option<impl Into<Cow<'a, str>>>) -> Self {
+        match style {
+            Some(style) => self.style(style),
+            None => self,
+        }
+    }
+
+    /// Speech role, which enables rich SSML options
+    pub fn role(mut self, role: Role) -> Self {
+        self.rich_ssml_options
+            .get_or_insert_with(Default::default)
+            .role = Some(role);
+        self
+    }
+
+    /// Speech role, which enables rich SSML options if specified
+    pub fn optional_role(self, role: Option<Role>) -> Self {
+        match role {
+            Some(role) => self.role(role),
+            None => self,
+        }
+    }
+
+    /// Speech style degree, which should be in range [0.01, 2] and enables rich SSML options
+    pub fn style_degree(mut self, style_degree: f32) -> Self {
+        self.rich_ssml_options
+            .get_or_insert_with(Default::default)
+            .style_degree = Some(style_degree);
+        self
+    }
+
+    /// Speech style degree, which should be in range [0.01, 2] and enables rich SSML options if specified
+    pub fn optional_style_degree(self, style_degree: Option<f32>) -> Self {
+        match style_degree {
+            Some(style_degree) => self.style_degree(style_degree),
+            None => self,
+        }
+    }
+
     /// Set the rich SSML options to the build result of a [`RichSsmlOptionsBuilder`].
     pub fn chain_rich_ssml_options_builder(
         mut self,
@@ -273,7 +609,34 @@ impl<'a> TextOptionsBuilder<'a> {
         self
     }
 
-    /// Build the [`TextOptions`] from the builder
+    /// Validate the options and build the [`TextOptions`] from the builder.
+    ///
+    /// Pitch and rate are normalized the same way as the CLI does, e.g. `0.5` becomes `50.00%`.
+    /// A style degree out of range [0.01, 2] is rejected.
+    pub fn try_build(mut self) -> Result<TextOptions<'a>, ParseError> {
+        self.pitch = self
+            .pitch
+            .map(|pitch| normalize(pitch, parse_pitch))
+            .transpose()?;
+        self.rate = self
+            .rate
+            .map(|rate| normalize(rate, parse_rate))
+            .transpose()?;
+        if let Some(degree) = self
+            .rich_ssml_options
+            .as_ref()
+            .and_then(|rich| rich.style_degree)
+        {
+            if !validate_style_degree(degree) {
+                return Err(ParseError::new(format!(
+                    "Invalid style degree value {degree}! out of range [0.01, 2]"
+                )));
+            }
+        }
+        Ok(self.build())
+    }
+
+    /// Build the [`TextOptions`] from the builder without validation, see [`Self::try_build`]
     pub fn build(self) -> TextOptions<'a> {
         TextOptions {
             voice: self.voice.unwrap_or_else(|| {
@@ -284,10 +647,29 @@ impl<'a> TextOptionsBuilder<'a> {
             pitch: self.pitch,
             rate: self.rate,
             rich_ssml_options: self.rich_ssml_options,
+            lang: self.lang,
+            ssml_template: self.ssml_template,
+            viseme_type: self.viseme_type,
+            lexicon: self.lexicon,
+            lexicon_url: self.lexicon_url,
+            emphasis: self.emphasis,
+            background_audio: self.background_audio,
         }
     }
 }
 
+/// Normalize a value with a parser, keeping the original if it is unchanged
+fn normalize<'a>(
+    value: Cow<'a, str>,
+    parse: impl for<'b> Fn(&'b str) -> Result<Cow<'b, str>, ParseError>,
+) -> Result<Cow<'a, str>, ParseError> {
+    let normalized = match parse(&value)? {
+        Cow::Borrowed(parsed) if parsed == value => None,
+        parsed => Some(parsed.into_owned()),
+    };
+    Ok(normalized.map(Cow::Owned).unwrap_or(value))
+}
+
 #[cfg(feature = "python")]
 pub(crate) fn register_python_items(
     _py: pyo3::Python<'_>,
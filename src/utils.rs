@@ -1,7 +1,10 @@
+use std::time::Duration;
+
 use reqwest::{ClientBuilder, Proxy};
 
 pub(crate) trait ClientBuilderExt {
     fn optional_proxy(self, proxy: Option<Proxy>) -> Self;
+    fn optional_connect_timeout(self, timeout: Option<Duration>) -> Self;
 }
 
 impl ClientBuilderExt for ClientBuilder {
@@ -12,6 +15,31 @@ impl ClientBuilderExt for ClientBuilder {
             self
         }
     }
+
+    fn optional_connect_timeout(self, timeout: Option<Duration>) -> Self {
+        if let Some(timeout) = timeout {
+            self.connect_timeout(timeout)
+        } else {
+            self
+        }
+    }
+}
+
+/// The Levenshtein distance between two strings, i.e. the number of characters to insert, delete or substitute
+/// to turn one into the other
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
 }
 
 #[cfg(feature = "rest-synthesizer")]
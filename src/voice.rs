@@ -6,10 +6,11 @@ use std::{
 
 use crate::{
     constants::{ORIGIN, TRIAL_VOICE_LIST_URL},
-    AudioFormat,
+    AudioFormat, ParseError, Role, TextOptions,
 };
 use colored::Colorize;
 use hyper::{header::InvalidHeaderValue, http::HeaderValue};
+use log::warn;
 use serde::{Deserialize, Serialize};
 use strum::AsRefStr;
 
@@ -32,6 +33,29 @@ pub struct Voice {
     words_per_minute: Option<String>,
     style_list: Option<Vec<String>>,
     role_play_list: Option<Vec<String>>,
+    /// The other locales a multilingual voice speaks
+    secondary_locale_list: Option<Vec<String>>,
+    voice_tag: Option<VoiceTag>,
+}
+
+/// Tags that describe what a [`Voice`] is good at
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VoiceTag {
+    tailored_scenarios: Option<Vec<String>>,
+    voice_personalities: Option<Vec<String>>,
+}
+
+impl VoiceTag {
+    /// The scenarios the voice is tailored for, e.g. `Chat` or `Narration`
+    pub fn tailored_scenarios(&self) -> Option<&[String]> {
+        self.tailored_scenarios.as_deref()
+    }
+
+    /// The personalities of the voice, e.g. `Warm` or `Confident`
+    pub fn voice_personalities(&self) -> Option<&[String]> {
+        self.voice_personalities.as_deref()
+    }
 }
 
 #[non_exhaustive]
@@ -78,6 +102,8 @@ impl Voice {
         additional_headers: Option<reqwest::header::HeaderMap>,
     ) -> Result<Vec<Self>, VoiceListAPIError> {
         let url = endpoint.get_endpoint_url();
+        // The voice list is large, reqwest asks for a compressed response with `Accept-Encoding: gzip, deflate`
+        // and decompresses it transparently.
         let mut client = reqwest::ClientBuilder::new().no_proxy(); // Disable default system proxy detection.
         if let Some(proxy) = proxy {
             client = client.proxy(reqwest::Proxy::all(proxy).map_err(|e| VoiceListAPIError {
@@ -109,8 +135,8 @@ impl Voice {
             }
             None => {}
         }
-        if additional_headers.is_some() {
-            request = request.headers(additional_headers.unwrap());
+        if let Some(additional_headers) = additional_headers {
+            request = request.headers(additional_headers);
         } else if Some(url.as_ref()) == TRIAL_VOICE_LIST_URL {
             // Trial endpoint
             request = request.header("Origin", HeaderValue::from_str(ORIGIN).unwrap());
@@ -192,6 +218,106 @@ impl Voice {
         self.role_play_list.as_deref()
     }
 
+    /// The other locales a multilingual voice speaks
+    pub fn secondary_locale_list(&self) -> Option<&[String]> {
+        self.secondary_locale_list.as_deref()
+    }
+
+    /// The tailored scenarios and personalities of the voice
+    pub fn voice_tag(&self) -> Option<&VoiceTag> {
+        self.voice_tag.as_ref()
+    }
+
+    /// Display all the details of the voice, including its secondary locales and [tags](VoiceTag),
+    /// while the [`Display`] implementation of the voice itself leaves them out for brevity.
+    pub fn verbose(&self) -> VerboseVoice<'_> {
+        VerboseVoice(self)
+    }
+
+    /// Whether the voice can switch languages with `<lang>` elements,
+    /// judging by its secondary locales or its name(e.g. `en-US-AvaMultilingualNeural`)
+    pub fn is_multilingual(&self) -> bool {
+        self.secondary_locale_list
+            .as_ref()
+            .is_some_and(|locales| !locales.is_empty())
+            || self.short_name.contains("Multilingual")
+    }
+
+    /// Whether the voice speaks the language(e.g. `zh-CN`), compared case-insensitively.
+    ///
+    /// Multilingual voices without a list of secondary locales are assumed to speak every language.
+    pub fn supports_lang(&self, lang: &str) -> bool {
+        if self.locale.eq_ignore_ascii_case(lang) {
+            return true;
+        }
+        match self.secondary_locale_list() {
+            Some(locales) if !locales.is_empty() => locales
+                .iter()
+                .any(|locale| locale.eq_ignore_ascii_case(lang)),
+            _ => self.is_multilingual(),
+        }
+    }
+
+    /// Whether the voice supports the speaking style, compared case-insensitively
+    pub fn supports_style(&self, style: &str) -> bool {
+        self.style_list
+            .iter()
+            .flatten()
+            .any(|s| s.eq_ignore_ascii_case(style))
+    }
+
+    /// Whether the voice supports the role-play role
+    pub fn supports_role(&self, role: Role) -> bool {
+        let role: &str = role.into();
+        self.role_play_list.iter().flatten().any(|r| r == role)
+    }
+
+    /// Check that the voice supports the style, style degree and role of the options,
+    /// which only some multi-style neural voices do, before the service rejects the request.
+    /// A warning is logged if the voice does not speak the [language](TextOptions::lang) of the options.
+    ///
+    /// The error names the styles or roles that the voice supports.
+    pub fn check_text_options(&self, options: &TextOptions) -> Result<(), ParseError> {
+        if let Some(lang) = options.lang().filter(|lang| !self.supports_lang(lang)) {
+            warn!(
+                "Voice {} does not speak {lang}, only multilingual voices can switch languages",
+                self.short_name
+            );
+        }
+        let Some(rich) = options.rich_ssml_options() else {
+            return Ok(());
+        };
+        let styles = || match self.style_list() {
+            Some(styles) if !styles.is_empty() => styles.join(", "),
+            _ => "none".to_string(),
+        };
+        if let Some(style) = rich.style().filter(|style| !self.supports_style(style)) {
+            return Err(ParseError::new(format!(
+                "Voice {} does not support style `{style}`. Available styles: {}",
+                self.short_name,
+                styles()
+            )));
+        }
+        if rich.style_degree().is_some() && self.style_list().unwrap_or_default().is_empty() {
+            return Err(ParseError::new(format!(
+                "Voice {} does not support styles, so style degree is not supported",
+                self.short_name
+            )));
+        }
+        if let Some(role) = rich.role().filter(|role| !self.supports_role(*role)) {
+            let roles = match self.role_play_list() {
+                Some(roles) if !roles.is_empty() => roles.join(", "),
+                _ => "none".to_string(),
+            };
+            return Err(ParseError::new(format!(
+                "Voice {} does not support role `{}`. Available roles: {roles}",
+                self.short_name,
+                Into::<&str>::into(role)
+            )));
+        }
+        Ok(())
+    }
+
     pub fn friendly_name(&self) -> Option<&str> {
         self.friendly_name.as_deref()
     }
@@ -212,6 +338,112 @@ impl Voice {
     }
 }
 
+/// Filter voices by locale, short name, gender or style, shared by the voice listing of the CLI and library users.
+///
+/// A voice passes the filter if it matches all of the criteria. An empty filter matches every voice.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VoiceFilter {
+    locales: Vec<String>,
+    short_name: Option<String>,
+    gender: Option<String>,
+    style: Option<String>,
+}
+
+impl VoiceFilter {
+    /// Create a filter that matches every voice
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match voices of any of the locales, e.g. `["en-US", "fr-FR"]`.
+    /// A locale may also be a comma-separated list like `en-US,fr-FR`,
+    /// or a prefix like `en` that matches all `en-*` locales. Locales are compared case-insensitively.
+    pub fn locales(mut self, locales: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.locales = locales
+            .into_iter()
+            .flat_map(|l| {
+                l.as_ref()
+                    .split(',')
+                    .map(|l| l.trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|l| !l.is_empty())
+            .collect();
+        self
+    }
+
+    /// Only match the voice with this short name, e.g. `en-US-JennyNeural`
+    pub fn short_name(mut self, short_name: impl Into<String>) -> Self {
+        self.short_name = Some(short_name.into());
+        self
+    }
+
+    /// Only match the voice with this short name if it is `Some`
+    pub fn optional_short_name(mut self, short_name: Option<impl Into<String>>) -> Self {
+        self.short_name = short_name.map(Into::into);
+        self
+    }
+
+    /// Only match voices of this gender, e.g. `Female`, compared case-insensitively
+    pub fn gender(mut self, gender: impl Into<String>) -> Self {
+        self.gender = Some(gender.into());
+        self
+    }
+
+    /// Only match voices of this gender if it is `Some`
+    pub fn optional_gender(mut self, gender: Option<impl Into<String>>) -> Self {
+        self.gender = gender.map(Into::into);
+        self
+    }
+
+    /// Only match voices that support this style, e.g. `cheerful`
+    pub fn style(mut self, style: impl Into<String>) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    /// Only match voices that support this style if it is `Some`
+    pub fn optional_style(mut self, style: Option<impl Into<String>>) -> Self {
+        self.style = style.map(Into::into);
+        self
+    }
+
+    fn matches_locale(filter: &str, locale: &str) -> bool {
+        // `en` matches `en-US` but not `eng-XX`
+        locale.eq_ignore_ascii_case(filter)
+            || (locale.len() > filter.len()
+                && locale.is_char_boundary(filter.len())
+                && locale[..filter.len()].eq_ignore_ascii_case(filter)
+                && locale[filter.len()..].starts_with('-'))
+    }
+
+    /// Whether the voice passes the filter
+    pub fn matches(&self, voice: &Voice) -> bool {
+        (self.locales.is_empty()
+            || self
+                .locales
+                .iter()
+                .any(|l| Self::matches_locale(l, voice.locale())))
+            && self
+                .short_name
+                .as_deref()
+                .is_none_or(|name| name == voice.short_name())
+            && self
+                .gender
+                .as_deref()
+                .is_none_or(|gender| gender.eq_ignore_ascii_case(voice.gender()))
+            && self
+                .style
+                .as_deref()
+                .is_none_or(|style| voice.supports_style(style))
+    }
+
+    /// Filter the voices, keeping their order
+    pub fn filter<'v>(&'v self, voices: &'v [Voice]) -> impl Iterator<Item = &'v Voice> + 'v {
+        voices.iter().filter(|voice| self.matches(voice))
+    }
+}
+
 impl Display for Voice {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "{}", self.name.bright_green())?;
@@ -247,6 +479,28 @@ impl Display for Voice {
     }
 }
 
+/// Display all the details of a [`Voice`], see [`Voice::verbose`]
+pub struct VerboseVoice<'a>(&'a Voice);
+
+impl Display for VerboseVoice<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)?;
+        if let Some(locales) = self.0.secondary_locale_list() {
+            writeln!(f, "Secondary locales: {locales:?}")?;
+        }
+        let Some(tag) = self.0.voice_tag() else {
+            return Ok(());
+        };
+        if let Some(scenarios) = tag.tailored_scenarios() {
+            writeln!(f, "Tailored scenarios: {scenarios:?}")?;
+        }
+        if let Some(personalities) = tag.voice_personalities() {
+            writeln!(f, "Personalities: {personalities:?}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct VoiceListAPIResponseStatusError {
@@ -312,3 +566,247 @@ pub enum VoiceListAPIErrorKind {
     /// A response was received from the voice list API, but it is not successful
     Response,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RichSsmlOptionsBuilder, TextOptionsBuilder};
+
+    fn voices() -> Vec<Voice> {
+        serde_json::from_value(serde_json::json!([
+            {
+                "Name": "Microsoft Server Speech Text to Speech Voice (en-US, JennyNeural)",
+                "ShortName": "en-US-JennyNeural",
+                "Gender": "Female",
+                "Locale": "en-US",
+                "Status": "GA",
+                "StyleList": ["cheerful", "sad"],
+                "VoiceTag": {
+                    "TailoredScenarios": ["Chat"],
+                    "VoicePersonalities": ["Warm", "Bright"]
+                }
+            },
+            {
+                "Name": "Microsoft Server Speech Text to Speech Voice (en-GB, RyanNeural)",
+                "ShortName": "en-GB-RyanNeural",
+                "Gender": "Male",
+                "Locale": "en-GB",
+                "Status": "GA"
+            },
+            {
+                "Name": "Microsoft Server Speech Text to Speech Voice (zh-CN, YunxiNeural)",
+                "ShortName": "zh-CN-YunxiNeural",
+                "Gender": "Male",
+                "Locale": "zh-CN",
+                "Status": "GA",
+                "StyleList": ["narration-relaxed", "Cheerful"],
+                "RolePlayList": ["Boy", "Narrator"],
+                "SecondaryLocaleList": ["en-US"]
+            },
+            {
+                "Name": "Microsoft Server Speech Text to Speech Voice (en-US, AvaMultilingualNeural)",
+                "ShortName": "en-US-AvaMultilingualNeural",
+                "Gender": "Female",
+                "Locale": "en-US",
+                "Status": "GA"
+            }
+        ]))
+        .unwrap()
+    }
+
+    fn voice(short_name: &str) -> Voice {
+        voices()
+            .into_iter()
+            .find(|voice| voice.short_name() == short_name)
+            .unwrap()
+    }
+
+    fn options_with(rich: RichSsmlOptionsBuilder<'static>) -> TextOptions<'static> {
+        TextOptionsBuilder::new()
+            .rich_ssml_options(rich.build())
+            .build()
+    }
+
+    #[test]
+    fn accepts_supported_styles_and_roles() {
+        let yunxi = voice("zh-CN-YunxiNeural");
+        let options = options_with(
+            RichSsmlOptionsBuilder::new()
+                .style("cheerful")
+                .style_degree(1.5)
+                .role(Role::Boy),
+        );
+        assert!(yunxi.check_text_options(&options).is_ok());
+        assert!(voice("en-GB-RyanNeural")
+            .check_text_options(&TextOptions::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_unsupported_styles_and_roles() {
+        let jenny = voice("en-US-JennyNeural");
+        let err = jenny
+            .check_text_options(&options_with(RichSsmlOptionsBuilder::new().style("angry")))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Available styles: cheerful, sad"),
+            "{err}"
+        );
+        let err = jenny
+            .check_text_options(&options_with(
+                RichSsmlOptionsBuilder::new().role(Role::Girl),
+            ))
+            .unwrap_err();
+        assert!(err.to_string().contains("Available roles: none"), "{err}");
+        let err = voice("en-GB-RyanNeural")
+            .check_text_options(&options_with(
+                RichSsmlOptionsBuilder::new().style_degree(2.0),
+            ))
+            .unwrap_err();
+        assert!(err.to_string().contains("style degree"), "{err}");
+    }
+
+    #[test]
+    fn shows_secondary_locales_only_when_verbose() {
+        colored::control::set_override(false);
+        let yunxi = voice("zh-CN-YunxiNeural");
+        let brief = yunxi.to_string();
+        assert!(brief.contains("Roles: [\"Boy\", \"Narrator\"]"), "{brief}");
+        assert!(!brief.contains("Secondary locales"), "{brief}");
+        let verbose = yunxi.verbose().to_string();
+        assert!(verbose.starts_with(&brief), "{verbose}");
+        assert!(
+            verbose.ends_with("Secondary locales: [\"en-US\"]\n"),
+            "{verbose}"
+        );
+    }
+
+    #[test]
+    fn renders_every_detail_of_a_voice_list_entry_when_verbose() {
+        colored::control::set_override(false);
+        // An entry of the voice list API, as returned by the service
+        let voice: Voice = serde_json::from_str(
+            r#"{
+                "Name": "Microsoft Server Speech Text to Speech Voice (en-US, JennyNeural)",
+                "DisplayName": "Jenny",
+                "LocalName": "Jenny",
+                "ShortName": "en-US-JennyNeural",
+                "Gender": "Female",
+                "Locale": "en-US",
+                "LocaleName": "English (United States)",
+                "StyleList": ["assistant", "chat", "cheerful"],
+                "SampleRateHertz": "24000",
+                "VoiceType": "Neural",
+                "Status": "GA",
+                "VoiceTag": {
+                    "TailoredScenarios": ["Chat", "Assistant"],
+                    "VoicePersonalities": ["Warm", "Upbeat"]
+                },
+                "WordsPerMinute": "152"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            voice.verbose().to_string(),
+            "Microsoft Server Speech Text to Speech Voice (en-US, JennyNeural)\n\
+             Display name: Jenny\n\
+             Local name: Jenny\n\
+             Locale name: English (United States)\n\
+             Locale: en-US\n\
+             Gender: Female\n\
+             Short name: en-US-JennyNeural\n\
+             Voice type: Neural\n\
+             Status: GA\n\
+             Sample rate: 24000Hz\n\
+             Words per minute: 152\n\
+             Styles: [\"assistant\", \"chat\", \"cheerful\"]\n\
+             Tailored scenarios: [\"Chat\", \"Assistant\"]\n\
+             Personalities: [\"Warm\", \"Upbeat\"]\n"
+        );
+        // The tags are left out of the terse display
+        assert!(voice
+            .to_string()
+            .ends_with("Styles: [\"assistant\", \"chat\", \"cheerful\"]\n"));
+    }
+
+    #[test]
+    fn checks_languages_by_the_voice_metadata() {
+        let yunxi = voice("zh-CN-YunxiNeural");
+        assert!(yunxi.is_multilingual());
+        assert!(yunxi.supports_lang("ZH-cn"));
+        assert!(yunxi.supports_lang("en-US"));
+        assert!(!yunxi.supports_lang("fr-FR"));
+        let ava = voice("en-US-AvaMultilingualNeural");
+        assert!(ava.is_multilingual());
+        assert!(ava.supports_lang("fr-FR"));
+        let ryan = voice("en-GB-RyanNeural");
+        assert!(!ryan.is_multilingual());
+        assert!(!ryan.supports_lang("en-US"));
+    }
+
+    fn short_names(filter: &VoiceFilter) -> Vec<String> {
+        filter
+            .filter(&voices())
+            .map(|voice| voice.short_name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn filters_voices_by_gender_style_and_locale_prefix() {
+        assert_eq!(short_names(&VoiceFilter::new()).len(), 4);
+        assert_eq!(
+            short_names(&VoiceFilter::new().gender("male")),
+            ["en-GB-RyanNeural", "zh-CN-YunxiNeural"]
+        );
+        assert_eq!(
+            short_names(&VoiceFilter::new().style("CHEERFUL")),
+            ["en-US-JennyNeural", "zh-CN-YunxiNeural"]
+        );
+        assert_eq!(
+            short_names(&VoiceFilter::new().locales(["en"])),
+            [
+                "en-US-JennyNeural",
+                "en-GB-RyanNeural",
+                "en-US-AvaMultilingualNeural"
+            ]
+        );
+        // A prefix only matches whole subtags
+        assert!(short_names(&VoiceFilter::new().locales(["e", "en-U"])).is_empty());
+        assert_eq!(
+            short_names(&VoiceFilter::new().locales(["en-gb, zh"])),
+            ["en-GB-RyanNeural", "zh-CN-YunxiNeural"]
+        );
+    }
+
+    #[test]
+    fn combines_voice_filters() {
+        assert_eq!(
+            short_names(&VoiceFilter::new().locales(["en"]).gender("Female")),
+            ["en-US-JennyNeural", "en-US-AvaMultilingualNeural"]
+        );
+        assert_eq!(
+            short_names(
+                &VoiceFilter::new()
+                    .locales(["en"])
+                    .gender("Female")
+                    .style("cheerful")
+            ),
+            ["en-US-JennyNeural"]
+        );
+        assert!(short_names(
+            &VoiceFilter::new()
+                .locales(["en"])
+                .style("narration-relaxed")
+        )
+        .is_empty());
+        assert_eq!(
+            short_names(
+                &VoiceFilter::new()
+                    .optional_short_name(Some("zh-CN-YunxiNeural"))
+                    .optional_gender(None::<String>)
+                    .optional_style(Some("narration-relaxed"))
+            ),
+            ["zh-CN-YunxiNeural"]
+        );
+    }
+}
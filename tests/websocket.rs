@@ -0,0 +1,301 @@
+use std::{
+    error::Error,
+    io, iter,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use aspeak::{
+    parse_word_boundaries, test_util::MockServer, AudioFormat, AuthOptionsBuilder, ReconnectPolicy,
+    SynthesizerConfig, WebsocketSynthesizer, WebsocketSynthesizerErrorKind,
+};
+use tokio::io::{AsyncReadExt, AsyncWrite};
+use tokio_tungstenite::tungstenite::{self, error::ProtocolError};
+
+const SSML: &str = r#"<speak version="1.0" xmlns="http://www.w3.org/2001/10/synthesis" xml:lang="en-US"><voice name="en-US-JennyNeural">Hello world</voice></speak>"#;
+
+async fn connect(server: &MockServer) -> WebsocketSynthesizer {
+    let auth = AuthOptionsBuilder::new(server.endpoint()).build();
+    SynthesizerConfig::new(auth, AudioFormat::Raw24Khz16BitMonoPcm)
+        .connect_websocket()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn synthesizes_a_whole_turn() {
+    let server = MockServer::builder()
+        .word_boundary(
+            "Hello",
+            Duration::from_millis(50),
+            Duration::from_millis(300),
+        )
+        .word_boundary(
+            "world",
+            Duration::from_millis(400),
+            Duration::from_millis(350),
+        )
+        .audio_chunk(vec![1; 4800])
+        .audio_chunk(vec![2; 2400])
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    let audio = syn.synthesize_ssml(SSML).await.unwrap();
+    assert_eq!(audio.len(), 7200);
+    assert!(audio[..4800].iter().all(|&b| b == 1));
+    assert!(audio[4800..].iter().all(|&b| b == 2));
+    let metadata = syn.audio_metadata.take().unwrap();
+    let boundaries = parse_word_boundaries(&metadata).unwrap();
+    let words: Vec<_> = boundaries.iter().map(|b| b.text.as_str()).collect();
+    assert_eq!(words, ["Hello", "world"]);
+    assert_eq!(boundaries[1].offset, Duration::from_millis(400));
+    assert_eq!(boundaries[1].duration, Duration::from_millis(350));
+    assert_eq!(server.received_ssml(), [SSML]);
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn reports_a_close_in_the_middle_of_a_turn() {
+    let server = MockServer::builder()
+        .audio_chunk(vec![0; 4800])
+        .close_mid_turn(1011, "Internal server error")
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    let err = syn.synthesize_ssml(SSML).await.unwrap_err();
+    match &err.kind {
+        WebsocketSynthesizerErrorKind::WebsocketConnectionClosed { code, reason } => {
+            assert_eq!(code, "1011");
+            assert_eq!(reason, "Internal server error");
+        }
+        kind => panic!("unexpected error kind: {kind:?}"),
+    }
+    assert!(!err.is_connection_reset());
+    let stats = syn.close_stats();
+    assert_eq!(stats.last_code, Some(1011));
+    assert_eq!(stats.last_reason.as_deref(), Some("Internal server error"));
+}
+
+#[tokio::test]
+async fn reconnects_after_a_close_in_the_middle_of_a_turn() {
+    let server = MockServer::builder()
+        .audio_chunk(vec![0; 4800])
+        .close_mid_turn(1011, "Internal server error")
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    assert!(syn.synthesize_ssml(SSML).await.is_err());
+    // The interrupted connection is replaced before the next synthesis starts
+    assert!(syn.synthesize_ssml(SSML).await.is_err());
+    assert_eq!(server.connections(), 2);
+    assert_eq!(server.received_ssml().len(), 2);
+}
+
+#[tokio::test]
+async fn retries_after_a_reset_without_closing_handshake() {
+    let server = MockServer::builder()
+        .audio_chunk(vec![1; 4800])
+        .reset_first(2)
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    let err = syn.synthesize_ssml(SSML).await.unwrap_err();
+    assert!(err.is_connection_reset(), "{err:?}");
+    let reset = iter::successors(Some(&err as &dyn Error), |&e| e.source()).any(|e| {
+        matches!(
+            e.downcast_ref(),
+            Some(tungstenite::Error::Protocol(
+                ProtocolError::ResetWithoutClosingHandshake
+            ))
+        )
+    });
+    assert!(reset, "{err:?}");
+    // The second reset is retried on a new connection
+    let policy = ReconnectPolicy::new(3).with_initial_backoff(Duration::from_millis(10));
+    let audio = syn
+        .synthesize_ssml_with_reconnect(SSML, &policy)
+        .await
+        .unwrap();
+    assert_eq!(audio, vec![1; 4800]);
+    assert_eq!(server.connections(), 3);
+    assert_eq!(server.received_ssml(), [SSML; 3]);
+}
+
+#[tokio::test]
+async fn reports_a_close_instead_of_a_turn() {
+    let server = MockServer::builder()
+        .close_with(1007, "Unsupported voice en-US-FooNeural.")
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    let err = syn.synthesize_ssml(SSML).await.unwrap_err();
+    assert!(matches!(
+        err.kind,
+        WebsocketSynthesizerErrorKind::WebsocketConnectionClosed { .. }
+    ));
+    assert!(err.is_voice_unavailable());
+    assert_eq!(syn.close_stats().last_code, Some(1007));
+}
+
+#[tokio::test]
+async fn consecutive_turns_do_not_leak_into_each_other() {
+    let server = MockServer::builder()
+        .word_boundary(
+            "Hello",
+            Duration::from_millis(50),
+            Duration::from_millis(300),
+        )
+        .audio_chunk(vec![1; 4800])
+        .audio_chunk(vec![2; 2400])
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    let texts = ["One", "Two", "Three"];
+    for text in texts {
+        let ssml = SSML.replace("Hello world", text);
+        let audio = syn.synthesize_ssml(&ssml).await.unwrap();
+        assert_eq!(audio.len(), 7200, "audio of {text}");
+        assert!(audio[..4800].iter().all(|&b| b == 1));
+        assert!(audio[4800..].iter().all(|&b| b == 2));
+        // The metadata of the previous turn is replaced, not appended to
+        let metadata = syn.audio_metadata.as_deref().unwrap();
+        assert_eq!(parse_word_boundaries(metadata).unwrap().len(), 1);
+    }
+    let expected: Vec<_> = texts
+        .iter()
+        .map(|text| SSML.replace("Hello world", text))
+        .collect();
+    assert_eq!(server.received_ssml(), expected);
+    assert_eq!(server.connections(), 1);
+}
+
+/// A writer that records how many bytes were written at every flush
+#[derive(Default)]
+struct FlushRecorder {
+    written: usize,
+    flushes: Vec<usize>,
+}
+
+impl AsyncWrite for FlushRecorder {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.written += buf.len();
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let written = self.written;
+        self.flushes.push(written);
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn flushes_at_the_flush_interval() {
+    let server = MockServer::builder()
+        .audio_chunk(vec![0; 100])
+        .audio_chunk(vec![0; 200])
+        .audio_chunk(vec![0; 300])
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+
+    // By default every chunk is flushed as soon as it is written
+    assert_eq!(syn.flush_interval(), Duration::ZERO);
+    let mut writer = FlushRecorder::default();
+    syn.synthesize_ssml_to_writer(SSML, &mut writer)
+        .await
+        .unwrap();
+    assert_eq!(writer.flushes, [100, 300, 600, 600]);
+
+    // A long interval leaves only the flush at the end of the synthesis
+    syn.set_flush_interval(Duration::from_secs(3600));
+    let mut writer = FlushRecorder::default();
+    syn.synthesize_ssml_to_writer(SSML, &mut writer)
+        .await
+        .unwrap();
+    assert_eq!(writer.flushes, [600]);
+}
+
+#[tokio::test]
+async fn a_slow_reader_receives_the_first_chunk_before_the_synthesis_completes() {
+    let server = MockServer::builder()
+        .audio_chunk(vec![1; 4800])
+        .audio_chunk(vec![2; 2400])
+        .pause_after_audio_chunks(1)
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    // A pipe that holds much less than a chunk, so the synthesis waits for the reader
+    let (mut writer, mut reader) = tokio::io::duplex(256);
+    let synthesis = async move {
+        syn.synthesize_ssml_to_writer(SSML, &mut writer)
+            .await
+            .unwrap();
+        // Close the pipe
+        drop(writer);
+    };
+    let consumer = async {
+        let mut first = vec![0; 4800];
+        for part in first.chunks_mut(480) {
+            reader.read_exact(part).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert!(first.iter().all(|&b| b == 1));
+        // The rest of the audio is only sent after the first chunk is read
+        server.resume_audio();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, vec![2; 2400]);
+    };
+    // The synthesis would never complete if the first chunk were held back until the end
+    tokio::time::timeout(Duration::from_secs(10), async {
+        tokio::join!(synthesis, consumer)
+    })
+    .await
+    .expect("the first chunk is not received before the synthesis completes");
+}
+
+#[tokio::test]
+async fn a_cancelled_synthesis_by_sentence_keeps_the_metadata_options() {
+    let server = MockServer::builder()
+        .audio_chunk(vec![1; 4800])
+        .audio_chunk(vec![2; 2400])
+        .pause_after_audio_chunks(1)
+        .start()
+        .await
+        .unwrap();
+    let mut syn = connect(&server).await;
+    // Both syntheses are dropped while the server is paused in the middle of them
+    let timeout = Duration::from_millis(200);
+    let by_sentence = syn.synthesize_ssml_by_sentence(SSML);
+    assert!(tokio::time::timeout(timeout, by_sentence).await.is_err());
+    assert!(tokio::time::timeout(timeout, syn.synthesize_ssml(SSML))
+        .await
+        .is_err());
+    let sentence_boundaries: Vec<_> = server
+        .received_synthesis_contexts()
+        .iter()
+        .map(|context| {
+            let context: serde_json::Value = serde_json::from_str(context).unwrap();
+            context["synthesis"]["audio"]["metadataOptions"]["sentenceBoundaryEnabled"].clone()
+        })
+        .collect();
+    assert_eq!(sentence_boundaries, [true, false]);
+}